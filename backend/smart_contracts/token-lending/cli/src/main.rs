@@ -3,13 +3,18 @@ use {
         crate_description, crate_name, crate_version, value_t, App, AppSettings, Arg, ArgMatches,
         SubCommand,
     },
+    solana_account_decoder::UiAccountEncoding,
     solana_clap_utils::{
         fee_payer::fee_payer_arg,
         input_parsers::{keypair_of, pubkey_of, value_of},
         input_validators::{is_amount, is_keypair, is_parsable, is_pubkey, is_url},
         keypair::signer_from_path,
     },
-    solana_client::rpc_client::RpcClient,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    },
     solana_program::{native_token::lamports_to_sol, program_pack::Pack, pubkey::Pubkey},
     solana_sdk::{
         commitment_config::CommitmentConfig,
@@ -25,9 +30,19 @@ use {
     },
     spl_token_lending::{
         self,
-        instruction::{init_lending_market, init_reserve, update_reserve_config},
+        instruction::{
+            accept_lending_market_owner, apply_lending_market_emode_group, apply_reserve_config,
+            close_obligation, deprecate_reserve, init_lending_market, init_reserve,
+            propose_lending_market_emode_group, propose_lending_market_owner,
+            propose_reserve_config, refresh_obligation, set_lending_market_emode_group,
+            update_reserve_config,
+        },
         math::WAD,
-        state::{LendingMarket, Reserve, ReserveConfig, ReserveFees},
+        state::{
+            pending_emode_group_address, pending_reserve_config_address, EModeGroup, LendingMarket,
+            Obligation, PendingEModeGroup, PendingReserveConfig, Reserve, ReserveConfig,
+            ReserveFees, RESERVE_CONFIG_DELAY_SLOTS,
+        },
     },
     std::{borrow::Borrow, process::exit, str::FromStr},
     system_instruction::create_account,
@@ -66,6 +81,20 @@ struct PartialReserveConfig {
     pub borrow_limit: Option<u64>,
     /// Liquidity fee receiver
     pub fee_receiver: Option<Pubkey>,
+    /// Reserve can only be borrowed alone, never alongside other borrows
+    pub isolated: Option<bool>,
+    /// Reserve can only be borrowed, never used as collateral
+    pub borrow_only: Option<bool>,
+    /// Added borrow weight in basis points
+    pub added_borrow_weight_bps: Option<u64>,
+    /// Efficiency mode group of the lending market, 0 for none
+    pub emode_group: Option<u8>,
+    /// Max bonus a liquidator gets when repaying part of a very unhealthy obligation
+    pub max_liquidation_bonus: Option<u8>,
+    /// Premium added to the current borrow rate to price fixed term borrows
+    pub fixed_rate_premium: Option<u8>,
+    /// Max term of fixed term borrows in slots, 0 to disable them
+    pub max_fixed_term_slots: Option<u64>,
 }
 
 /// Reserve Fees with optional fields
@@ -296,6 +325,36 @@ fn main() {
                         .default_value("5")
                         .help("Bonus a liquidator gets when repaying part of an unhealthy obligation: [0, 100]"),
                 )
+                .arg(
+                    Arg::with_name("max_liquidation_bonus")
+                        .long("max-liquidation-bonus")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Max bonus a liquidator gets as an obligation grows more unhealthy, the liquidation bonus is fixed if lower: [0, 100]"),
+                )
+                .arg(
+                    Arg::with_name("fixed_rate_premium")
+                        .long("fixed-rate-premium")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Premium added to the current borrow rate to price fixed term borrows"),
+                )
+                .arg(
+                    Arg::with_name("max_fixed_term_slots")
+                        .long("max-fixed-term-slots")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Max term of fixed term borrows in slots, 0 to disable fixed term borrows: [0, 63072000]"),
+                )
                 .arg(
                     Arg::with_name("liquidation_threshold")
                         .long("liquidation-threshold")
@@ -386,6 +445,38 @@ fn main() {
                         .default_value("18446744073709551615")
                         .help("Borrow limit"),
                 )
+                .arg(
+                    Arg::with_name("added_borrow_weight_bps")
+                        .long("added-borrow-weight-bps")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_BPS")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Added borrow weight in basis points, borrows count as 1 + bps / 10000 times their value"),
+                )
+                .arg(
+                    Arg::with_name("emode_group")
+                        .long("emode-group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Efficiency mode group of the lending market, 0 for none"),
+                )
+                .arg(
+                    Arg::with_name("isolated")
+                        .long("isolated")
+                        .takes_value(false)
+                        .help("Reserve can only be borrowed alone, never alongside other borrows"),
+                )
+                .arg(
+                    Arg::with_name("borrow_only")
+                        .long("borrow-only")
+                        .takes_value(false)
+                        .help("Reserve can only be borrowed, never used as collateral"),
+                )
         )
         .subcommand(
             SubCommand::with_name("update-reserve")
@@ -445,6 +536,33 @@ fn main() {
                         .required(false)
                         .help("Bonus a liquidator gets when repaying part of an unhealthy obligation: [0, 100]"),
                 )
+                .arg(
+                    Arg::with_name("max_liquidation_bonus")
+                        .long("max-liquidation-bonus")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Max bonus a liquidator gets as an obligation grows more unhealthy, the liquidation bonus is fixed if lower: [0, 100]"),
+                )
+                .arg(
+                    Arg::with_name("fixed_rate_premium")
+                        .long("fixed-rate-premium")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Premium added to the current borrow rate to price fixed term borrows"),
+                )
+                .arg(
+                    Arg::with_name("max_fixed_term_slots")
+                        .long("max-fixed-term-slots")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Max term of fixed term borrows in slots, 0 to disable fixed term borrows: [0, 63072000]"),
+                )
                 .arg(
                     Arg::with_name("liquidation_threshold")
                         .long("liquidation-threshold")
@@ -535,6 +653,42 @@ fn main() {
                         .required(false)
                         .help("Fee receiver address"),
                 )
+                .arg(
+                    Arg::with_name("added_borrow_weight_bps")
+                        .long("added-borrow-weight-bps")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_BPS")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Added borrow weight in basis points, borrows count as 1 + bps / 10000 times their value"),
+                )
+                .arg(
+                    Arg::with_name("emode_group")
+                        .long("emode-group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Efficiency mode group of the lending market, 0 for none"),
+                )
+                .arg(
+                    Arg::with_name("isolated")
+                        .long("isolated")
+                        .validator(is_parsable::<bool>)
+                        .value_name("BOOL")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Reserve can only be borrowed alone, never alongside other borrows"),
+                )
+                .arg(
+                    Arg::with_name("borrow_only")
+                        .long("borrow-only")
+                        .validator(is_parsable::<bool>)
+                        .value_name("BOOL")
+                        .takes_value(true)
+                        .required(false)
+                        .help("Reserve can only be borrowed, never used as collateral"),
+                )
                 .arg(
                     Arg::with_name("pyth_product")
                         .long("pyth-product")
@@ -563,6 +717,209 @@ fn main() {
                         .help("Switchboard price feed account: https://switchboard.xyz/#/explorer"),
                 )
         )
+        .subcommand(
+            SubCommand::with_name("set-emode-group")
+                .about("Set the loan to value ratio and liquidation threshold of an efficiency mode group, proposing changes that add risk")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .long("market")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lending market address"),
+                )
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Efficiency mode group, starting from 1"),
+                )
+                .arg(
+                    Arg::with_name("loan_to_value_ratio")
+                        .long("loan-to-value-ratio")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Target ratio of the value of borrows to deposits within the group"),
+                )
+                .arg(
+                    Arg::with_name("liquidation_threshold")
+                        .long("liquidation-threshold")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Loan to value ratio at which obligations within the group can be liquidated, 0 disables the group"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("propose-market-owner")
+                .about("Propose a new lending market owner, which must accept ownership")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .long("market")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lending market address"),
+                )
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("new_owner")
+                        .long("new-owner")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Proposed owner of the lending market"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("accept-market-owner")
+                .about("Accept ownership of a lending market proposed with propose-market-owner")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .long("market")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lending market address"),
+                )
+                .arg(
+                    Arg::with_name("new_owner")
+                        .long("new-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Pending owner of the lending market"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("apply-reserve-config")
+                .about("Apply a proposed reserve config once its delay has passed")
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("apply-emode-group")
+                .about("Apply a proposed efficiency mode group once its delay has passed")
+                .arg(
+                    Arg::with_name("lending_market")
+                        .long("market")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lending market address"),
+                )
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Efficiency mode group, starting from 1"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("close-obligation")
+                .about("Close an empty obligation and reclaim its rent")
+                .arg(
+                    Arg::with_name("obligation")
+                        .long("obligation")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Obligation address"),
+                )
+                .arg(
+                    Arg::with_name("obligation_owner")
+                        .long("obligation-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the obligation, receives the obligation rent"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("deprecate-reserve")
+                .about("Deprecate a reserve, blocking deposits and borrows and decaying its loan to value ratio to zero")
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("ltv_decay_slots")
+                        .long("ltv-decay-slots")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Slots over which the loan to value ratio of deposits decays to zero"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("migration-report")
+                .about("Report the obligations that still hold deposits or borrows in a reserve")
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+        )
         .get_matches();
 
     let mut wallet_manager = None;
@@ -632,6 +989,9 @@ fn main() {
                 value_of(arg_matches, "optimal_utilization_rate").unwrap();
             let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio").unwrap();
             let liquidation_bonus = value_of(arg_matches, "liquidation_bonus").unwrap();
+            let max_liquidation_bonus = value_of(arg_matches, "max_liquidation_bonus").unwrap();
+            let fixed_rate_premium = value_of(arg_matches, "fixed_rate_premium").unwrap();
+            let max_fixed_term_slots = value_of(arg_matches, "max_fixed_term_slots").unwrap();
             let liquidation_threshold = value_of(arg_matches, "liquidation_threshold").unwrap();
             let min_borrow_rate = value_of(arg_matches, "min_borrow_rate").unwrap();
             let optimal_borrow_rate = value_of(arg_matches, "optimal_borrow_rate").unwrap();
@@ -641,6 +1001,11 @@ fn main() {
             let host_fee_percentage = value_of(arg_matches, "host_fee_percentage").unwrap();
             let deposit_limit = value_of(arg_matches, "deposit_limit").unwrap();
             let borrow_limit = value_of(arg_matches, "borrow_limit").unwrap();
+            let isolated = arg_matches.is_present("isolated");
+            let borrow_only = arg_matches.is_present("borrow_only");
+            let added_borrow_weight_bps =
+                value_of(arg_matches, "added_borrow_weight_bps").unwrap();
+            let emode_group = value_of(arg_matches, "emode_group").unwrap();
 
             let borrow_fee_wad = (borrow_fee * WAD as f64) as u64;
             let flash_loan_fee_wad = (flash_loan_fee * WAD as f64) as u64;
@@ -683,6 +1048,13 @@ fn main() {
                     deposit_limit,
                     borrow_limit,
                     fee_receiver: liquidity_fee_receiver_keypair.pubkey(),
+                    isolated,
+                    borrow_only,
+                    added_borrow_weight_bps,
+                    emode_group,
+                    max_liquidation_bonus,
+                    fixed_rate_premium,
+                    max_fixed_term_slots,
                 },
                 source_liquidity_pubkey,
                 source_liquidity_owner_keypair,
@@ -703,6 +1075,9 @@ fn main() {
             let optimal_utilization_rate = value_of(arg_matches, "optimal_utilization_rate");
             let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio");
             let liquidation_bonus = value_of(arg_matches, "liquidation_bonus");
+            let max_liquidation_bonus = value_of(arg_matches, "max_liquidation_bonus");
+            let fixed_rate_premium = value_of(arg_matches, "fixed_rate_premium");
+            let max_fixed_term_slots = value_of(arg_matches, "max_fixed_term_slots");
             let liquidation_threshold = value_of(arg_matches, "liquidation_threshold");
             let min_borrow_rate = value_of(arg_matches, "min_borrow_rate");
             let optimal_borrow_rate = value_of(arg_matches, "optimal_borrow_rate");
@@ -713,6 +1088,10 @@ fn main() {
             let deposit_limit = value_of(arg_matches, "deposit_limit");
             let borrow_limit = value_of(arg_matches, "borrow_limit");
             let fee_receiver = pubkey_of(arg_matches, "fee_receiver");
+            let isolated = value_of(arg_matches, "isolated");
+            let borrow_only = value_of(arg_matches, "borrow_only");
+            let added_borrow_weight_bps = value_of(arg_matches, "added_borrow_weight_bps");
+            let emode_group = value_of(arg_matches, "emode_group");
             let pyth_product_pubkey = pubkey_of(arg_matches, "pyth_product");
             let pyth_price_pubkey = pubkey_of(arg_matches, "pyth_price");
             let switchboard_feed_pubkey = pubkey_of(arg_matches, "switchboard_feed");
@@ -738,6 +1117,13 @@ fn main() {
                     deposit_limit,
                     borrow_limit,
                     fee_receiver,
+                    isolated,
+                    borrow_only,
+                    added_borrow_weight_bps,
+                    emode_group,
+                    max_liquidation_bonus,
+                    fixed_rate_premium,
+                    max_fixed_term_slots,
                 },
                 pyth_product_pubkey,
                 pyth_price_pubkey,
@@ -747,18 +1133,91 @@ fn main() {
                 lending_market_owner_keypair,
             )
         }
-        _ => unreachable!(),
-    }
-    .map_err(|err| {
-        eprintln!("{}", err);
-        exit(1);
-    });
-}
-
-// COMMANDS
+        ("set-emode-group", Some(arg_matches)) => {
+            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let group = value_of(arg_matches, "group").unwrap();
+            let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio").unwrap();
+            let liquidation_threshold = value_of(arg_matches, "liquidation_threshold").unwrap();
 
-fn command_create_lending_market(
-    config: &Config,
+            command_set_emode_group(
+                &config,
+                group,
+                EModeGroup {
+                    loan_to_value_ratio,
+                    liquidation_threshold,
+                },
+                lending_market_pubkey,
+                lending_market_owner_keypair,
+            )
+        }
+        ("propose-market-owner", Some(arg_matches)) => {
+            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let new_owner = pubkey_of(arg_matches, "new_owner").unwrap();
+
+            command_propose_lending_market_owner(
+                &config,
+                lending_market_pubkey,
+                lending_market_owner_keypair,
+                new_owner,
+            )
+        }
+        ("accept-market-owner", Some(arg_matches)) => {
+            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let new_owner_keypair = keypair_of(arg_matches, "new_owner").unwrap();
+
+            command_accept_lending_market_owner(&config, lending_market_pubkey, new_owner_keypair)
+        }
+        ("apply-reserve-config", Some(arg_matches)) => {
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+
+            command_apply_reserve_config(&config, reserve_pubkey)
+        }
+        ("apply-emode-group", Some(arg_matches)) => {
+            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let group = value_of(arg_matches, "group").unwrap();
+
+            command_apply_emode_group(&config, lending_market_pubkey, group)
+        }
+        ("close-obligation", Some(arg_matches)) => {
+            let obligation_pubkey = pubkey_of(arg_matches, "obligation").unwrap();
+            let obligation_owner_keypair = keypair_of(arg_matches, "obligation_owner").unwrap();
+
+            command_close_obligation(&config, obligation_pubkey, obligation_owner_keypair)
+        }
+        ("deprecate-reserve", Some(arg_matches)) => {
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let ltv_decay_slots = value_of(arg_matches, "ltv_decay_slots").unwrap();
+
+            command_deprecate_reserve(
+                &config,
+                reserve_pubkey,
+                lending_market_owner_keypair,
+                ltv_decay_slots,
+            )
+        }
+        ("migration-report", Some(arg_matches)) => {
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+
+            command_migration_report(&config, reserve_pubkey)
+        }
+        _ => unreachable!(),
+    }
+    .map_err(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+}
+
+// COMMANDS
+
+fn command_create_lending_market(
+    config: &Config,
     lending_market_owner: Pubkey,
     quote_currency: [u8; 32],
     oracle_program_id: Pubkey,
@@ -1038,6 +1497,7 @@ fn command_update_reserve(
 ) -> CommandResult {
     let reserve_info = config.rpc_client.get_account(&reserve_pubkey)?;
     let mut reserve = Reserve::unpack_from_slice(reserve_info.data.borrow())?;
+    let previous_reserve = reserve.clone();
     if reserve_config.optimal_utilization_rate.is_some() {
         println!(
             "Updating optimal_utilization_rate from {} to {}",
@@ -1164,6 +1624,69 @@ fn command_update_reserve(
         reserve.config.fee_receiver = reserve_config.fee_receiver.unwrap();
     }
 
+    if reserve_config.isolated.is_some() {
+        println!(
+            "Updating isolated from {} to {}",
+            reserve.config.isolated,
+            reserve_config.isolated.unwrap(),
+        );
+        reserve.config.isolated = reserve_config.isolated.unwrap();
+    }
+
+    if reserve_config.borrow_only.is_some() {
+        println!(
+            "Updating borrow_only from {} to {}",
+            reserve.config.borrow_only,
+            reserve_config.borrow_only.unwrap(),
+        );
+        reserve.config.borrow_only = reserve_config.borrow_only.unwrap();
+    }
+
+    if reserve_config.added_borrow_weight_bps.is_some() {
+        println!(
+            "Updating added_borrow_weight_bps from {} to {}",
+            reserve.config.added_borrow_weight_bps,
+            reserve_config.added_borrow_weight_bps.unwrap(),
+        );
+        reserve.config.added_borrow_weight_bps = reserve_config.added_borrow_weight_bps.unwrap();
+    }
+
+    if reserve_config.emode_group.is_some() {
+        println!(
+            "Updating emode_group from {} to {}",
+            reserve.config.emode_group,
+            reserve_config.emode_group.unwrap(),
+        );
+        reserve.config.emode_group = reserve_config.emode_group.unwrap();
+    }
+
+    if reserve_config.max_liquidation_bonus.is_some() {
+        println!(
+            "Updating max_liquidation_bonus from {} to {}",
+            reserve.config.max_liquidation_bonus,
+            reserve_config.max_liquidation_bonus.unwrap(),
+        );
+        reserve.config.max_liquidation_bonus = reserve_config.max_liquidation_bonus.unwrap();
+    }
+
+    if reserve_config.fixed_rate_premium.is_some() {
+        println!(
+            "Updating fixed_rate_premium from {} to {}",
+            reserve.config.fixed_rate_premium,
+            reserve_config.fixed_rate_premium.unwrap(),
+        );
+        reserve.config.fixed_rate_premium = reserve_config.fixed_rate_premium.unwrap();
+    }
+
+    if reserve_config.max_fixed_term_slots.is_some() {
+        println!(
+            "Updating max_fixed_term_slots from {} to {}",
+            reserve.config.max_fixed_term_slots,
+            reserve_config.max_fixed_term_slots.unwrap(),
+        );
+        reserve.config.max_fixed_term_slots = reserve_config.max_fixed_term_slots.unwrap();
+    }
+
     let mut new_pyth_product_pubkey = spl_token_lending::NULL_PUBKEY;
     if pyth_price_pubkey.is_some() {
         println!(
@@ -1184,8 +1707,31 @@ fn command_update_reserve(
         reserve.liquidity.switchboard_oracle_pubkey = switchboard_feed_pubkey.unwrap();
     }
 
-    let mut transaction = Transaction::new_with_payer(
-        &[update_reserve_config(
+    // changes that add risk or replace oracles only apply after a delay
+    let instruction = if reserve.liquidity.pyth_oracle_pubkey
+        == previous_reserve.liquidity.pyth_oracle_pubkey
+        && reserve.liquidity.switchboard_oracle_pubkey
+            == previous_reserve.liquidity.switchboard_oracle_pubkey
+        && previous_reserve
+            .config
+            .is_risk_reducing_change(&reserve.config)
+    {
+        update_reserve_config(
+            config.lending_program_id,
+            reserve.config,
+            reserve_pubkey,
+            lending_market_pubkey,
+            lending_market_owner_keypair.pubkey(),
+            new_pyth_product_pubkey,
+            reserve.liquidity.pyth_oracle_pubkey,
+            reserve.liquidity.switchboard_oracle_pubkey,
+        )
+    } else {
+        println!(
+            "Proposing the changes, which can be applied with apply-reserve-config in {} slots",
+            RESERVE_CONFIG_DELAY_SLOTS
+        );
+        propose_reserve_config(
             config.lending_program_id,
             reserve.config,
             reserve_pubkey,
@@ -1194,6 +1740,334 @@ fn command_update_reserve(
             new_pyth_product_pubkey,
             reserve.liquidity.pyth_oracle_pubkey,
             reserve.liquidity.switchboard_oracle_pubkey,
+            Some(reserve.liquidity.token_swap_oracle_pubkey)
+                .filter(|token_swap_oracle_pubkey| *token_swap_oracle_pubkey != Pubkey::default()),
+            Some(reserve.liquidity.stake_pool_oracle_pubkey)
+                .filter(|stake_pool_oracle_pubkey| *stake_pool_oracle_pubkey != Pubkey::default()),
+        )
+    };
+    let mut transaction =
+        Transaction::new_with_payer(&[instruction], Some(&config.fee_payer.pubkey()));
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_emode_group(
+    config: &Config,
+    group: u8,
+    emode_group: EModeGroup,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+) -> CommandResult {
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_from_slice(lending_market_account.data.borrow())?;
+    let previous = lending_market
+        .emode_groups
+        .get((group as usize).wrapping_sub(1))
+        .copied()
+        .unwrap_or_default();
+    println!(
+        "Updating efficiency mode group {} from {:?} to {:?}",
+        group, previous, emode_group
+    );
+
+    // changes that add risk only apply after a delay
+    let instruction = if previous.is_risk_reducing_change(&emode_group) {
+        set_lending_market_emode_group(
+            config.lending_program_id,
+            group,
+            emode_group,
+            lending_market_pubkey,
+            lending_market_owner_keypair.pubkey(),
+        )
+    } else {
+        println!(
+            "Proposing the change, which can be applied with apply-emode-group in {} slots",
+            RESERVE_CONFIG_DELAY_SLOTS
+        );
+        propose_lending_market_emode_group(
+            config.lending_program_id,
+            group,
+            emode_group,
+            lending_market_pubkey,
+            lending_market_owner_keypair.pubkey(),
+        )
+    };
+    let mut transaction =
+        Transaction::new_with_payer(&[instruction], Some(&config.fee_payer.pubkey()));
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_propose_lending_market_owner(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+    new_owner: Pubkey,
+) -> CommandResult {
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_from_slice(lending_market_account.data.borrow())?;
+    if lending_market.pending_owner != Pubkey::default() {
+        println!(
+            "Replacing pending owner {} of lending market {}",
+            lending_market.pending_owner, lending_market_pubkey
+        );
+    }
+    println!(
+        "Proposing {} as the owner of lending market {}, which it must accept with accept-market-owner",
+        new_owner, lending_market_pubkey
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            config.lending_program_id,
+            lending_market_pubkey,
+            lending_market_owner_keypair.pubkey(),
+            new_owner,
+        )],
+        Some(&config.fee_payer.pubkey()),
+    );
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_accept_lending_market_owner(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    new_owner_keypair: Keypair,
+) -> CommandResult {
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_from_slice(lending_market_account.data.borrow())?;
+    if lending_market.pending_owner != new_owner_keypair.pubkey() {
+        return Err(format!(
+            "{} is not the pending owner of lending market {}",
+            new_owner_keypair.pubkey(),
+            lending_market_pubkey
+        )
+        .into());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[accept_lending_market_owner(
+            config.lending_program_id,
+            lending_market_pubkey,
+            new_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+    );
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(
+        &vec![config.fee_payer.as_ref(), &new_owner_keypair],
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_apply_reserve_config(config: &Config, reserve_pubkey: Pubkey) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+    let lending_market_account = config.rpc_client.get_account(&reserve.lending_market)?;
+    let lending_market = LendingMarket::unpack_from_slice(lending_market_account.data.borrow())?;
+
+    let (pending_reserve_config_pubkey, _bump_seed) =
+        pending_reserve_config_address(&config.lending_program_id, &reserve_pubkey);
+    let pending_reserve_config_account = config
+        .rpc_client
+        .get_account(&pending_reserve_config_pubkey)
+        .map_err(|_| format!("Reserve {} has no proposed config", reserve_pubkey))?;
+    let pending_reserve_config =
+        PendingReserveConfig::unpack_from_slice(pending_reserve_config_account.data.borrow())?;
+    let slot = config.rpc_client.get_slot()?;
+    if slot < pending_reserve_config.activation_slot {
+        return Err(format!(
+            "Proposed config of reserve {} can be applied from slot {}, {} slots from now",
+            reserve_pubkey,
+            pending_reserve_config.activation_slot,
+            pending_reserve_config.activation_slot - slot
+        )
+        .into());
+    }
+    println!(
+        "Applying reserve config {:?} with pyth oracle {} and switchboard oracle {}",
+        pending_reserve_config.config,
+        pending_reserve_config.pyth_oracle_pubkey,
+        pending_reserve_config.switchboard_oracle_pubkey
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_reserve_config(
+            config.lending_program_id,
+            reserve_pubkey,
+            reserve.lending_market,
+            lending_market.owner,
+        )],
+        Some(&config.fee_payer.pubkey()),
+    );
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(&vec![config.fee_payer.as_ref()], recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_apply_emode_group(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    group: u8,
+) -> CommandResult {
+    let lending_market_account = config.rpc_client.get_account(&lending_market_pubkey)?;
+    let lending_market = LendingMarket::unpack_from_slice(lending_market_account.data.borrow())?;
+
+    let (pending_emode_group_pubkey, _bump_seed) =
+        pending_emode_group_address(&config.lending_program_id, &lending_market_pubkey, group);
+    let pending_emode_group_account = config
+        .rpc_client
+        .get_account(&pending_emode_group_pubkey)
+        .map_err(|_| {
+            format!(
+                "Efficiency mode group {} of lending market {} has no proposal",
+                group, lending_market_pubkey
+            )
+        })?;
+    let pending_emode_group =
+        PendingEModeGroup::unpack_from_slice(pending_emode_group_account.data.borrow())?;
+    let slot = config.rpc_client.get_slot()?;
+    if slot < pending_emode_group.activation_slot {
+        return Err(format!(
+            "Proposed efficiency mode group {} can be applied from slot {}, {} slots from now",
+            group,
+            pending_emode_group.activation_slot,
+            pending_emode_group.activation_slot - slot
+        )
+        .into());
+    }
+    println!(
+        "Applying efficiency mode group {} {:?}",
+        group, pending_emode_group.config
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_lending_market_emode_group(
+            config.lending_program_id,
+            group,
+            lending_market_pubkey,
+            lending_market.owner,
+        )],
+        Some(&config.fee_payer.pubkey()),
+    );
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(&vec![config.fee_payer.as_ref()], recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_close_obligation(
+    config: &Config,
+    obligation_pubkey: Pubkey,
+    obligation_owner_keypair: Keypair,
+) -> CommandResult {
+    let obligation_account = config.rpc_client.get_account(&obligation_pubkey)?;
+    let obligation = Obligation::unpack_from_slice(obligation_account.data.borrow())?;
+    if !obligation.deposits.is_empty() || !obligation.borrows.is_empty() {
+        return Err(format!(
+            "Obligation {} still has {} deposits and {} borrows",
+            obligation_pubkey,
+            obligation.deposits.len(),
+            obligation.borrows.len()
+        )
+        .into());
+    }
+    println!(
+        "Closing obligation {} and returning {} SOL to {}",
+        obligation_pubkey,
+        lamports_to_sol(obligation_account.lamports),
+        obligation_owner_keypair.pubkey()
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(config.lending_program_id, obligation_pubkey, vec![]),
+            close_obligation(
+                config.lending_program_id,
+                obligation_pubkey,
+                obligation.lending_market,
+                obligation_owner_keypair.pubkey(),
+            ),
+        ],
+        Some(&config.fee_payer.pubkey()),
+    );
+
+    let (recent_blockhash, fee_calculator) = config.rpc_client.get_recent_blockhash()?;
+    check_fee_payer_balance(config, fee_calculator.calculate_fee(transaction.message()))?;
+
+    transaction.sign(
+        &vec![config.fee_payer.as_ref(), &obligation_owner_keypair],
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_deprecate_reserve(
+    config: &Config,
+    reserve_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+    ltv_decay_slots: u64,
+) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+    if reserve.deprecated {
+        return Err(format!(
+            "Reserve {} was already deprecated at slot {}",
+            reserve_pubkey, reserve.deprecation_slot
+        )
+        .into());
+    }
+    println!(
+        "Deprecating reserve {}, its loan to value ratio of {}% decays to 0% over {} slots",
+        reserve_pubkey, reserve.config.loan_to_value_ratio, ltv_decay_slots
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deprecate_reserve(
+            config.lending_program_id,
+            ltv_decay_slots,
+            reserve_pubkey,
+            reserve.lending_market,
+            lending_market_owner_keypair.pubkey(),
         )],
         Some(&config.fee_payer.pubkey()),
     );
@@ -1209,6 +2083,99 @@ fn command_update_reserve(
     Ok(())
 }
 
+fn command_migration_report(config: &Config, reserve_pubkey: Pubkey) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+    let collateral_exchange_rate = reserve.collateral_exchange_rate()?;
+    let decimals = reserve.liquidity.mint_decimals;
+
+    let slot = config.rpc_client.get_slot()?;
+    if reserve.deprecated {
+        println!(
+            "Reserve {} was deprecated at slot {}, loan to value rate is {} at slot {}",
+            reserve_pubkey,
+            reserve.deprecation_slot,
+            reserve.loan_to_value_rate(slot)?,
+            slot
+        );
+    } else {
+        println!("Reserve {} is not deprecated", reserve_pubkey);
+    }
+
+    // the lending market follows the version and last update of obligations and reserves, reserves
+    // fail to unpack as obligations, which can be longer than Obligation::LEN once resized
+    let program_accounts = config.rpc_client.get_program_accounts_with_config(
+        &config.lending_program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+                offset: 10,
+                bytes: MemcmpEncodedBytes::Binary(reserve.lending_market.to_string()),
+                encoding: None,
+            })]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64Zstd),
+                ..RpcAccountInfoConfig::default()
+            },
+            with_context: None,
+        },
+    )?;
+
+    let mut obligation_count = 0;
+    let mut total_deposited_amount = 0u64;
+    let mut total_borrowed_amount = 0u64;
+    for (obligation_pubkey, obligation_account) in program_accounts {
+        let obligation = match Obligation::unpack(obligation_account.data.borrow()) {
+            Ok(obligation) => obligation,
+            Err(_) => continue,
+        };
+
+        let deposited_amount = obligation
+            .deposits
+            .iter()
+            .find(|collateral| collateral.deposit_reserve == reserve_pubkey)
+            .map_or(0, |collateral| collateral.deposited_amount);
+        let borrowed_amount = match obligation
+            .borrows
+            .iter()
+            .find(|liquidity| liquidity.borrow_reserve == reserve_pubkey)
+        {
+            Some(liquidity) => liquidity.borrowed_amount_wads.try_ceil_u64()?,
+            None => 0,
+        };
+        if deposited_amount == 0 && borrowed_amount == 0 {
+            continue;
+        }
+
+        println!(
+            "Obligation {} owned by {}: {} deposited ({} collateral), {} borrowed",
+            obligation_pubkey,
+            obligation.owner,
+            amount_to_ui_amount(
+                collateral_exchange_rate.collateral_to_liquidity(deposited_amount)?,
+                decimals
+            ),
+            amount_to_ui_amount(deposited_amount, decimals),
+            amount_to_ui_amount(borrowed_amount, decimals)
+        );
+        obligation_count += 1;
+        total_deposited_amount = total_deposited_amount.saturating_add(deposited_amount);
+        total_borrowed_amount = total_borrowed_amount.saturating_add(borrowed_amount);
+    }
+
+    println!(
+        "{} obligations hold positions in reserve {}: {} deposited ({} collateral), {} borrowed",
+        obligation_count,
+        reserve_pubkey,
+        amount_to_ui_amount(
+            collateral_exchange_rate.collateral_to_liquidity(total_deposited_amount)?,
+            decimals
+        ),
+        amount_to_ui_amount(total_deposited_amount, decimals),
+        amount_to_ui_amount(total_borrowed_amount, decimals)
+    );
+    Ok(())
+}
+
 // HELPERS
 
 fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
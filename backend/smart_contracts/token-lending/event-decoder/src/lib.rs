@@ -0,0 +1,141 @@
+#![deny(missing_docs)]
+
+//! Decodes structured lending events from transaction logs
+
+use borsh::BorshDeserialize;
+use solana_program::pubkey::Pubkey;
+pub use spl_token_lending::events::{LendingEvent, EVENT_VERSION};
+use thiserror::Error;
+
+const PROGRAM_LOG_PREFIX: &str = "Program ";
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// Errors that may be returned while decoding events
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    /// Event data is not valid base64
+    #[error("Event data is not valid base64")]
+    InvalidBase64(#[from] base64::DecodeError),
+    /// Event data is empty
+    #[error("Event data is empty")]
+    EmptyEvent,
+    /// Event was logged with an unknown layout version
+    #[error("Unsupported event version {0}")]
+    UnsupportedVersion(u8),
+    /// Event data does not match any known event
+    #[error("Event data could not be deserialized")]
+    InvalidEvent(#[from] std::io::Error),
+}
+
+/// Decodes a single event from its raw log data
+pub fn decode_event(data: &[u8]) -> Result<LendingEvent, DecodeError> {
+    let (version, event) = data.split_first().ok_or(DecodeError::EmptyEvent)?;
+    if *version != EVENT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(*version));
+    }
+    Ok(LendingEvent::try_from_slice(event)?)
+}
+
+/// Decodes the events logged by the lending program in a transaction's log messages, in the order
+/// they were logged. Data logged by other programs, including programs invoked by the lending
+/// program, is skipped.
+pub fn decode_logs<S: AsRef<str>>(
+    program_id: &Pubkey,
+    logs: &[S],
+) -> Result<Vec<LendingEvent>, DecodeError> {
+    let program_id = program_id.to_string();
+    let mut invoke_stack: Vec<&str> = vec![];
+    let mut events = vec![];
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if invoke_stack.last() == Some(&program_id.as_str()) {
+                for field in data.split_whitespace() {
+                    events.push(decode_event(&base64::decode(field)?)?);
+                }
+            }
+        } else if let Some(rest) = log.strip_prefix(PROGRAM_LOG_PREFIX) {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(id), Some("invoke")) => invoke_stack.push(id),
+                (Some(id), Some("success")) | (Some(id), Some("failed:"))
+                    if invoke_stack.last() == Some(&id) =>
+                {
+                    invoke_stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    fn encode(event: &LendingEvent) -> String {
+        let mut data = vec![EVENT_VERSION];
+        event.serialize(&mut data).unwrap();
+        base64::encode(data)
+    }
+
+    #[test]
+    fn decode_program_logs() {
+        let program_id = Pubkey::new_unique();
+        let token_program_id = Pubkey::new_unique();
+        let deposit = LendingEvent::DepositReserveLiquidity {
+            reserve: Pubkey::new_unique(),
+            liquidity_amount: 100,
+            collateral_amount: 100,
+        };
+        let refresh = LendingEvent::RefreshObligation {
+            obligation: Pubkey::new_unique(),
+            slot: 1,
+            deposited_value_wads: 2,
+            borrowed_value_wads: 3,
+            allowed_borrow_value_wads: 4,
+            unhealthy_borrow_value_wads: 5,
+        };
+
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: Deposit Reserve Liquidity".to_string(),
+            format!("Program {} invoke [2]", token_program_id),
+            format!("Program data: {}", encode(&refresh)),
+            format!("Program {} success", token_program_id),
+            format!("Program data: {}", encode(&deposit)),
+            format!("Program {} consumed 1000 of 200000 compute units", program_id),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", token_program_id),
+            format!("Program data: {}", encode(&refresh)),
+            format!("Program {} success", token_program_id),
+            format!("Program {} invoke [1]", program_id),
+            format!("Program data: {}", encode(&refresh)),
+            format!("Program {} success", program_id),
+        ];
+
+        assert_eq!(
+            decode_logs(&program_id, &logs).unwrap(),
+            vec![deposit, refresh]
+        );
+    }
+
+    #[test]
+    fn decode_unsupported_version() {
+        let mut data = base64::decode(encode(&LendingEvent::CloseObligation {
+            obligation: Pubkey::new_unique(),
+        }))
+        .unwrap();
+        data[0] = EVENT_VERSION + 1;
+
+        assert!(matches!(
+            decode_event(&data),
+            Err(DecodeError::UnsupportedVersion(version)) if version == EVENT_VERSION + 1
+        ));
+        assert!(matches!(decode_event(&[]), Err(DecodeError::EmptyEvent)));
+    }
+}
@@ -163,6 +163,96 @@ pub enum LendingError {
     /// Null oracle config
     #[error("Null oracle config")]
     NullOracleConfig,
+    /// Flash borrow is not followed by a matching flash repay
+    #[error("Flash borrow instruction does not have a matching flash repay")]
+    NoFlashRepayFound,
+    /// Flash repay does not match its flash borrow
+    #[error("Flash repay instruction does not match its flash borrow")]
+    InvalidFlashRepay,
+    /// Flash borrow and repay cannot be invoked through CPI
+    #[error("Flash borrow and repay instructions cannot be invoked through CPI")]
+    FlashLoanCpiNotAllowed,
+    /// Only one flash borrow per transaction is allowed
+    #[error("Multiple flash borrows are not allowed in the same transaction")]
+    MultipleFlashBorrows,
+
+    // 50
+    /// Isolated reserves cannot be borrowed alongside other reserves
+    #[error("Isolated reserve cannot be borrowed alongside other reserves")]
+    IsolatedReserveViolation,
+    /// Obligation still has deposits
+    #[error("Obligation still has deposits and its debt cannot be forgiven")]
+    ObligationDepositsNotEmpty,
+    /// Operation is paused on the lending market or reserve
+    #[error("Operation is paused")]
+    OperationPaused,
+    /// Signer is not allowed to change pause flags
+    #[error("Signer is not the lending market owner or guardian")]
+    InvalidPauseAuthority,
+    /// Outflow rate limit exceeded
+    #[error("Outflow rate limit exceeded for the current window")]
+    OutflowRateLimitExceeded,
+
+    // 55
+    /// Swap output is below the minimum
+    #[error("Swap output is below the minimum liquidity out")]
+    InsufficientSwapOutput,
+    /// Obligation health worsened
+    #[error("Obligation health cannot worsen when it exceeds the allowed borrow value")]
+    ObligationHealthWorsened,
+    /// Leverage target exceeded
+    #[error("Obligation loan to value exceeds the leverage target")]
+    LeverageTargetExceeded,
+    /// Stake pool stale
+    #[error("Stake pool must be updated in the current epoch")]
+    StakePoolStale,
+    /// Obligation still has deposits or borrows
+    #[error("Obligation must have no deposits or borrows to be closed")]
+    ObligationNotEmpty,
+
+    // 60
+    /// New obligation owner already has an obligation
+    #[error("New obligation owner already has an obligation at its seeded address")]
+    ObligationOwnerConflict,
+    /// Reserve config change must be proposed
+    #[error("Reserve config changes that add risk must be proposed and applied after a delay")]
+    ReserveConfigChangeNotAllowed,
+    /// Pending reserve config is not active yet
+    #[error("Pending reserve config cannot be applied before its activation slot")]
+    ReserveConfigNotActive,
+    /// Wallet is not a member of an allowlisted lending market
+    #[error("Wallet must be a member of the allowlisted lending market")]
+    NotMarketMember,
+    /// Expected the lending market allowlist admin
+    #[error("Allowlist admin must be the allowlist admin of the lending market")]
+    InvalidAllowlistAdmin,
+
+    // 65
+    /// Fixed term is zero or longer than the reserve allows
+    #[error("Fixed term must be between one slot and the max fixed term of the reserve")]
+    InvalidFixedTerm,
+    /// Fixed rate borrow combined with another borrow from the same reserve
+    #[error("Fixed rate borrows cannot be combined with other borrows from the same reserve")]
+    FixedRateBorrowConflict,
+    /// Fixed term borrow has not matured
+    #[error("Fixed term borrow can only be settled after its maturity slot")]
+    FixedTermNotMatured,
+    /// Borrow exceeds the credit delegation allowance
+    #[error("Delegated borrow cannot exceed the remaining credit delegation allowance")]
+    CreditDelegationExceeded,
+    /// Reserve is deprecated
+    #[error("Reserve is deprecated and does not allow deposits or borrows")]
+    ReserveDeprecated,
+
+    // 70
+    /// Efficiency mode group change must be proposed
+    #[error(
+        "Efficiency mode group changes that add risk must be proposed and applied after a delay"
+    )]
+    EModeGroupChangeNotAllowed,
+    /// Pending efficiency mode group is not active yet
+    #[error("Pending efficiency mode group cannot be applied before its activation slot")]
+    EModeGroupNotActive,
 }
 
 impl From<LendingError> for ProgramError {
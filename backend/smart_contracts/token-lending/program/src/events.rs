@@ -0,0 +1,406 @@
+//! Structured events logged by the lending program for indexers
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+/// Version of the event layout, logged as the first byte of each event
+pub const EVENT_VERSION: u8 = 1;
+
+/// Lending state change, logged as `Program data: <base64>` of the event version followed by the
+/// borsh serialized event. Decimal values are logged as scaled wads.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum LendingEvent {
+    /// Lending market initialized
+    InitLendingMarket {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Lending market owner
+        owner: Pubkey,
+        /// Quote currency of market values
+        quote_currency: [u8; 32],
+    },
+    /// Lending market owner changed
+    SetLendingMarketOwner {
+        /// Lending market
+        lending_market: Pubkey,
+        /// New lending market owner
+        new_owner: Pubkey,
+    },
+    /// Reserve initialized with its initial liquidity
+    InitReserve {
+        /// Reserve
+        reserve: Pubkey,
+        /// Lending market of the reserve
+        lending_market: Pubkey,
+        /// Reserve liquidity mint
+        liquidity_mint: Pubkey,
+        /// Initial liquidity deposited
+        liquidity_amount: u64,
+        /// Collateral minted for the initial liquidity
+        collateral_amount: u64,
+    },
+    /// Reserve interest accrued and market price updated
+    RefreshReserve {
+        /// Reserve
+        reserve: Pubkey,
+        /// Slot of the refresh
+        slot: u64,
+        /// Market price of the reserve liquidity
+        market_price_wads: u128,
+        /// Cumulative borrow rate after accruing interest
+        cumulative_borrow_rate_wads: u128,
+        /// Borrowed liquidity at variable and fixed rates after accruing interest
+        borrowed_amount_wads: u128,
+        /// Liquidity available to borrow
+        available_amount: u64,
+    },
+    /// Liquidity deposited into a reserve for collateral
+    DepositReserveLiquidity {
+        /// Reserve
+        reserve: Pubkey,
+        /// Liquidity deposited
+        liquidity_amount: u64,
+        /// Collateral minted
+        collateral_amount: u64,
+    },
+    /// Collateral redeemed from a reserve for liquidity
+    RedeemReserveCollateral {
+        /// Reserve
+        reserve: Pubkey,
+        /// Collateral burned
+        collateral_amount: u64,
+        /// Liquidity withdrawn
+        liquidity_amount: u64,
+    },
+    /// Reserve config or oracles updated
+    UpdateReserveConfig {
+        /// Reserve
+        reserve: Pubkey,
+    },
+    /// Obligation initialized
+    InitObligation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Lending market of the obligation
+        lending_market: Pubkey,
+        /// Obligation owner
+        owner: Pubkey,
+    },
+    /// Obligation market values updated
+    RefreshObligation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Slot of the refresh
+        slot: u64,
+        /// Market value of deposits
+        deposited_value_wads: u128,
+        /// Weighted market value of borrows
+        borrowed_value_wads: u128,
+        /// Max borrow value
+        allowed_borrow_value_wads: u128,
+        /// Borrow value at which the obligation can be liquidated
+        unhealthy_borrow_value_wads: u128,
+    },
+    /// Collateral deposited into an obligation
+    DepositObligationCollateral {
+        /// Obligation
+        obligation: Pubkey,
+        /// Deposit reserve
+        reserve: Pubkey,
+        /// Collateral deposited
+        collateral_amount: u64,
+    },
+    /// Collateral withdrawn from an obligation
+    WithdrawObligationCollateral {
+        /// Obligation
+        obligation: Pubkey,
+        /// Withdraw reserve
+        reserve: Pubkey,
+        /// Collateral withdrawn
+        collateral_amount: u64,
+    },
+    /// Liquidity borrowed by an obligation
+    BorrowObligationLiquidity {
+        /// Obligation
+        obligation: Pubkey,
+        /// Borrow reserve
+        reserve: Pubkey,
+        /// Liquidity received by the borrower
+        liquidity_amount: u64,
+        /// Borrow fee, including the host fee
+        borrow_fee: u64,
+        /// Part of the borrow fee paid to the host
+        host_fee: u64,
+    },
+    /// Liquidity repaid to an obligation
+    RepayObligationLiquidity {
+        /// Obligation
+        obligation: Pubkey,
+        /// Repay reserve
+        reserve: Pubkey,
+        /// Liquidity repaid
+        liquidity_amount: u64,
+    },
+    /// Unhealthy obligation liquidated
+    LiquidateObligation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Repay reserve
+        repay_reserve: Pubkey,
+        /// Withdraw reserve
+        withdraw_reserve: Pubkey,
+        /// Liquidity repaid by the liquidator
+        liquidity_amount: u64,
+        /// Collateral received by the liquidator
+        collateral_amount: u64,
+    },
+    /// Liquidity flash borrowed and repaid in a single instruction
+    FlashLoan {
+        /// Reserve
+        reserve: Pubkey,
+        /// Liquidity borrowed
+        liquidity_amount: u64,
+        /// Flash loan fee, including the host fee
+        fee: u64,
+    },
+    /// Liquidity flash borrowed, to be repaid later in the transaction
+    FlashBorrowReserveLiquidity {
+        /// Reserve
+        reserve: Pubkey,
+        /// Liquidity borrowed
+        liquidity_amount: u64,
+    },
+    /// Flash borrowed liquidity repaid
+    FlashRepayReserveLiquidity {
+        /// Reserve
+        reserve: Pubkey,
+        /// Liquidity repaid, excluding the fee
+        liquidity_amount: u64,
+        /// Flash loan fee, including the host fee
+        fee: u64,
+    },
+    /// Obligation debt forgiven by the lending market owner
+    ForgiveDebt {
+        /// Obligation
+        obligation: Pubkey,
+        /// Reserve of the forgiven debt
+        reserve: Pubkey,
+        /// Borrowed liquidity forgiven
+        liquidity_amount_wads: u128,
+    },
+    /// Lending market guardian changed
+    SetLendingMarketGuardian {
+        /// Lending market
+        lending_market: Pubkey,
+        /// New guardian
+        new_guardian: Pubkey,
+    },
+    /// Lending market pause flags changed
+    SetLendingMarketPauseFlags {
+        /// Lending market
+        lending_market: Pubkey,
+        /// New pause flags
+        pause_flags: u8,
+    },
+    /// Reserve pause flags changed
+    SetReservePauseFlags {
+        /// Reserve
+        reserve: Pubkey,
+        /// New pause flags
+        pause_flags: u8,
+    },
+    /// Lending market outflow rate limiter changed
+    SetLendingMarketRateLimiterConfig {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Length of the window in slots
+        window_duration: u64,
+        /// Max outflow value per window
+        max_outflow: u64,
+    },
+    /// Reserve outflow rate limiter changed
+    SetReserveRateLimiterConfig {
+        /// Reserve
+        reserve: Pubkey,
+        /// Length of the window in slots
+        window_duration: u64,
+        /// Max outflow liquidity per window
+        max_outflow: u64,
+    },
+    /// Liquidity swapped through spl-token-swap
+    SwapLiquidity {
+        /// Token swap
+        token_swap: Pubkey,
+        /// Liquidity swapped
+        amount_in: u64,
+        /// Liquidity received
+        amount_out: u64,
+    },
+    /// Reserve token swap oracle changed
+    SetReserveTokenSwapOracle {
+        /// Reserve
+        reserve: Pubkey,
+        /// Token swap, or the default pubkey when cleared
+        token_swap: Pubkey,
+    },
+    /// Reserve stake pool oracle changed
+    SetReserveStakePoolOracle {
+        /// Reserve
+        reserve: Pubkey,
+        /// Stake pool, or the default pubkey when cleared
+        stake_pool: Pubkey,
+    },
+    /// Empty obligation closed
+    CloseObligation {
+        /// Obligation
+        obligation: Pubkey,
+    },
+    /// Obligation transferred to a new owner
+    SetObligationOwner {
+        /// Obligation
+        obligation: Pubkey,
+        /// New obligation owner
+        new_owner: Pubkey,
+    },
+    /// Obligation reallocated to hold more reserves
+    ResizeObligation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Max number of deposits and borrows combined
+        max_reserves: u8,
+    },
+    /// An efficiency mode group of a lending market was set
+    SetLendingMarketEModeGroup {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Group number
+        group: u8,
+        /// Loan to value ratio of the group, as a percentage
+        loan_to_value_ratio: u8,
+        /// Liquidation threshold of the group, as a percentage
+        liquidation_threshold: u8,
+    },
+    /// Reserve config or oracles proposed, applied with an `UpdateReserveConfig` event
+    ProposeReserveConfig {
+        /// Reserve
+        reserve: Pubkey,
+        /// First slot the proposal can be applied in
+        activation_slot: u64,
+    },
+    /// Lending market owner proposed, accepted with a `SetLendingMarketOwner` event
+    ProposeLendingMarketOwner {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Proposed lending market owner, default if the proposal was cancelled
+        pending_owner: Pubkey,
+    },
+    /// Allowlist admin of a lending market set
+    SetLendingMarketAllowlistAdmin {
+        /// Lending market
+        lending_market: Pubkey,
+        /// New allowlist admin, default if the allowlist was removed
+        new_admin: Pubkey,
+    },
+    /// Wallet added to an allowlisted lending market
+    AddMarketMember {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Member wallet
+        member: Pubkey,
+    },
+    /// Wallet removed from an allowlisted lending market
+    RevokeMarketMember {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Member wallet
+        member: Pubkey,
+    },
+    /// Liquidity borrowed by an obligation at a fixed rate until maturity
+    BorrowObligationLiquidityFixedTerm {
+        /// Obligation
+        obligation: Pubkey,
+        /// Borrow reserve
+        reserve: Pubkey,
+        /// Liquidity received by the borrower
+        liquidity_amount: u64,
+        /// Borrow fee, including the host fee
+        borrow_fee: u64,
+        /// Part of the borrow fee paid to the host
+        host_fee: u64,
+        /// Fixed annual borrow rate
+        fixed_borrow_rate_wads: u128,
+        /// Slot the borrow matures at
+        maturity_slot: u64,
+    },
+    /// Matured fixed term borrow converted to a variable rate borrow
+    SettleFixedTermBorrow {
+        /// Obligation
+        obligation: Pubkey,
+        /// Borrow reserve
+        reserve: Pubkey,
+        /// Borrowed liquidity moved to variable rate borrows
+        borrowed_amount_wads: u128,
+    },
+    /// Delegate approved to borrow against an obligation
+    ApproveCreditDelegation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Delegate wallet
+        delegate: Pubkey,
+        /// Reserve the delegate can borrow from
+        reserve: Pubkey,
+        /// Liquidity the delegate can borrow, including borrow fees
+        borrow_amount: u64,
+    },
+    /// Credit delegation revoked
+    RevokeCreditDelegation {
+        /// Obligation
+        obligation: Pubkey,
+        /// Delegate wallet
+        delegate: Pubkey,
+        /// Reserve the delegate could borrow from
+        reserve: Pubkey,
+    },
+    /// Liquidity borrowed against an obligation by a delegate of its owner
+    BorrowObligationLiquidityDelegated {
+        /// Obligation
+        obligation: Pubkey,
+        /// Borrow reserve
+        reserve: Pubkey,
+        /// Delegate wallet
+        delegate: Pubkey,
+        /// Liquidity received by the delegate
+        liquidity_amount: u64,
+        /// Borrow fee, including the host fee
+        borrow_fee: u64,
+        /// Part of the borrow fee paid to the host
+        host_fee: u64,
+    },
+    /// Reserve deprecated
+    DeprecateReserve {
+        /// Reserve
+        reserve: Pubkey,
+        /// Slots over which the loan to value ratio of deposits decays to zero
+        ltv_decay_slots: u64,
+    },
+    /// Efficiency mode group proposed, applied with a `SetLendingMarketEModeGroup` event
+    ProposeLendingMarketEModeGroup {
+        /// Lending market
+        lending_market: Pubkey,
+        /// Group number
+        group: u8,
+        /// First slot the proposal can be applied in
+        activation_slot: u64,
+    },
+}
+
+impl LendingEvent {
+    /// Logs the event
+    #[inline(never)] // avoid stack frame limit
+    pub fn emit(&self) {
+        let mut data = vec![EVENT_VERSION];
+        // serializing into a vec cannot fail
+        self.serialize(&mut data).unwrap();
+        sol_log_data(&[&data]);
+    }
+}
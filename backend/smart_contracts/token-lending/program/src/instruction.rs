@@ -2,14 +2,19 @@
 
 use crate::{
     error::LendingError,
-    state::{ReserveConfig, ReserveFees},
+    state::{
+        credit_delegation_address, market_member_address, obligation_address_with_seed,
+        pending_emode_group_address, pending_reserve_config_address, EModeGroup, RateLimiterConfig,
+        ReserveConfig, ReserveFees,
+    },
+    NULL_PUBKEY,
 };
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     msg,
     program_error::ProgramError,
     pubkey::{Pubkey, PUBKEY_BYTES},
-    sysvar,
+    system_program, sysvar,
 };
 use std::{convert::TryInto, mem::size_of};
 
@@ -35,16 +40,8 @@ pub enum LendingInstruction {
     },
 
     // 1
-    /// Sets the new owner of a lending market.
-    ///
-    /// Accounts expected by this instruction:
-    ///
-    ///   0. `[writable]` Lending market account.
-    ///   1. `[signer]` Current owner.
-    SetLendingMarketOwner {
-        /// The new owner
-        new_owner: Pubkey,
-    },
+    // Unused, lending market ownership is transferred with ProposeLendingMarketOwner and
+    // AcceptLendingMarketOwner
 
     // 2
     /// Initializes a new lending market reserve.
@@ -89,6 +86,21 @@ pub enum LendingInstruction {
     ///   2. `[]` Switchboard Reserve liquidity oracle account.
     ///             Must be the Switchboard price feed account specified at InitReserve.
     ///   3. `[]` Clock sysvar.
+    ///
+    ///   Reserves priced by a token swap oracle also expect:
+    ///
+    ///   4. `[]` Token swap account.
+    ///             Must be the token swap applied with ApplyReserveConfig.
+    ///   5. `[]` Token swap token A account.
+    ///   6. `[]` Token swap token B account.
+    ///   7. `[]` Token swap pool mint, the reserve liquidity mint.
+    ///   8. `[]` Token A reserve account - refreshed.
+    ///   9. `[]` Token B reserve account - refreshed.
+    ///
+    ///   Reserves priced by a stake pool oracle instead expect:
+    ///
+    ///   4. `[]` Stake pool account.
+    ///             Must be the stake pool applied with ApplyReserveConfig.
     RefreshReserve,
 
     // 4
@@ -108,6 +120,8 @@ pub enum LendingInstruction {
     ///   7. `[signer]` User transfer authority ($authority).
     ///   8. `[]` Clock sysvar.
     ///   9. `[]` Token program id.
+    ///   10 `[optional]` Market member account of the user transfer authority - required only if
+    ///                     the lending market has an allowlist admin.
     DepositReserveLiquidity {
         /// Amount of liquidity to deposit in exchange for collateral tokens
         liquidity_amount: u64,
@@ -124,7 +138,7 @@ pub enum LendingInstruction {
     ///   2. `[writable]` Reserve account. - refreshed
     ///   3. `[writable]` Reserve collateral SPL Token mint.
     ///   4. `[writable]` Reserve liquidity supply SPL Token account.
-    ///   5. `[]` Lending market account.
+    ///   5. `[writable]` Lending market account.
     ///   6. `[]` Derived lending market authority.
     ///   7. `[signer]` User transfer authority ($authority).
     ///   8. `[]` Clock sysvar.
@@ -145,19 +159,24 @@ pub enum LendingInstruction {
     ///   3. `[]` Clock sysvar.
     ///   4. `[]` Rent sysvar.
     ///   5. `[]` Token program id.
+    ///   6. `[optional]` Market member account of the obligation owner - required only if the
+    ///                     lending market has an allowlist admin.
     InitObligation,
 
     // 7
     /// Refresh an obligation's accrued interest and collateral and liquidity prices. Requires
     /// refreshed reserves, as all obligation collateral deposit reserves in order, followed by all
-    /// liquidity borrow reserves in order.
+    /// liquidity borrow reserves in order. Matured fixed term borrows are settled into variable
+    /// rate borrows, as with SettleFixedTermBorrow.
     ///
     /// Accounts expected by this instruction:
     ///
     ///   0. `[writable]` Obligation account.
     ///   1. `[]` Clock sysvar.
     ///   .. `[]` Collateral deposit reserve accounts - refreshed, all, in order.
-    ///   .. `[]` Liquidity borrow reserve accounts - refreshed, all, in order.
+    ///   .. `[writable]` Liquidity borrow reserve accounts - refreshed, all, in order.
+    ///   .. `[]` Lending market account - required only if all deposit and borrow reserves
+    ///             belong to the same efficiency mode group.
     RefreshObligation,
 
     // 8
@@ -214,7 +233,7 @@ pub enum LendingInstruction {
     ///   3. `[writable]` Borrow reserve liquidity fee receiver account.
     ///                     Must be the fee account specified at InitReserve.
     ///   4. `[writable]` Obligation account - refreshed.
-    ///   5. `[]` Lending market account.
+    ///   5. `[writable]` Lending market account.
     ///   6. `[]` Derived lending market authority.
     ///   7. `[signer]` Obligation owner.
     ///   8. `[]` Clock sysvar.
@@ -286,7 +305,7 @@ pub enum LendingInstruction {
     ///   3. `[writable]` Flash loan fee receiver account.
     ///                     Must match the reserve liquidity fee receiver.
     ///   4. `[writable]` Host fee receiver.
-    ///   5. `[]` Lending market account.
+    ///   5. `[writable]` Lending market account.
     ///   6. `[]` Derived lending market authority.
     ///   7. `[]` Token program id.
     ///   8. `[]` Flash loan receiver program id.
@@ -334,6 +353,10 @@ pub enum LendingInstruction {
     ///   12 `[signer]` User transfer authority ($authority).
     ///   13 `[]` Clock sysvar.
     ///   14 `[]` Token program id.
+    ///   15 `[]` Market member account of the obligation owner - expected only if the lending
+    ///             market has an allowlist admin.
+    ///   .. `[]` Token swap oracle accounts, as expected by RefreshReserve, for reserves priced
+    ///             by a token swap oracle.
     DepositReserveLiquidityAndObligationCollateral {
         /// Amount of liquidity to deposit in exchange
         liquidity_amount: u64,
@@ -349,7 +372,7 @@ pub enum LendingInstruction {
     ///                     Minted by withdraw reserve collateral mint.
     ///   2. `[writable]` Withdraw reserve account - refreshed.
     ///   3. `[writable]` Obligation account - refreshed.
-    ///   4. `[]` Lending market account.
+    ///   4. `[writable]` Lending market account.
     ///   5. `[]` Derived lending market authority.
     ///   6. `[writable]` User liquidity token account.
     ///   7. `[writable]` Reserve collateral SPL Token mint.
@@ -364,7 +387,9 @@ pub enum LendingInstruction {
     },
 
     // 16
-    /// Updates a reserves config and a reserve price oracle pubkeys
+    /// Updates a reserves config, for changes that only reduce risk: a lower loan to value ratio
+    /// or lower deposit and borrow limits, with the rest of the config and the price oracles
+    /// unchanged. Other changes must be proposed with ProposeReserveConfig.
     ///
     /// Accounts expected by this instruction:
     ///
@@ -379,6 +404,571 @@ pub enum LendingInstruction {
         /// Reserve config to update to
         config: ReserveConfig,
     },
+
+    // 17
+    /// Flash borrow reserve liquidity. A matching FlashRepayReserveLiquidity instruction
+    /// must appear later in the same transaction, which is verified with the instructions sysvar.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///                     Must match the reserve liquidity supply.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Minted by reserve liquidity mint.
+    ///   2. `[writable]` Reserve account.
+    ///   3. `[writable]` Lending market account.
+    ///   4. `[]` Derived lending market authority.
+    ///   5. `[]` Instructions sysvar.
+    ///   6. `[]` Token program id.
+    FlashBorrowReserveLiquidity {
+        /// Amount of liquidity to flash borrow
+        liquidity_amount: u64,
+    },
+
+    // 18
+    /// Flash repay reserve liquidity, including the flash loan fee.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source liquidity token account.
+    ///                     $authority can transfer $liquidity_amount plus fees.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Must match the reserve liquidity supply.
+    ///   2. `[writable]` Flash loan fee receiver account.
+    ///                     Must match the reserve liquidity fee receiver.
+    ///   3. `[writable]` Host fee receiver.
+    ///   4. `[writable]` Reserve account.
+    ///   5. `[]` Lending market account.
+    ///   6. `[signer]` User transfer authority ($authority).
+    ///   7. `[]` Instructions sysvar.
+    ///   8. `[]` Token program id.
+    FlashRepayReserveLiquidity {
+        /// Amount of liquidity to repay, excluding fees - must match the flash borrow
+        liquidity_amount: u64,
+        /// Index of the FlashBorrowReserveLiquidity instruction within the transaction
+        borrow_instruction_index: u8,
+    },
+
+    // 19
+    /// Write off bad debt left on an obligation with no remaining deposits. The forgiven
+    /// liquidity is removed from the reserve's borrowed amount, lowering the collateral
+    /// exchange rate so the loss is socialized across depositors.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - refreshed.
+    ///   1. `[writable]` Reserve account - refreshed.
+    ///   2. `[]` Lending market account.
+    ///   3. `[signer]` Lending market owner.
+    ///   4. `[]` Clock sysvar.
+    ForgiveDebt {
+        /// Amount of liquidity to forgive - u64::MAX for 100% of borrowed amount
+        liquidity_amount: u64,
+    },
+
+    // 20
+    /// Sets the guardian of a lending market, which can pause the market and its reserves.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Current owner.
+    SetLendingMarketGuardian {
+        /// The new guardian - the default pubkey to remove the guardian
+        new_guardian: Pubkey,
+    },
+
+    // 21
+    /// Sets the operations paused across a lending market. The guardian can only add
+    /// pause flags, while the owner can also clear them.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner or guardian.
+    SetLendingMarketPauseFlags {
+        /// Bitmask of `PAUSE_*` flags
+        pause_flags: u8,
+    },
+
+    // 22
+    /// Sets the operations paused on a single reserve. The guardian can only add
+    /// pause flags, while the owner can also clear them.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner or guardian.
+    SetReservePauseFlags {
+        /// Bitmask of `PAUSE_*` flags
+        pause_flags: u8,
+    },
+
+    // 23
+    /// Sets the outflow rate limiter config of a lending market, resetting its current window.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    ///   2. `[]` Clock sysvar.
+    SetLendingMarketRateLimiterConfig {
+        /// Rate limiter configuration
+        config: RateLimiterConfig,
+    },
+
+    // 24
+    /// Sets the outflow rate limiter config of a reserve, resetting its current window.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[]` Clock sysvar.
+    SetReserveRateLimiterConfig {
+        /// Rate limiter configuration
+        config: RateLimiterConfig,
+    },
+
+    // 25
+    /// Repay borrowed liquidity using obligation collateral. The collateral is withdrawn,
+    /// redeemed, and swapped into the repay reserve liquidity through spl-token-swap, and the
+    /// swap output is used to repay the borrow. Obligation health is only checked at the end,
+    /// so unhealthy obligations can be deleveraged as long as their health does not worsen.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - refreshed.
+    ///   1. `[writable]` Withdraw reserve account - refreshed.
+    ///   2. `[writable]` Withdraw reserve collateral supply SPL Token account.
+    ///   3. `[writable]` Withdraw reserve collateral SPL Token mint.
+    ///   4. `[writable]` Withdraw reserve liquidity supply SPL Token account.
+    ///   5. `[writable]` User collateral token account.
+    ///                     Minted by withdraw reserve collateral mint.
+    ///                     Receives the withdrawn collateral before it is redeemed.
+    ///   6. `[writable]` User withdraw liquidity token account.
+    ///                     Minted by withdraw reserve liquidity mint.
+    ///                     Receives the redeemed liquidity and is the swap source.
+    ///   7. `[writable]` Repay reserve account - refreshed.
+    ///   8. `[writable]` Repay reserve liquidity supply SPL Token account.
+    ///   9. `[writable]` User repay liquidity token account.
+    ///                     Minted by repay reserve liquidity mint.
+    ///                     Receives the swap output and is the repay source.
+    ///   10 `[writable]` Lending market account.
+    ///   11 `[]` Derived lending market authority.
+    ///   12 `[signer]` Obligation owner, also the authority of the user token accounts.
+    ///   13 `[]` Clock sysvar.
+    ///   14 `[]` Token program id.
+    ///   15 `[]` Token swap program id.
+    ///   16 `[]` Token swap account.
+    ///   17 `[]` Token swap authority.
+    ///   18 `[writable]` Token swap source token account.
+    ///   19 `[writable]` Token swap destination token account.
+    ///   20 `[writable]` Token swap pool mint.
+    ///   21 `[writable]` Token swap pool fee account.
+    RepayWithCollateral {
+        /// Amount of collateral to withdraw and swap - u64::MAX for up to 100% of deposited amount
+        collateral_amount: u64,
+        /// Minimum amount of repay liquidity the swap must output
+        minimum_liquidity_out: u64,
+    },
+
+    // 26
+    /// Open or increase a leveraged position in one instruction. Liquidity is flash borrowed
+    /// from the borrow reserve, swapped into the deposit reserve liquidity through
+    /// spl-token-swap, deposited as obligation collateral, and the flash borrow is settled by
+    /// borrowing against the obligation. The obligation is refreshed after the deposit, and the
+    /// resulting loan to value must not exceed the target, which must be below the deposit reserve
+    /// loan to value ratio.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - refreshed.
+    ///   1. `[writable]` Borrow reserve account - refreshed.
+    ///   2. `[writable]` Borrow reserve liquidity supply SPL Token account.
+    ///   3. `[writable]` Borrow reserve liquidity fee receiver account.
+    ///                     Must be the fee account specified at InitReserve.
+    ///   4. `[writable]` User borrow liquidity token account.
+    ///                     Minted by borrow reserve liquidity mint.
+    ///                     Receives the flash borrowed liquidity and is the swap source.
+    ///   5. `[writable]` Deposit reserve account - refreshed.
+    ///   6. `[writable]` Deposit reserve liquidity supply SPL Token account.
+    ///   7. `[writable]` Deposit reserve collateral SPL Token mint.
+    ///   8. `[writable]` Deposit reserve collateral supply SPL Token account.
+    ///   9. `[]` Deposit reserve liquidity pyth oracle account.
+    ///   10 `[]` Deposit reserve liquidity switchboard oracle account.
+    ///   11 `[writable]` User deposit liquidity token account.
+    ///                     Minted by deposit reserve liquidity mint.
+    ///                     Receives the swap output and is the deposit source.
+    ///   12 `[writable]` User collateral token account.
+    ///                     Minted by deposit reserve collateral mint.
+    ///                     Receives the minted collateral before it is deposited.
+    ///   13 `[writable]` Lending market account.
+    ///   14 `[]` Derived lending market authority.
+    ///   15 `[signer]` Obligation owner, also the authority of the user token accounts.
+    ///   16 `[]` Clock sysvar.
+    ///   17 `[]` Token program id.
+    ///   18 `[]` Token swap program id.
+    ///   19 `[]` Token swap account.
+    ///   20 `[]` Token swap authority.
+    ///   21 `[writable]` Token swap source token account.
+    ///   22 `[writable]` Token swap destination token account.
+    ///   23 `[writable]` Token swap pool mint.
+    ///   24 `[writable]` Token swap pool fee account.
+    ///   25 `[]` Market member account of the obligation owner - expected only if the lending
+    ///             market has an allowlist admin.
+    ///   .. `[]` Deposit reserve oracle accounts, as expected by RefreshReserve, for reserves
+    ///             priced by a token swap or stake pool oracle.
+    ///   .. `[writable]` Obligation deposit reserves then borrow reserves, as expected by
+    ///                     RefreshObligation, including the deposit and borrow reserves - refreshed.
+    ///   .. `[optional, writable]` Host fee receiver account.
+    LeverageDeposit {
+        /// Amount of liquidity to flash borrow and swap
+        liquidity_amount: u64,
+        /// Minimum amount of deposit liquidity the swap must output
+        minimum_liquidity_out: u64,
+        /// Max loan to value of the obligation after the deposit, as a percentage
+        target_ltv: u8,
+    },
+
+    // 27, 28
+    // Unused, token swap and stake pool oracles are set with ProposeReserveConfig and
+    // ApplyReserveConfig
+
+    // 29
+    /// Close an obligation with no deposits or borrows and return its rent to the owner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - refreshed.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer, writable]` Obligation owner, receives the obligation lamports.
+    ///   3. `[]` Clock sysvar.
+    CloseObligation,
+
+    // 30
+    /// Transfer an obligation to a new owner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[signer]` New obligation owner.
+    ///             Only needs to sign when require_new_owner_signature is set.
+    ///   4. `[]` New obligation owner seeded obligation account.
+    ///             Must not be an initialized obligation.
+    ///   5. `[optional]` Market member account of the new obligation owner - required only if
+    ///                     the lending market has an allowlist admin.
+    SetObligationOwner {
+        /// Require the new owner to sign, accepting the obligation
+        require_new_owner_signature: bool,
+    },
+
+    // 31
+    /// Reallocate an obligation to hold more deposits and borrows combined than
+    /// MAX_OBLIGATION_RESERVES. The obligation owner pays the rent for the larger account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer, writable]` Obligation owner.
+    ///   3. `[]` Rent sysvar.
+    ///   4. `[]` System program.
+    ResizeObligation {
+        /// Max number of deposits and borrows combined, up to MAX_EXTENDED_OBLIGATION_RESERVES
+        max_reserves: u8,
+    },
+
+    // 32
+    /// Sets the loan to value ratio and liquidation threshold of an efficiency mode group of a
+    /// lending market, if the change only lowers the loan to value ratio. Other changes must be
+    /// proposed with ProposeLendingMarketEModeGroup.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetLendingMarketEModeGroup {
+        /// Group number, in range [1, MAX_EMODE_GROUPS]
+        group: u8,
+        /// Group parameters
+        config: EModeGroup,
+    },
+
+    // 33
+    /// Proposes a reserve config and price oracles, which can be applied with
+    /// ApplyReserveConfig after RESERVE_CONFIG_DELAY_SLOTS. Replaces any pending proposal for the
+    /// reserve. The lending market owner pays the rent for the pending config account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pending reserve config account - uninitialized or pending.
+    ///                     Derived from the reserve with PENDING_RESERVE_CONFIG_SEED.
+    ///   1. `[]` Reserve account.
+    ///   2. `[]` Lending market account.
+    ///   3. `[signer, writable]` Lending market owner.
+    ///   4. `[]` Pyth product key.
+    ///   5. `[]` Pyth price key.
+    ///   6. `[]` Switchboard key.
+    ///   7. `[]` Token swap account, or the null pubkey to price with the pyth and switchboard
+    ///             oracles. A token swap oracle prices the reserve liquidity as the pool token
+    ///             of a constant product spl-token-swap pool, from the pool balances and the
+    ///             market prices of reserves for both pool tokens.
+    ///   8. `[]` Stake pool account, or the null pubkey to price with the pyth and switchboard
+    ///             oracles directly. A stake pool oracle prices the reserve liquidity as the pool
+    ///             token of an spl-stake-pool, from the pool exchange rate and the SOL price of
+    ///             the pyth and switchboard oracles.
+    ///   9. `[]` Clock sysvar.
+    ///   10. `[]` Rent sysvar.
+    ///   11. `[]` System program.
+    ProposeReserveConfig {
+        /// Reserve config to propose
+        config: ReserveConfig,
+    },
+
+    // 34
+    /// Applies a pending reserve config and price oracles to the reserve once its activation slot
+    /// is reached, and closes the pending config account. Anyone can apply a pending config.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pending reserve config account.
+    ///   1. `[writable]` Reserve account.
+    ///   2. `[]` Lending market account.
+    ///   3. `[writable]` Lending market owner - receives the pending config account rent.
+    ///   4. `[]` Clock sysvar.
+    ApplyReserveConfig,
+
+    // 35
+    /// Proposes a new owner of a lending market, which becomes the owner once it accepts with
+    /// AcceptLendingMarketOwner. Replaces any pending owner, and proposing the default pubkey
+    /// cancels the transfer. Lending markets created before pending owners were stored are
+    /// reallocated, with the current owner paying the additional rent.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer, writable]` Current owner.
+    ///   2. `[]` Rent sysvar.
+    ///   3. `[]` System program.
+    ProposeLendingMarketOwner {
+        /// The proposed owner
+        new_owner: Pubkey,
+    },
+
+    // 36
+    /// Accepts ownership of a lending market proposed with ProposeLendingMarketOwner.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Pending owner.
+    AcceptLendingMarketOwner,
+
+    // 37
+    /// Sets the allowlist admin of a lending market. While a lending market has an allowlist
+    /// admin, InitObligation and DepositReserveLiquidity require a membership issued by the admin
+    /// with AddMarketMember. Lending markets created before allowlist admins were stored are
+    /// reallocated, with the owner paying the additional rent.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer, writable]` Lending market owner.
+    ///   2. `[]` Rent sysvar.
+    ///   3. `[]` System program.
+    SetLendingMarketAllowlistAdmin {
+        /// The new allowlist admin - the default pubkey to open the market to every wallet
+        new_admin: Pubkey,
+    },
+
+    // 38
+    /// Adds a wallet to an allowlisted lending market. The allowlist admin pays the rent for the
+    /// membership account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Market member account - uninitialized.
+    ///                     Derived from the lending market and member with MARKET_MEMBER_SEED.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer, writable]` Allowlist admin.
+    ///   3. `[]` Member wallet.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program.
+    AddMarketMember,
+
+    // 39
+    /// Removes a wallet from an allowlisted lending market and closes its membership account.
+    /// Existing obligations and collateral of the wallet are unaffected.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Market member account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer, writable]` Allowlist admin - receives the membership account rent.
+    RevokeMarketMember,
+
+    // 40
+    /// Borrow liquidity from a reserve at a fixed rate until maturity. The rate is the current
+    /// borrow rate of the reserve plus its fixed rate premium, and accrues simple interest on the
+    /// borrowed amount. Requires a refreshed obligation and reserve, and an obligation without
+    /// other borrows from the reserve.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source borrow reserve liquidity supply SPL Token account.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Minted by borrow reserve liquidity mint.
+    ///   2. `[writable]` Borrow reserve account - refreshed.
+    ///   3. `[writable]` Borrow reserve liquidity fee receiver account.
+    ///                     Must be the fee account specified at InitReserve.
+    ///   4. `[writable]` Obligation account - refreshed.
+    ///   5. `[writable]` Lending market account.
+    ///   6. `[]` Derived lending market authority.
+    ///   7. `[signer]` Obligation owner.
+    ///   8. `[]` Clock sysvar.
+    ///   9. `[]` Token program id.
+    ///   10 `[optional, writable]` Host fee receiver account.
+    BorrowObligationLiquidityFixedTerm {
+        /// Amount of liquidity to borrow - u64::MAX for 100% of borrowing power
+        liquidity_amount: u64,
+        /// Slots until the borrow matures, at most the max fixed term of the reserve
+        term_slots: u64,
+    },
+
+    // 41
+    /// Converts a fixed term borrow into a variable rate borrow once it has matured. Anyone can
+    /// settle a matured borrow.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[writable]` Borrow reserve account - refreshed.
+    ///   2. `[]` Clock sysvar.
+    SettleFixedTermBorrow,
+
+    // 42
+    /// Approves a delegate to borrow liquidity from a reserve against an obligation, replacing
+    /// the allowance of an existing credit delegation. The debt is recorded on the obligation and
+    /// can be repaid by either party. The obligation owner pays the rent for a new credit
+    /// delegation account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Credit delegation account.
+    ///                     Derived from the obligation, delegate and borrow reserve with
+    ///                     CREDIT_DELEGATION_SEED.
+    ///   1. `[]` Obligation account.
+    ///   2. `[]` Borrow reserve account.
+    ///   3. `[signer, writable]` Obligation owner.
+    ///   4. `[]` Delegate wallet.
+    ///   5. `[]` Rent sysvar.
+    ///   6. `[]` System program.
+    ///   7. `[]` Lending market account.
+    ///   8. `[optional]` Market member account of the delegate - required only if the lending
+    ///                     market has an allowlist admin.
+    ApproveCreditDelegation {
+        /// Liquidity the delegate can borrow, including borrow fees
+        borrow_amount: u64,
+    },
+
+    // 43
+    /// Revokes a credit delegation and closes its account. Debt already borrowed by the delegate
+    /// remains on the obligation. The rent is refunded to the obligation owner that approved the
+    /// delegation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Credit delegation account.
+    ///   1. `[]` Obligation account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[writable]` Delegator - receives the credit delegation account rent.
+    RevokeCreditDelegation,
+
+    // 44
+    /// Borrow liquidity from a reserve against an obligation as a delegate approved with
+    /// ApproveCreditDelegation. Requires a refreshed obligation and reserve. Delegations approved
+    /// by a previous obligation owner can't be used.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Source borrow reserve liquidity supply SPL Token account.
+    ///   1. `[writable]` Destination liquidity token account.
+    ///                     Minted by borrow reserve liquidity mint.
+    ///   2. `[writable]` Borrow reserve account - refreshed.
+    ///   3. `[writable]` Borrow reserve liquidity fee receiver account.
+    ///                     Must be the fee account specified at InitReserve.
+    ///   4. `[writable]` Obligation account - refreshed.
+    ///   5. `[writable]` Lending market account.
+    ///   6. `[]` Derived lending market authority.
+    ///   7. `[signer]` Delegate.
+    ///   8. `[writable]` Credit delegation account.
+    ///   9. `[]` Clock sysvar.
+    ///   10 `[]` Token program id.
+    ///   11 `[]` Market member account of the delegate - expected only if the lending market has
+    ///             an allowlist admin.
+    ///   .. `[optional, writable]` Host fee receiver account.
+    BorrowObligationLiquidityDelegated {
+        /// Amount of liquidity to borrow - u64::MAX for up to the remaining allowance
+        liquidity_amount: u64,
+    },
+
+    // 45
+    /// Deprecates a reserve to retire it. Deprecated reserves do not allow deposits or borrows,
+    /// and the loan to value ratio of their deposits decays linearly to zero, while repayments,
+    /// withdrawals, redemptions and liquidations continue. Deprecation cannot be undone.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[]` Clock sysvar.
+    DeprecateReserve {
+        /// Slots over which the loan to value ratio of deposits decays to zero
+        ltv_decay_slots: u64,
+    },
+
+    // 46
+    /// Proposes the loan to value ratio and liquidation threshold of an efficiency mode group of a
+    /// lending market, which can be applied with ApplyLendingMarketEModeGroup after
+    /// RESERVE_CONFIG_DELAY_SLOTS. A liquidation threshold of 0 disables the group. Replaces any
+    /// pending proposal for the group. The lending market owner pays the rent for the pending
+    /// group account.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pending efficiency mode group account - uninitialized or pending.
+    ///                     Derived from the lending market and group with
+    ///                     PENDING_EMODE_GROUP_SEED.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer, writable]` Lending market owner.
+    ///   3. `[]` Clock sysvar.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program.
+    ProposeLendingMarketEModeGroup {
+        /// Group number, in range [1, MAX_EMODE_GROUPS]
+        group: u8,
+        /// Group parameters
+        config: EModeGroup,
+    },
+
+    // 47
+    /// Applies a pending efficiency mode group to the lending market once its activation slot is
+    /// reached, and closes the pending group account. Anyone can apply a pending group.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Pending efficiency mode group account.
+    ///   1. `[writable]` Lending market account.
+    ///   2. `[writable]` Lending market owner - receives the pending group account rent.
+    ///   3. `[]` Clock sysvar.
+    ApplyLendingMarketEModeGroup,
 }
 
 impl LendingInstruction {
@@ -396,10 +986,6 @@ impl LendingInstruction {
                     quote_currency: *quote_currency,
                 }
             }
-            1 => {
-                let (new_owner, _rest) = Self::unpack_pubkey(rest)?;
-                Self::SetLendingMarketOwner { new_owner }
-            }
             2 => {
                 let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
                 let (optimal_utilization_rate, rest) = Self::unpack_u8(rest)?;
@@ -414,7 +1000,14 @@ impl LendingInstruction {
                 let (host_fee_percentage, rest) = Self::unpack_u8(rest)?;
                 let (deposit_limit, rest) = Self::unpack_u64(rest)?;
                 let (borrow_limit, rest) = Self::unpack_u64(rest)?;
-                let (fee_receiver, _) = Self::unpack_pubkey(rest)?;
+                let (fee_receiver, rest) = Self::unpack_pubkey(rest)?;
+                let (isolated, rest) = Self::unpack_bool(rest)?;
+                let (borrow_only, rest) = Self::unpack_bool(rest)?;
+                let (added_borrow_weight_bps, rest) = Self::unpack_u64(rest)?;
+                let (emode_group, rest) = Self::unpack_u8(rest)?;
+                let (max_liquidation_bonus, rest) = Self::unpack_u8(rest)?;
+                let (fixed_rate_premium, rest) = Self::unpack_u8(rest)?;
+                let (max_fixed_term_slots, _rest) = Self::unpack_u64(rest)?;
                 Self::InitReserve {
                     liquidity_amount,
                     config: ReserveConfig {
@@ -433,6 +1026,13 @@ impl LendingInstruction {
                         deposit_limit,
                         borrow_limit,
                         fee_receiver,
+                        isolated,
+                        borrow_only,
+                        added_borrow_weight_bps,
+                        emode_group,
+                        max_liquidation_bonus,
+                        fixed_rate_premium,
+                        max_fixed_term_slots,
                     },
                 }
             }
@@ -479,7 +1079,7 @@ impl LendingInstruction {
                 let (collateral_amount, _rest) = Self::unpack_u64(rest)?;
                 Self::WithdrawObligationCollateralAndRedeemReserveCollateral { collateral_amount }
             }
-            16 => {
+            16 | 33 => {
                 let (optimal_utilization_rate, _rest) = Self::unpack_u8(rest)?;
                 let (loan_to_value_ratio, _rest) = Self::unpack_u8(_rest)?;
                 let (liquidation_bonus, _rest) = Self::unpack_u8(_rest)?;
@@ -492,28 +1092,181 @@ impl LendingInstruction {
                 let (host_fee_percentage, _rest) = Self::unpack_u8(_rest)?;
                 let (deposit_limit, _rest) = Self::unpack_u64(_rest)?;
                 let (borrow_limit, _rest) = Self::unpack_u64(_rest)?;
-                let (fee_receiver, _) = Self::unpack_pubkey(_rest)?;
+                let (fee_receiver, _rest) = Self::unpack_pubkey(_rest)?;
+                let (isolated, _rest) = Self::unpack_bool(_rest)?;
+                let (borrow_only, _rest) = Self::unpack_bool(_rest)?;
+                let (added_borrow_weight_bps, _rest) = Self::unpack_u64(_rest)?;
+                let (emode_group, _rest) = Self::unpack_u8(_rest)?;
+                let (max_liquidation_bonus, _rest) = Self::unpack_u8(_rest)?;
+                let (fixed_rate_premium, _rest) = Self::unpack_u8(_rest)?;
+                let (max_fixed_term_slots, _rest) = Self::unpack_u64(_rest)?;
 
-                Self::UpdateReserveConfig {
-                    config: ReserveConfig {
-                        optimal_utilization_rate,
-                        loan_to_value_ratio,
-                        liquidation_bonus,
-                        liquidation_threshold,
-                        min_borrow_rate,
-                        optimal_borrow_rate,
-                        max_borrow_rate,
-                        fees: ReserveFees {
-                            borrow_fee_wad,
-                            flash_loan_fee_wad,
-                            host_fee_percentage,
-                        },
-                        deposit_limit,
-                        borrow_limit,
-                        fee_receiver,
+                let config = ReserveConfig {
+                    optimal_utilization_rate,
+                    loan_to_value_ratio,
+                    liquidation_bonus,
+                    liquidation_threshold,
+                    min_borrow_rate,
+                    optimal_borrow_rate,
+                    max_borrow_rate,
+                    fees: ReserveFees {
+                        borrow_fee_wad,
+                        flash_loan_fee_wad,
+                        host_fee_percentage,
+                    },
+                    deposit_limit,
+                    borrow_limit,
+                    fee_receiver,
+                    isolated,
+                    borrow_only,
+                    added_borrow_weight_bps,
+                    emode_group,
+                    max_liquidation_bonus,
+                    fixed_rate_premium,
+                    max_fixed_term_slots,
+                };
+                if tag == 16 {
+                    Self::UpdateReserveConfig { config }
+                } else {
+                    Self::ProposeReserveConfig { config }
+                }
+            }
+            17 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::FlashBorrowReserveLiquidity { liquidity_amount }
+            }
+            18 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (borrow_instruction_index, _rest) = Self::unpack_u8(rest)?;
+                Self::FlashRepayReserveLiquidity {
+                    liquidity_amount,
+                    borrow_instruction_index,
+                }
+            }
+            19 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::ForgiveDebt { liquidity_amount }
+            }
+            20 => {
+                let (new_guardian, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetLendingMarketGuardian { new_guardian }
+            }
+            21 => {
+                let (pause_flags, _rest) = Self::unpack_u8(rest)?;
+                Self::SetLendingMarketPauseFlags { pause_flags }
+            }
+            22 => {
+                let (pause_flags, _rest) = Self::unpack_u8(rest)?;
+                Self::SetReservePauseFlags { pause_flags }
+            }
+            23 => {
+                let (window_duration, rest) = Self::unpack_u64(rest)?;
+                let (max_outflow, _rest) = Self::unpack_u64(rest)?;
+                Self::SetLendingMarketRateLimiterConfig {
+                    config: RateLimiterConfig {
+                        window_duration,
+                        max_outflow,
+                    },
+                }
+            }
+            24 => {
+                let (window_duration, rest) = Self::unpack_u64(rest)?;
+                let (max_outflow, _rest) = Self::unpack_u64(rest)?;
+                Self::SetReserveRateLimiterConfig {
+                    config: RateLimiterConfig {
+                        window_duration,
+                        max_outflow,
+                    },
+                }
+            }
+            25 => {
+                let (collateral_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_liquidity_out, _rest) = Self::unpack_u64(rest)?;
+                Self::RepayWithCollateral {
+                    collateral_amount,
+                    minimum_liquidity_out,
+                }
+            }
+            26 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_liquidity_out, rest) = Self::unpack_u64(rest)?;
+                let (target_ltv, _rest) = Self::unpack_u8(rest)?;
+                Self::LeverageDeposit {
+                    liquidity_amount,
+                    minimum_liquidity_out,
+                    target_ltv,
+                }
+            }
+            29 => Self::CloseObligation,
+            30 => {
+                let (require_new_owner_signature, _rest) = Self::unpack_bool(rest)?;
+                Self::SetObligationOwner {
+                    require_new_owner_signature,
+                }
+            }
+            31 => {
+                let (max_reserves, _rest) = Self::unpack_u8(rest)?;
+                Self::ResizeObligation { max_reserves }
+            }
+            32 => {
+                let (group, rest) = Self::unpack_u8(rest)?;
+                let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
+                let (liquidation_threshold, _rest) = Self::unpack_u8(rest)?;
+                Self::SetLendingMarketEModeGroup {
+                    group,
+                    config: EModeGroup {
+                        loan_to_value_ratio,
+                        liquidation_threshold,
+                    },
+                }
+            }
+            34 => Self::ApplyReserveConfig,
+            35 => {
+                let (new_owner, _rest) = Self::unpack_pubkey(rest)?;
+                Self::ProposeLendingMarketOwner { new_owner }
+            }
+            36 => Self::AcceptLendingMarketOwner,
+            37 => {
+                let (new_admin, _rest) = Self::unpack_pubkey(rest)?;
+                Self::SetLendingMarketAllowlistAdmin { new_admin }
+            }
+            38 => Self::AddMarketMember,
+            39 => Self::RevokeMarketMember,
+            40 => {
+                let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
+                let (term_slots, _rest) = Self::unpack_u64(rest)?;
+                Self::BorrowObligationLiquidityFixedTerm {
+                    liquidity_amount,
+                    term_slots,
+                }
+            }
+            41 => Self::SettleFixedTermBorrow,
+            42 => {
+                let (borrow_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::ApproveCreditDelegation { borrow_amount }
+            }
+            43 => Self::RevokeCreditDelegation,
+            44 => {
+                let (liquidity_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::BorrowObligationLiquidityDelegated { liquidity_amount }
+            }
+            45 => {
+                let (ltv_decay_slots, _rest) = Self::unpack_u64(rest)?;
+                Self::DeprecateReserve { ltv_decay_slots }
+            }
+            46 => {
+                let (group, rest) = Self::unpack_u8(rest)?;
+                let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
+                let (liquidation_threshold, _rest) = Self::unpack_u8(rest)?;
+                Self::ProposeLendingMarketEModeGroup {
+                    group,
+                    config: EModeGroup {
+                        loan_to_value_ratio,
+                        liquidation_threshold,
                     },
                 }
             }
+            47 => Self::ApplyLendingMarketEModeGroup,
             _ => {
                 msg!("Instruction cannot be unpacked");
                 return Err(LendingError::InstructionUnpackError.into());
@@ -549,6 +1302,18 @@ impl LendingInstruction {
         Ok((value, rest))
     }
 
+    fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
+        let (value, rest) = Self::unpack_u8(input)?;
+        match value {
+            0 => Ok((false, rest)),
+            1 => Ok((true, rest)),
+            _ => {
+                msg!("bool cannot be unpacked");
+                Err(LendingError::InstructionUnpackError.into())
+            }
+        }
+    }
+
     fn unpack_bytes32(input: &[u8]) -> Result<(&[u8; 32], &[u8]), ProgramError> {
         if input.len() < 32 {
             msg!("32 bytes cannot be unpacked");
@@ -585,10 +1350,6 @@ impl LendingInstruction {
                 buf.extend_from_slice(owner.as_ref());
                 buf.extend_from_slice(quote_currency.as_ref());
             }
-            Self::SetLendingMarketOwner { new_owner } => {
-                buf.push(1);
-                buf.extend_from_slice(new_owner.as_ref());
-            }
             Self::InitReserve {
                 liquidity_amount,
                 config:
@@ -609,6 +1370,13 @@ impl LendingInstruction {
                         deposit_limit,
                         borrow_limit,
                         fee_receiver,
+                        isolated,
+                        borrow_only,
+                        added_borrow_weight_bps,
+                        emode_group,
+                        max_liquidation_bonus,
+                        fixed_rate_premium,
+                        max_fixed_term_slots,
                     },
             } => {
                 buf.push(2);
@@ -626,6 +1394,13 @@ impl LendingInstruction {
                 buf.extend_from_slice(&deposit_limit.to_le_bytes());
                 buf.extend_from_slice(&borrow_limit.to_le_bytes());
                 buf.extend_from_slice(&fee_receiver.to_bytes());
+                buf.push(isolated as u8);
+                buf.push(borrow_only as u8);
+                buf.extend_from_slice(&added_borrow_weight_bps.to_le_bytes());
+                buf.extend_from_slice(&emode_group.to_le_bytes());
+                buf.extend_from_slice(&max_liquidation_bonus.to_le_bytes());
+                buf.extend_from_slice(&fixed_rate_premium.to_le_bytes());
+                buf.extend_from_slice(&max_fixed_term_slots.to_le_bytes());
             }
             Self::RefreshReserve => {
                 buf.push(3);
@@ -676,8 +1451,12 @@ impl LendingInstruction {
                 buf.push(15);
                 buf.extend_from_slice(&collateral_amount.to_le_bytes());
             }
-            Self::UpdateReserveConfig { config } => {
-                buf.push(16);
+            Self::UpdateReserveConfig { config } | Self::ProposeReserveConfig { config } => {
+                buf.push(if matches!(self, Self::UpdateReserveConfig { .. }) {
+                    16
+                } else {
+                    33
+                });
                 buf.extend_from_slice(&config.optimal_utilization_rate.to_le_bytes());
                 buf.extend_from_slice(&config.loan_to_value_ratio.to_le_bytes());
                 buf.extend_from_slice(&config.liquidation_bonus.to_le_bytes());
@@ -691,6 +1470,143 @@ impl LendingInstruction {
                 buf.extend_from_slice(&config.deposit_limit.to_le_bytes());
                 buf.extend_from_slice(&config.borrow_limit.to_le_bytes());
                 buf.extend_from_slice(&config.fee_receiver.to_bytes());
+                buf.push(config.isolated as u8);
+                buf.push(config.borrow_only as u8);
+                buf.extend_from_slice(&config.added_borrow_weight_bps.to_le_bytes());
+                buf.extend_from_slice(&config.emode_group.to_le_bytes());
+                buf.extend_from_slice(&config.max_liquidation_bonus.to_le_bytes());
+                buf.extend_from_slice(&config.fixed_rate_premium.to_le_bytes());
+                buf.extend_from_slice(&config.max_fixed_term_slots.to_le_bytes());
+            }
+            Self::FlashBorrowReserveLiquidity { liquidity_amount } => {
+                buf.push(17);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::FlashRepayReserveLiquidity {
+                liquidity_amount,
+                borrow_instruction_index,
+            } => {
+                buf.push(18);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&borrow_instruction_index.to_le_bytes());
+            }
+            Self::ForgiveDebt { liquidity_amount } => {
+                buf.push(19);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::SetLendingMarketGuardian { new_guardian } => {
+                buf.push(20);
+                buf.extend_from_slice(new_guardian.as_ref());
+            }
+            Self::SetLendingMarketPauseFlags { pause_flags } => {
+                buf.push(21);
+                buf.extend_from_slice(&pause_flags.to_le_bytes());
+            }
+            Self::SetReservePauseFlags { pause_flags } => {
+                buf.push(22);
+                buf.extend_from_slice(&pause_flags.to_le_bytes());
+            }
+            Self::SetLendingMarketRateLimiterConfig { config } => {
+                buf.push(23);
+                buf.extend_from_slice(&config.window_duration.to_le_bytes());
+                buf.extend_from_slice(&config.max_outflow.to_le_bytes());
+            }
+            Self::SetReserveRateLimiterConfig { config } => {
+                buf.push(24);
+                buf.extend_from_slice(&config.window_duration.to_le_bytes());
+                buf.extend_from_slice(&config.max_outflow.to_le_bytes());
+            }
+            Self::RepayWithCollateral {
+                collateral_amount,
+                minimum_liquidity_out,
+            } => {
+                buf.push(25);
+                buf.extend_from_slice(&collateral_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_liquidity_out.to_le_bytes());
+            }
+            Self::LeverageDeposit {
+                liquidity_amount,
+                minimum_liquidity_out,
+                target_ltv,
+            } => {
+                buf.push(26);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_liquidity_out.to_le_bytes());
+                buf.extend_from_slice(&target_ltv.to_le_bytes());
+            }
+            Self::CloseObligation => {
+                buf.push(29);
+            }
+            Self::SetObligationOwner {
+                require_new_owner_signature,
+            } => {
+                buf.push(30);
+                buf.push(require_new_owner_signature as u8);
+            }
+            Self::ResizeObligation { max_reserves } => {
+                buf.push(31);
+                buf.extend_from_slice(&max_reserves.to_le_bytes());
+            }
+            Self::SetLendingMarketEModeGroup { group, config } => {
+                buf.push(32);
+                buf.extend_from_slice(&group.to_le_bytes());
+                buf.extend_from_slice(&config.loan_to_value_ratio.to_le_bytes());
+                buf.extend_from_slice(&config.liquidation_threshold.to_le_bytes());
+            }
+            Self::ApplyReserveConfig => {
+                buf.push(34);
+            }
+            Self::ProposeLendingMarketOwner { new_owner } => {
+                buf.push(35);
+                buf.extend_from_slice(new_owner.as_ref());
+            }
+            Self::AcceptLendingMarketOwner => {
+                buf.push(36);
+            }
+            Self::SetLendingMarketAllowlistAdmin { new_admin } => {
+                buf.push(37);
+                buf.extend_from_slice(new_admin.as_ref());
+            }
+            Self::AddMarketMember => {
+                buf.push(38);
+            }
+            Self::RevokeMarketMember => {
+                buf.push(39);
+            }
+            Self::BorrowObligationLiquidityFixedTerm {
+                liquidity_amount,
+                term_slots,
+            } => {
+                buf.push(40);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+                buf.extend_from_slice(&term_slots.to_le_bytes());
+            }
+            Self::SettleFixedTermBorrow => {
+                buf.push(41);
+            }
+            Self::ApproveCreditDelegation { borrow_amount } => {
+                buf.push(42);
+                buf.extend_from_slice(&borrow_amount.to_le_bytes());
+            }
+            Self::RevokeCreditDelegation => {
+                buf.push(43);
+            }
+            Self::BorrowObligationLiquidityDelegated { liquidity_amount } => {
+                buf.push(44);
+                buf.extend_from_slice(&liquidity_amount.to_le_bytes());
+            }
+            Self::DeprecateReserve { ltv_decay_slots } => {
+                buf.push(45);
+                buf.extend_from_slice(&ltv_decay_slots.to_le_bytes());
+            }
+            Self::ProposeLendingMarketEModeGroup { group, config } => {
+                buf.push(46);
+                buf.extend_from_slice(&group.to_le_bytes());
+                buf.extend_from_slice(&config.loan_to_value_ratio.to_le_bytes());
+                buf.extend_from_slice(&config.liquidation_threshold.to_le_bytes());
+            }
+            Self::ApplyLendingMarketEModeGroup => {
+                buf.push(47);
             }
         }
         buf
@@ -723,8 +1639,8 @@ pub fn init_lending_market(
     }
 }
 
-/// Creates a 'SetLendingMarketOwner' instruction.
-pub fn set_lending_market_owner(
+/// Creates a 'ProposeLendingMarketOwner' instruction.
+pub fn propose_lending_market_owner(
     program_id: Pubkey,
     lending_market_pubkey: Pubkey,
     lending_market_owner: Pubkey,
@@ -734,9 +1650,89 @@ pub fn set_lending_market_owner(
         program_id,
         accounts: vec![
             AccountMeta::new(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_owner, true),
+            AccountMeta::new(lending_market_owner, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ProposeLendingMarketOwner { new_owner }.pack(),
+    }
+}
+
+/// Creates an 'AcceptLendingMarketOwner' instruction.
+pub fn accept_lending_market_owner(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    pending_owner: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(pending_owner, true),
+        ],
+        data: LendingInstruction::AcceptLendingMarketOwner.pack(),
+    }
+}
+
+/// Creates a 'SetLendingMarketAllowlistAdmin' instruction.
+pub fn set_lending_market_allowlist_admin(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    new_admin: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_owner, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::SetLendingMarketAllowlistAdmin { new_admin }.pack(),
+    }
+}
+
+/// Creates an 'AddMarketMember' instruction.
+pub fn add_market_member(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    allowlist_admin_pubkey: Pubkey,
+    member_pubkey: Pubkey,
+) -> Instruction {
+    let (market_member_pubkey, _bump_seed) =
+        market_member_address(&program_id, &lending_market_pubkey, &member_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(market_member_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(allowlist_admin_pubkey, true),
+            AccountMeta::new_readonly(member_pubkey, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::AddMarketMember.pack(),
+    }
+}
+
+/// Creates a 'RevokeMarketMember' instruction.
+pub fn revoke_market_member(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    allowlist_admin_pubkey: Pubkey,
+    member_pubkey: Pubkey,
+) -> Instruction {
+    let (market_member_pubkey, _bump_seed) =
+        market_member_address(&program_id, &lending_market_pubkey, &member_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(market_member_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(allowlist_admin_pubkey, true),
         ],
-        data: LendingInstruction::SetLendingMarketOwner { new_owner }.pack(),
+        data: LendingInstruction::RevokeMarketMember.pack(),
     }
 }
 
@@ -815,6 +1811,57 @@ pub fn refresh_reserve(
     }
 }
 
+/// Creates a `RefreshReserve` instruction for a reserve priced by a token swap oracle
+#[allow(clippy::too_many_arguments)]
+pub fn refresh_reserve_with_token_swap_oracle(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_pyth_oracle_pubkey: Pubkey,
+    reserve_liquidity_switchboard_oracle_pubkey: Pubkey,
+    token_swap_pubkey: Pubkey,
+    token_swap_token_a_pubkey: Pubkey,
+    token_swap_token_b_pubkey: Pubkey,
+    token_swap_pool_mint_pubkey: Pubkey,
+    token_a_reserve_pubkey: Pubkey,
+    token_b_reserve_pubkey: Pubkey,
+) -> Instruction {
+    let mut instruction = refresh_reserve(
+        program_id,
+        reserve_pubkey,
+        reserve_liquidity_pyth_oracle_pubkey,
+        reserve_liquidity_switchboard_oracle_pubkey,
+    );
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(token_swap_pubkey, false),
+        AccountMeta::new_readonly(token_swap_token_a_pubkey, false),
+        AccountMeta::new_readonly(token_swap_token_b_pubkey, false),
+        AccountMeta::new_readonly(token_swap_pool_mint_pubkey, false),
+        AccountMeta::new_readonly(token_a_reserve_pubkey, false),
+        AccountMeta::new_readonly(token_b_reserve_pubkey, false),
+    ]);
+    instruction
+}
+
+/// Creates a `RefreshReserve` instruction for a reserve priced by a stake pool oracle
+pub fn refresh_reserve_with_stake_pool_oracle(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_pyth_oracle_pubkey: Pubkey,
+    reserve_liquidity_switchboard_oracle_pubkey: Pubkey,
+    stake_pool_pubkey: Pubkey,
+) -> Instruction {
+    let mut instruction = refresh_reserve(
+        program_id,
+        reserve_pubkey,
+        reserve_liquidity_pyth_oracle_pubkey,
+        reserve_liquidity_switchboard_oracle_pubkey,
+    );
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(stake_pool_pubkey, false));
+    instruction
+}
+
 /// Creates a 'DepositReserveLiquidity' instruction.
 #[allow(clippy::too_many_arguments)]
 pub fn deposit_reserve_liquidity(
@@ -827,25 +1874,30 @@ pub fn deposit_reserve_liquidity(
     reserve_collateral_mint_pubkey: Pubkey,
     lending_market_pubkey: Pubkey,
     user_transfer_authority_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
 ) -> Instruction {
     let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
         &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
         &program_id,
     );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_collateral_pubkey, false),
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new(reserve_collateral_mint_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(source_liquidity_pubkey, false),
-            AccountMeta::new(destination_collateral_pubkey, false),
-            AccountMeta::new(reserve_pubkey, false),
-            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new(reserve_collateral_mint_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+        accounts,
         data: LendingInstruction::DepositReserveLiquidity { liquidity_amount }.pack(),
     }
 }
@@ -875,7 +1927,7 @@ pub fn redeem_reserve_collateral(
             AccountMeta::new(reserve_pubkey, false),
             AccountMeta::new(reserve_collateral_mint_pubkey, false),
             AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_pubkey, false),
             AccountMeta::new_readonly(lending_market_authority_pubkey, false),
             AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
             AccountMeta::new_readonly(sysvar::clock::id(), false),
@@ -892,17 +1944,22 @@ pub fn init_obligation(
     obligation_pubkey: Pubkey,
     lending_market_pubkey: Pubkey,
     obligation_owner_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
 ) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(obligation_owner_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+        accounts,
         data: LendingInstruction::InitObligation.pack(),
     }
 }
@@ -921,7 +1978,7 @@ pub fn refresh_obligation(
     accounts.extend(
         reserve_pubkeys
             .into_iter()
-            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+            .map(|pubkey| AccountMeta::new(pubkey, false)),
     );
     Instruction {
         program_id,
@@ -977,30 +2034,35 @@ pub fn deposit_reserve_liquidity_and_obligation_collateral(
     reserve_liquidity_pyth_oracle_pubkey: Pubkey,
     reserve_liquidity_switchboard_oracle_pubkey: Pubkey,
     user_transfer_authority_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
 ) -> Instruction {
     let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
         &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
         &program_id,
     );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(user_collateral_pubkey, false),
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new(reserve_collateral_mint_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new(destination_deposit_collateral_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(reserve_liquidity_pyth_oracle_pubkey, false),
+        AccountMeta::new_readonly(reserve_liquidity_switchboard_oracle_pubkey, false),
+        AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(source_liquidity_pubkey, false),
-            AccountMeta::new(user_collateral_pubkey, false),
-            AccountMeta::new(reserve_pubkey, false),
-            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
-            AccountMeta::new(reserve_collateral_mint_pubkey, false),
-            AccountMeta::new_readonly(lending_market_pubkey, false),
-            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
-            AccountMeta::new(destination_deposit_collateral_pubkey, false),
-            AccountMeta::new(obligation_pubkey, false),
-            AccountMeta::new(obligation_owner_pubkey, true),
-            AccountMeta::new_readonly(reserve_liquidity_pyth_oracle_pubkey, false),
-            AccountMeta::new_readonly(reserve_liquidity_switchboard_oracle_pubkey, false),
-            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
+        accounts,
         data: LendingInstruction::DepositReserveLiquidityAndObligationCollateral {
             liquidity_amount,
         }
@@ -1065,7 +2127,7 @@ pub fn borrow_obligation_liquidity(
         AccountMeta::new(borrow_reserve_pubkey, false),
         AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
         AccountMeta::new(obligation_pubkey, false),
-        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new(lending_market_pubkey, false),
         AccountMeta::new_readonly(lending_market_authority_pubkey, false),
         AccountMeta::new_readonly(obligation_owner_pubkey, true),
         AccountMeta::new_readonly(sysvar::clock::id(), false),
@@ -1081,14 +2143,182 @@ pub fn borrow_obligation_liquidity(
     }
 }
 
-/// Creates a `RepayObligationLiquidity` instruction
+/// Creates a `BorrowObligationLiquidityFixedTerm` instruction
 #[allow(clippy::too_many_arguments)]
-pub fn repay_obligation_liquidity(
+pub fn borrow_obligation_liquidity_fixed_term(
     program_id: Pubkey,
     liquidity_amount: u64,
+    term_slots: u64,
     source_liquidity_pubkey: Pubkey,
     destination_liquidity_pubkey: Pubkey,
-    repay_reserve_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let mut instruction = borrow_obligation_liquidity(
+        program_id,
+        liquidity_amount,
+        source_liquidity_pubkey,
+        destination_liquidity_pubkey,
+        borrow_reserve_pubkey,
+        borrow_reserve_liquidity_fee_receiver_pubkey,
+        obligation_pubkey,
+        lending_market_pubkey,
+        obligation_owner_pubkey,
+        host_fee_receiver_pubkey,
+    );
+    instruction.data = LendingInstruction::BorrowObligationLiquidityFixedTerm {
+        liquidity_amount,
+        term_slots,
+    }
+    .pack();
+    instruction
+}
+
+/// Creates a `SettleFixedTermBorrow` instruction
+pub fn settle_fixed_term_borrow(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new(borrow_reserve_pubkey, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::SettleFixedTermBorrow.pack(),
+    }
+}
+
+/// Creates an 'ApproveCreditDelegation' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn approve_credit_delegation(
+    program_id: Pubkey,
+    borrow_amount: u64,
+    obligation_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    delegate_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &program_id,
+        &obligation_pubkey,
+        &delegate_pubkey,
+        &borrow_reserve_pubkey,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(credit_delegation_pubkey, false),
+        AccountMeta::new_readonly(obligation_pubkey, false),
+        AccountMeta::new_readonly(borrow_reserve_pubkey, false),
+        AccountMeta::new(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(delegate_pubkey, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::ApproveCreditDelegation { borrow_amount }.pack(),
+    }
+}
+
+/// Creates a 'RevokeCreditDelegation' instruction.
+pub fn revoke_credit_delegation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    delegate_pubkey: Pubkey,
+    delegator_pubkey: Pubkey,
+) -> Instruction {
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &program_id,
+        &obligation_pubkey,
+        &delegate_pubkey,
+        &borrow_reserve_pubkey,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(credit_delegation_pubkey, false),
+            AccountMeta::new_readonly(obligation_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(delegator_pubkey, false),
+        ],
+        data: LendingInstruction::RevokeCreditDelegation.pack(),
+    }
+}
+
+/// Creates a `BorrowObligationLiquidityDelegated` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn borrow_obligation_liquidity_delegated(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    delegate_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &program_id,
+        &obligation_pubkey,
+        &delegate_pubkey,
+        &borrow_reserve_pubkey,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(source_liquidity_pubkey, false),
+        AccountMeta::new(destination_liquidity_pubkey, false),
+        AccountMeta::new(borrow_reserve_pubkey, false),
+        AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(delegate_pubkey, true),
+        AccountMeta::new(credit_delegation_pubkey, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
+    if let Some(host_fee_receiver_pubkey) = host_fee_receiver_pubkey {
+        accounts.push(AccountMeta::new(host_fee_receiver_pubkey, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::BorrowObligationLiquidityDelegated { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `RepayObligationLiquidity` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn repay_obligation_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
     obligation_pubkey: Pubkey,
     lending_market_pubkey: Pubkey,
     user_transfer_authority_pubkey: Pubkey,
@@ -1172,7 +2402,7 @@ pub fn flash_loan(
         AccountMeta::new(reserve_pubkey, false),
         AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
         AccountMeta::new(host_fee_receiver_pubkey, false),
-        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new(lending_market_pubkey, false),
         AccountMeta::new_readonly(lending_market_authority_pubkey, false),
         AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(flash_loan_receiver_program_id, false),
@@ -1216,3 +2446,553 @@ pub fn update_reserve_config(
         data: LendingInstruction::UpdateReserveConfig { config }.pack(),
     }
 }
+
+/// Creates a `ProposeReserveConfig` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn propose_reserve_config(
+    program_id: Pubkey,
+    config: ReserveConfig,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+    pyth_product_pubkey: Pubkey,
+    pyth_price_pubkey: Pubkey,
+    switchboard_feed_pubkey: Pubkey,
+    token_swap_oracle_pubkey: Option<Pubkey>,
+    stake_pool_oracle_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (pending_reserve_config_pubkey, _bump_seed) =
+        pending_reserve_config_address(&program_id, &reserve_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pending_reserve_config_pubkey, false),
+            AccountMeta::new_readonly(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(pyth_product_pubkey, false),
+            AccountMeta::new_readonly(pyth_price_pubkey, false),
+            AccountMeta::new_readonly(switchboard_feed_pubkey, false),
+            AccountMeta::new_readonly(token_swap_oracle_pubkey.unwrap_or(NULL_PUBKEY), false),
+            AccountMeta::new_readonly(stake_pool_oracle_pubkey.unwrap_or(NULL_PUBKEY), false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ProposeReserveConfig { config }.pack(),
+    }
+}
+
+/// Creates an `ApplyReserveConfig` instruction
+pub fn apply_reserve_config(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (pending_reserve_config_pubkey, _bump_seed) =
+        pending_reserve_config_address(&program_id, &reserve_pubkey);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pending_reserve_config_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_owner_pubkey, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::ApplyReserveConfig.pack(),
+    }
+}
+
+/// Creates a 'DeprecateReserve' instruction.
+pub fn deprecate_reserve(
+    program_id: Pubkey,
+    ltv_decay_slots: u64,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::DeprecateReserve { ltv_decay_slots }.pack(),
+    }
+}
+
+/// Creates a `FlashBorrowReserveLiquidity` instruction.
+pub fn flash_borrow_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `FlashRepayReserveLiquidity` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn flash_repay_reserve_liquidity(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+    source_liquidity_pubkey: Pubkey,
+    destination_liquidity_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    host_fee_receiver_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(source_liquidity_pubkey, false),
+            AccountMeta::new(destination_liquidity_pubkey, false),
+            AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(host_fee_receiver_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `ForgiveDebt` instruction.
+pub fn forgive_debt(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    obligation_pubkey: Pubkey,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::ForgiveDebt { liquidity_amount }.pack(),
+    }
+}
+
+/// Creates a `SetLendingMarketGuardian` instruction.
+pub fn set_lending_market_guardian(
+    program_id: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner: Pubkey,
+    new_guardian: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner, true),
+        ],
+        data: LendingInstruction::SetLendingMarketGuardian { new_guardian }.pack(),
+    }
+}
+
+/// Creates a `SetLendingMarketPauseFlags` instruction.
+pub fn set_lending_market_pause_flags(
+    program_id: Pubkey,
+    pause_flags: u8,
+    lending_market_pubkey: Pubkey,
+    pause_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(pause_authority, true),
+        ],
+        data: LendingInstruction::SetLendingMarketPauseFlags { pause_flags }.pack(),
+    }
+}
+
+/// Creates a `SetReservePauseFlags` instruction.
+pub fn set_reserve_pause_flags(
+    program_id: Pubkey,
+    pause_flags: u8,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    pause_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(pause_authority, true),
+        ],
+        data: LendingInstruction::SetReservePauseFlags { pause_flags }.pack(),
+    }
+}
+
+/// Creates a `SetLendingMarketRateLimiterConfig` instruction.
+pub fn set_lending_market_rate_limiter_config(
+    program_id: Pubkey,
+    config: RateLimiterConfig,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::SetLendingMarketRateLimiterConfig { config }.pack(),
+    }
+}
+
+/// Creates a `SetReserveRateLimiterConfig` instruction.
+pub fn set_reserve_rate_limiter_config(
+    program_id: Pubkey,
+    config: RateLimiterConfig,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::SetReserveRateLimiterConfig { config }.pack(),
+    }
+}
+
+/// Creates a `RepayWithCollateral` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn repay_with_collateral(
+    program_id: Pubkey,
+    collateral_amount: u64,
+    minimum_liquidity_out: u64,
+    obligation_pubkey: Pubkey,
+    withdraw_reserve_pubkey: Pubkey,
+    withdraw_reserve_collateral_supply_pubkey: Pubkey,
+    withdraw_reserve_collateral_mint_pubkey: Pubkey,
+    withdraw_reserve_liquidity_supply_pubkey: Pubkey,
+    user_collateral_pubkey: Pubkey,
+    user_withdraw_liquidity_pubkey: Pubkey,
+    repay_reserve_pubkey: Pubkey,
+    repay_reserve_liquidity_supply_pubkey: Pubkey,
+    user_repay_liquidity_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    token_swap_program_id: Pubkey,
+    token_swap_pubkey: Pubkey,
+    token_swap_authority_pubkey: Pubkey,
+    token_swap_source_pubkey: Pubkey,
+    token_swap_destination_pubkey: Pubkey,
+    token_swap_pool_mint_pubkey: Pubkey,
+    token_swap_pool_fee_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new(withdraw_reserve_pubkey, false),
+            AccountMeta::new(withdraw_reserve_collateral_supply_pubkey, false),
+            AccountMeta::new(withdraw_reserve_collateral_mint_pubkey, false),
+            AccountMeta::new(withdraw_reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(user_collateral_pubkey, false),
+            AccountMeta::new(user_withdraw_liquidity_pubkey, false),
+            AccountMeta::new(repay_reserve_pubkey, false),
+            AccountMeta::new(repay_reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new(user_repay_liquidity_pubkey, false),
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(token_swap_program_id, false),
+            AccountMeta::new_readonly(token_swap_pubkey, false),
+            AccountMeta::new_readonly(token_swap_authority_pubkey, false),
+            AccountMeta::new(token_swap_source_pubkey, false),
+            AccountMeta::new(token_swap_destination_pubkey, false),
+            AccountMeta::new(token_swap_pool_mint_pubkey, false),
+            AccountMeta::new(token_swap_pool_fee_pubkey, false),
+        ],
+        data: LendingInstruction::RepayWithCollateral {
+            collateral_amount,
+            minimum_liquidity_out,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `LeverageDeposit` instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn leverage_deposit(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    minimum_liquidity_out: u64,
+    target_ltv: u8,
+    obligation_pubkey: Pubkey,
+    borrow_reserve_pubkey: Pubkey,
+    borrow_reserve_liquidity_supply_pubkey: Pubkey,
+    borrow_reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    user_borrow_liquidity_pubkey: Pubkey,
+    deposit_reserve_pubkey: Pubkey,
+    deposit_reserve_liquidity_supply_pubkey: Pubkey,
+    deposit_reserve_collateral_mint_pubkey: Pubkey,
+    deposit_reserve_collateral_supply_pubkey: Pubkey,
+    deposit_reserve_liquidity_pyth_oracle_pubkey: Pubkey,
+    deposit_reserve_liquidity_switchboard_oracle_pubkey: Pubkey,
+    user_deposit_liquidity_pubkey: Pubkey,
+    user_collateral_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    token_swap_program_id: Pubkey,
+    token_swap_pubkey: Pubkey,
+    token_swap_authority_pubkey: Pubkey,
+    token_swap_source_pubkey: Pubkey,
+    token_swap_destination_pubkey: Pubkey,
+    token_swap_pool_mint_pubkey: Pubkey,
+    token_swap_pool_fee_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
+    deposit_reserve_oracle_pubkeys: Vec<Pubkey>,
+    obligation_reserve_pubkeys: Vec<Pubkey>,
+    host_fee_receiver_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    let mut accounts = vec![
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new(borrow_reserve_pubkey, false),
+        AccountMeta::new(borrow_reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new(borrow_reserve_liquidity_fee_receiver_pubkey, false),
+        AccountMeta::new(user_borrow_liquidity_pubkey, false),
+        AccountMeta::new(deposit_reserve_pubkey, false),
+        AccountMeta::new(deposit_reserve_liquidity_supply_pubkey, false),
+        AccountMeta::new(deposit_reserve_collateral_mint_pubkey, false),
+        AccountMeta::new(deposit_reserve_collateral_supply_pubkey, false),
+        AccountMeta::new_readonly(deposit_reserve_liquidity_pyth_oracle_pubkey, false),
+        AccountMeta::new_readonly(deposit_reserve_liquidity_switchboard_oracle_pubkey, false),
+        AccountMeta::new(user_deposit_liquidity_pubkey, false),
+        AccountMeta::new(user_collateral_pubkey, false),
+        AccountMeta::new(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(token_swap_program_id, false),
+        AccountMeta::new_readonly(token_swap_pubkey, false),
+        AccountMeta::new_readonly(token_swap_authority_pubkey, false),
+        AccountMeta::new(token_swap_source_pubkey, false),
+        AccountMeta::new(token_swap_destination_pubkey, false),
+        AccountMeta::new(token_swap_pool_mint_pubkey, false),
+        AccountMeta::new(token_swap_pool_fee_pubkey, false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
+    accounts.extend(
+        deposit_reserve_oracle_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    accounts.extend(
+        obligation_reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new(pubkey, false)),
+    );
+    if let Some(host_fee_receiver_pubkey) = host_fee_receiver_pubkey {
+        accounts.push(AccountMeta::new(host_fee_receiver_pubkey, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::LeverageDeposit {
+            liquidity_amount,
+            minimum_liquidity_out,
+            target_ltv,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `CloseObligation` instruction.
+pub fn close_obligation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::CloseObligation.pack(),
+    }
+}
+
+/// Creates a `SetObligationOwner` instruction.
+pub fn set_obligation_owner(
+    program_id: Pubkey,
+    require_new_owner_signature: bool,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    new_obligation_owner_pubkey: Pubkey,
+    market_member_pubkey: Option<Pubkey>,
+) -> Instruction {
+    let new_owner_obligation_pubkey = obligation_address_with_seed(
+        &new_obligation_owner_pubkey,
+        &lending_market_pubkey,
+        &program_id,
+    )
+    .unwrap();
+    let mut accounts = vec![
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(new_obligation_owner_pubkey, require_new_owner_signature),
+        AccountMeta::new_readonly(new_owner_obligation_pubkey, false),
+    ];
+    if let Some(market_member_pubkey) = market_member_pubkey {
+        accounts.push(AccountMeta::new_readonly(market_member_pubkey, false));
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::SetObligationOwner {
+            require_new_owner_signature,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a `ResizeObligation` instruction.
+pub fn resize_obligation(
+    program_id: Pubkey,
+    max_reserves: u8,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(obligation_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ResizeObligation { max_reserves }.pack(),
+    }
+}
+
+/// Creates a `SetLendingMarketEModeGroup` instruction.
+pub fn set_lending_market_emode_group(
+    program_id: Pubkey,
+    group: u8,
+    config: EModeGroup,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetLendingMarketEModeGroup { group, config }.pack(),
+    }
+}
+
+/// Creates a `ProposeLendingMarketEModeGroup` instruction.
+pub fn propose_lending_market_emode_group(
+    program_id: Pubkey,
+    group: u8,
+    config: EModeGroup,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (pending_emode_group_pubkey, _bump_seed) =
+        pending_emode_group_address(&program_id, &lending_market_pubkey, group);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pending_emode_group_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ProposeLendingMarketEModeGroup { group, config }.pack(),
+    }
+}
+
+/// Creates an `ApplyLendingMarketEModeGroup` instruction.
+pub fn apply_lending_market_emode_group(
+    program_id: Pubkey,
+    group: u8,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let (pending_emode_group_pubkey, _bump_seed) =
+        pending_emode_group_address(&program_id, &lending_market_pubkey, group);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pending_emode_group_pubkey, false),
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new(lending_market_owner_pubkey, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::ApplyLendingMarketEModeGroup.pack(),
+    }
+}
@@ -4,11 +4,14 @@
 
 pub mod entrypoint;
 pub mod error;
+pub mod events;
 pub mod instruction;
 pub mod math;
 pub mod processor;
 pub mod pyth;
+pub mod stake_pool;
 pub mod state;
+pub mod token_swap;
 
 // Export current sdk types for downstream users building with a different sdk version
 pub use solana_program;
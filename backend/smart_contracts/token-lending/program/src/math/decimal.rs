@@ -96,6 +96,16 @@ impl Decimal {
             .ok_or(LendingError::MathOverflow)?;
         Ok(u64::try_from(ceil_val).map_err(|_| LendingError::MathOverflow)?)
     }
+
+    /// Square root, rounded down
+    pub fn try_sqrt(&self) -> Result<Self, ProgramError> {
+        Ok(Self(
+            self.0
+                .checked_mul(Self::wad())
+                .ok_or(LendingError::MathOverflow)?
+                .integer_sqrt(),
+        ))
+    }
 }
 
 impl fmt::Display for Decimal {
@@ -213,4 +223,17 @@ mod test {
     fn test_scaler() {
         assert_eq!(U192::exp10(SCALE), Decimal::wad());
     }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(Decimal::zero().try_sqrt().unwrap(), Decimal::zero());
+        assert_eq!(
+            Decimal::from(144u64).try_sqrt().unwrap(),
+            Decimal::from(12u64)
+        );
+        assert_eq!(
+            Decimal::from_percent(25).try_sqrt().unwrap(),
+            Decimal::from_percent(50)
+        );
+    }
 }
@@ -3,32 +3,48 @@
 use crate::{
     self as spl_token_lending,
     error::LendingError,
+    events::LendingEvent,
     instruction::LendingInstruction,
     math::{Decimal, Rate, TryAdd, TryDiv, TryMul, TrySub, WAD},
-    pyth,
+    pyth, stake_pool,
     state::{
-        CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
+        credit_delegation_address, market_member_address, obligation_address_with_seed,
+        pending_emode_group_address, pending_reserve_config_address, CalculateBorrowResult,
+        CalculateLiquidationResult, CalculateRepayResult, CreditDelegation, EModeGroup,
         InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-        NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, Reserve,
-        ReserveCollateral, ReserveConfig, ReserveLiquidity,
+        MarketMember, NewReserveCollateralParams, NewReserveLiquidityParams, Obligation,
+        PendingEModeGroup, PendingReserveConfig, RateLimiter, RateLimiterConfig, Reserve,
+        ReserveCollateral, ReserveConfig, ReserveLiquidity, CREDIT_DELEGATION_SEED,
+        MARKET_MEMBER_SEED, MAX_EMODE_GROUPS, MAX_EXTENDED_OBLIGATION_RESERVES,
+        MAX_OBLIGATION_RESERVES, PAUSE_ALL, PAUSE_BORROWS, PAUSE_DEPOSITS, PAUSE_FLASH_LOANS,
+        PAUSE_LIQUIDATIONS, PENDING_EMODE_GROUP_SEED, PENDING_RESERVE_CONFIG_SEED, PROGRAM_VERSION,
+        RESERVE_CONFIG_DELAY_SLOTS, SLOTS_PER_YEAR,
     },
+    token_swap,
 };
 use num_traits::FromPrimitive;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::{next_account_info, next_account_infos, AccountInfo},
     decode_error::DecodeError,
     entrypoint::ProgramResult,
     instruction::Instruction,
     msg,
+    native_token::LAMPORTS_PER_SOL,
     program::{invoke, invoke_signed},
     program_error::{PrintProgramError, ProgramError},
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    sysvar::{clock::Clock, rent::Rent, Sysvar},
+    system_instruction,
+    sysvar::{
+        clock::Clock,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        rent::Rent,
+        Sysvar,
+    },
 };
 use spl_token::solana_program::instruction::AccountMeta;
 use spl_token::state::{Account, Mint};
-use std::{convert::TryInto, result::Result};
+use std::{convert::TryInto, iter::Peekable, result::Result, slice::Iter};
 use switchboard_program::{
     get_aggregator, get_aggregator_result, AggregatorState, RoundResult, SwitchboardAccountType,
 };
@@ -48,10 +64,6 @@ pub fn process_instruction(
             msg!("Instruction: Init Lending Market");
             process_init_lending_market(program_id, owner, quote_currency, accounts)
         }
-        LendingInstruction::SetLendingMarketOwner { new_owner } => {
-            msg!("Instruction: Set Lending Market Owner");
-            process_set_lending_market_owner(program_id, new_owner, accounts)
-        }
         LendingInstruction::InitReserve {
             liquidity_amount,
             config,
@@ -89,7 +101,7 @@ pub fn process_instruction(
         }
         LendingInstruction::BorrowObligationLiquidity { liquidity_amount } => {
             msg!("Instruction: Borrow Obligation Liquidity");
-            process_borrow_obligation_liquidity(program_id, liquidity_amount, accounts)
+            process_borrow_obligation_liquidity(program_id, liquidity_amount, None, false, accounts)
         }
         LendingInstruction::RepayObligationLiquidity { liquidity_amount } => {
             msg!("Instruction: Repay Obligation Liquidity");
@@ -125,6 +137,159 @@ pub fn process_instruction(
             msg!("Instruction: UpdateReserveConfig");
             process_update_reserve_config(program_id, config, accounts)
         }
+        LendingInstruction::FlashBorrowReserveLiquidity { liquidity_amount } => {
+            msg!("Instruction: Flash Borrow Reserve Liquidity");
+            process_flash_borrow_reserve_liquidity(program_id, liquidity_amount, accounts)
+        }
+        LendingInstruction::FlashRepayReserveLiquidity {
+            liquidity_amount,
+            borrow_instruction_index,
+        } => {
+            msg!("Instruction: Flash Repay Reserve Liquidity");
+            process_flash_repay_reserve_liquidity(
+                program_id,
+                liquidity_amount,
+                borrow_instruction_index,
+                accounts,
+            )
+        }
+        LendingInstruction::ForgiveDebt { liquidity_amount } => {
+            msg!("Instruction: Forgive Debt");
+            process_forgive_debt(program_id, liquidity_amount, accounts)
+        }
+        LendingInstruction::SetLendingMarketGuardian { new_guardian } => {
+            msg!("Instruction: Set Lending Market Guardian");
+            process_set_lending_market_guardian(program_id, new_guardian, accounts)
+        }
+        LendingInstruction::SetLendingMarketPauseFlags { pause_flags } => {
+            msg!("Instruction: Set Lending Market Pause Flags");
+            process_set_lending_market_pause_flags(program_id, pause_flags, accounts)
+        }
+        LendingInstruction::SetReservePauseFlags { pause_flags } => {
+            msg!("Instruction: Set Reserve Pause Flags");
+            process_set_reserve_pause_flags(program_id, pause_flags, accounts)
+        }
+        LendingInstruction::SetLendingMarketRateLimiterConfig { config } => {
+            msg!("Instruction: Set Lending Market Rate Limiter Config");
+            process_set_lending_market_rate_limiter_config(program_id, config, accounts)
+        }
+        LendingInstruction::SetReserveRateLimiterConfig { config } => {
+            msg!("Instruction: Set Reserve Rate Limiter Config");
+            process_set_reserve_rate_limiter_config(program_id, config, accounts)
+        }
+        LendingInstruction::RepayWithCollateral {
+            collateral_amount,
+            minimum_liquidity_out,
+        } => {
+            msg!("Instruction: Repay With Collateral");
+            process_repay_with_collateral(
+                program_id,
+                collateral_amount,
+                minimum_liquidity_out,
+                accounts,
+            )
+        }
+        LendingInstruction::LeverageDeposit {
+            liquidity_amount,
+            minimum_liquidity_out,
+            target_ltv,
+        } => {
+            msg!("Instruction: Leverage Deposit");
+            process_leverage_deposit(
+                program_id,
+                liquidity_amount,
+                minimum_liquidity_out,
+                target_ltv,
+                accounts,
+            )
+        }
+        LendingInstruction::CloseObligation => {
+            msg!("Instruction: Close Obligation");
+            process_close_obligation(program_id, accounts)
+        }
+        LendingInstruction::SetObligationOwner {
+            require_new_owner_signature,
+        } => {
+            msg!("Instruction: Set Obligation Owner");
+            process_set_obligation_owner(program_id, require_new_owner_signature, accounts)
+        }
+        LendingInstruction::ResizeObligation { max_reserves } => {
+            msg!("Instruction: Resize Obligation");
+            process_resize_obligation(program_id, max_reserves, accounts)
+        }
+        LendingInstruction::SetLendingMarketEModeGroup { group, config } => {
+            msg!("Instruction: Set Lending Market EMode Group");
+            process_set_lending_market_emode_group(program_id, group, config, accounts)
+        }
+        LendingInstruction::ProposeReserveConfig { config } => {
+            msg!("Instruction: Propose Reserve Config");
+            process_propose_reserve_config(program_id, config, accounts)
+        }
+        LendingInstruction::ApplyReserveConfig => {
+            msg!("Instruction: Apply Reserve Config");
+            process_apply_reserve_config(program_id, accounts)
+        }
+        LendingInstruction::ProposeLendingMarketOwner { new_owner } => {
+            msg!("Instruction: Propose Lending Market Owner");
+            process_propose_lending_market_owner(program_id, new_owner, accounts)
+        }
+        LendingInstruction::AcceptLendingMarketOwner => {
+            msg!("Instruction: Accept Lending Market Owner");
+            process_accept_lending_market_owner(program_id, accounts)
+        }
+        LendingInstruction::SetLendingMarketAllowlistAdmin { new_admin } => {
+            msg!("Instruction: Set Lending Market Allowlist Admin");
+            process_set_lending_market_allowlist_admin(program_id, new_admin, accounts)
+        }
+        LendingInstruction::AddMarketMember => {
+            msg!("Instruction: Add Market Member");
+            process_add_market_member(program_id, accounts)
+        }
+        LendingInstruction::RevokeMarketMember => {
+            msg!("Instruction: Revoke Market Member");
+            process_revoke_market_member(program_id, accounts)
+        }
+        LendingInstruction::BorrowObligationLiquidityFixedTerm {
+            liquidity_amount,
+            term_slots,
+        } => {
+            msg!("Instruction: Borrow Obligation Liquidity Fixed Term");
+            process_borrow_obligation_liquidity(
+                program_id,
+                liquidity_amount,
+                Some(term_slots),
+                false,
+                accounts,
+            )
+        }
+        LendingInstruction::SettleFixedTermBorrow => {
+            msg!("Instruction: Settle Fixed Term Borrow");
+            process_settle_fixed_term_borrow(program_id, accounts)
+        }
+        LendingInstruction::ApproveCreditDelegation { borrow_amount } => {
+            msg!("Instruction: Approve Credit Delegation");
+            process_approve_credit_delegation(program_id, borrow_amount, accounts)
+        }
+        LendingInstruction::RevokeCreditDelegation => {
+            msg!("Instruction: Revoke Credit Delegation");
+            process_revoke_credit_delegation(program_id, accounts)
+        }
+        LendingInstruction::BorrowObligationLiquidityDelegated { liquidity_amount } => {
+            msg!("Instruction: Borrow Obligation Liquidity Delegated");
+            process_borrow_obligation_liquidity(program_id, liquidity_amount, None, true, accounts)
+        }
+        LendingInstruction::DeprecateReserve { ltv_decay_slots } => {
+            msg!("Instruction: Deprecate Reserve");
+            process_deprecate_reserve(program_id, ltv_decay_slots, accounts)
+        }
+        LendingInstruction::ProposeLendingMarketEModeGroup { group, config } => {
+            msg!("Instruction: Propose Lending Market EMode Group");
+            process_propose_lending_market_emode_group(program_id, group, config, accounts)
+        }
+        LendingInstruction::ApplyLendingMarketEModeGroup => {
+            msg!("Instruction: Apply Lending Market EMode Group");
+            process_apply_lending_market_emode_group(program_id, accounts)
+        }
     }
 }
 
@@ -158,11 +323,18 @@ fn process_init_lending_market(
     });
     LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
+    LendingEvent::InitLendingMarket {
+        lending_market: *lending_market_info.key,
+        owner,
+        quote_currency,
+    }
+    .emit();
+
     Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
-fn process_set_lending_market_owner(
+fn process_propose_lending_market_owner(
     program_id: &Pubkey,
     new_owner: Pubkey,
     accounts: &[AccountInfo],
@@ -170,6 +342,91 @@ fn process_set_lending_market_owner(
     let account_info_iter = &mut accounts.iter();
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    realloc_legacy_lending_market(
+        lending_market_info,
+        lending_market_owner_info,
+        rent,
+        system_program_info,
+    )?;
+
+    lending_market.pending_owner = new_owner;
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::ProposeLendingMarketOwner {
+        lending_market: *lending_market_info.key,
+        pending_owner: new_owner,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_accept_lending_market_owner(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let pending_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if lending_market.pending_owner == Pubkey::default() {
+        msg!("Lending market has no pending owner");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if &lending_market.pending_owner != pending_owner_info.key {
+        msg!("Lending market pending owner does not match the pending owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !pending_owner_info.is_signer {
+        msg!("Lending market pending owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    lending_market.owner = lending_market.pending_owner;
+    lending_market.pending_owner = Pubkey::default();
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::SetLendingMarketOwner {
+        lending_market: *lending_market_info.key,
+        new_owner: *pending_owner_info.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_set_lending_market_allowlist_admin(
+    program_id: &Pubkey,
+    new_admin: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
 
     let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
@@ -185,9 +442,122 @@ fn process_set_lending_market_owner(
         return Err(LendingError::InvalidSigner.into());
     }
 
-    lending_market.owner = new_owner;
+    realloc_legacy_lending_market(
+        lending_market_info,
+        lending_market_owner_info,
+        rent,
+        system_program_info,
+    )?;
+
+    lending_market.allowlist_admin = new_admin;
     LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
 
+    LendingEvent::SetLendingMarketAllowlistAdmin {
+        lending_market: *lending_market_info.key,
+        new_admin,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_add_market_member(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_member_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let allowlist_admin_info = next_account_info(account_info_iter)?;
+    let member_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    assert_allowlist_admin(&lending_market, allowlist_admin_info)?;
+
+    let (market_member_pubkey, bump_seed) =
+        market_member_address(program_id, lending_market_info.key, member_info.key);
+    if &market_member_pubkey != market_member_info.key {
+        msg!("Market member provided does not match the address derived from the lending market and member");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if !market_member_info.data_is_empty() {
+        msg!("Market member already exists");
+        return Err(LendingError::AlreadyInitialized.into());
+    }
+
+    create_program_derived_account(
+        program_id,
+        market_member_info,
+        allowlist_admin_info,
+        system_program_info,
+        rent,
+        MarketMember::LEN,
+        &[
+            lending_market_info.key.as_ref(),
+            member_info.key.as_ref(),
+            MARKET_MEMBER_SEED,
+            &[bump_seed],
+        ],
+    )?;
+    MarketMember::pack(
+        MarketMember {
+            version: PROGRAM_VERSION,
+            lending_market: *lending_market_info.key,
+            member: *member_info.key,
+        },
+        &mut market_member_info.data.borrow_mut(),
+    )?;
+
+    LendingEvent::AddMarketMember {
+        lending_market: *lending_market_info.key,
+        member: *member_info.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_revoke_market_member(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let market_member_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let allowlist_admin_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    assert_allowlist_admin(&lending_market, allowlist_admin_info)?;
+
+    let market_member = MarketMember::unpack(&market_member_info.data.borrow())?;
+    if market_member_info.owner != program_id {
+        msg!("Market member provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &market_member.lending_market != lending_market_info.key {
+        msg!("Market member lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    market_member_info.data.borrow_mut().fill(0);
+
+    let market_member_lamports = market_member_info.lamports();
+    **market_member_info.lamports.borrow_mut() = 0;
+    **allowlist_admin_info.lamports.borrow_mut() = allowlist_admin_info
+        .lamports()
+        .checked_add(market_member_lamports)
+        .ok_or(LendingError::MathOverflow)?;
+
+    LendingEvent::RevokeMarketMember {
+        lending_market: *lending_market_info.key,
+        member: market_member.member,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -370,11 +740,20 @@ fn process_init_reserve(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::InitReserve {
+        reserve: *reserve_info.key,
+        lending_market: *lending_market_info.key,
+        liquidity_mint: *reserve_liquidity_mint_info.key,
+        liquidity_amount,
+        collateral_amount,
+    }
+    .emit();
+
     Ok(())
 }
 
 fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter().peekable();
+    let account_info_iter = &mut accounts.iter();
     let reserve_info = next_account_info(account_info_iter)?;
     let pyth_price_info = next_account_info(account_info_iter)?;
     let switchboard_feed_info = next_account_info(account_info_iter)?;
@@ -384,15 +763,28 @@ fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
         reserve_info,
         pyth_price_info,
         switchboard_feed_info,
+        account_info_iter.as_slice(),
         clock,
     )
 }
 
+/// Number of accounts RefreshReserve expects after the clock sysvar to price the reserve
+fn additional_oracle_accounts_len(reserve: &Reserve) -> usize {
+    if reserve.liquidity.token_swap_oracle_pubkey != Pubkey::default() {
+        6
+    } else if reserve.liquidity.stake_pool_oracle_pubkey != Pubkey::default() {
+        1
+    } else {
+        0
+    }
+}
+
 fn _refresh_reserve<'a>(
     program_id: &Pubkey,
     reserve_info: &AccountInfo<'a>,
     pyth_price_info: &AccountInfo<'a>,
     switchboard_feed_info: &AccountInfo<'a>,
+    additional_oracle_infos: &[AccountInfo<'a>],
     clock: &Clock,
 ) -> ProgramResult {
     let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
@@ -410,10 +802,41 @@ fn _refresh_reserve<'a>(
         return Err(LendingError::InvalidOracleConfig.into());
     }
 
-    reserve.liquidity.market_price = get_price(switchboard_feed_info, pyth_price_info, clock)?;
+    reserve.liquidity.market_price =
+        if reserve.liquidity.token_swap_oracle_pubkey != Pubkey::default() {
+            get_token_swap_pool_price(program_id, &reserve, additional_oracle_infos, clock)?
+        } else if reserve.liquidity.stake_pool_oracle_pubkey != Pubkey::default() {
+            get_stake_pool_price(
+                &reserve,
+                switchboard_feed_info,
+                pyth_price_info,
+                additional_oracle_infos,
+                clock,
+            )?
+        } else {
+            get_price(switchboard_feed_info, pyth_price_info, clock)?
+        };
+    let slots_elapsed = reserve.last_update.slots_elapsed(clock.slot)?;
+    reserve
+        .liquidity
+        .update_smoothed_market_price(slots_elapsed)?;
 
     reserve.accrue_interest(clock.slot)?;
     reserve.last_update.update_slot(clock.slot);
+
+    LendingEvent::RefreshReserve {
+        reserve: *reserve_info.key,
+        slot: clock.slot,
+        market_price_wads: reserve.liquidity.market_price.to_scaled_val()?,
+        cumulative_borrow_rate_wads: reserve
+            .liquidity
+            .cumulative_borrow_rate_wads
+            .to_scaled_val()?,
+        borrowed_amount_wads: reserve.liquidity.total_borrowed()?.to_scaled_val()?,
+        available_amount: reserve.liquidity.available_amount,
+    }
+    .emit();
+
     Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
     Ok(())
@@ -440,6 +863,20 @@ fn process_deposit_reserve_liquidity(
     let user_transfer_authority_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
+    let market_member_info = next_account_info(account_info_iter).ok();
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        user_transfer_authority_info,
+        market_member_info,
+    )?;
 
     // We don't care about the return value here, so just ignore it.
     _deposit_reserve_liquidity(
@@ -509,6 +946,8 @@ fn _deposit_reserve_liquidity<'a>(
         msg!("Reserve collateral supply cannot be used as the destination collateral provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    assert_not_paused(&lending_market, &reserve, PAUSE_DEPOSITS)?;
+    assert_not_deprecated(&reserve)?;
     if reserve.last_update.is_stale(clock.slot)? {
         msg!("Reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
@@ -560,6 +999,13 @@ fn _deposit_reserve_liquidity<'a>(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::DepositReserveLiquidity {
+        reserve: *reserve_info.key,
+        liquidity_amount,
+        collateral_amount,
+    }
+    .emit();
+
     Ok(collateral_amount)
 }
 
@@ -597,7 +1043,8 @@ fn process_redeem_reserve_collateral(
         user_transfer_authority_info,
         clock,
         token_program_id,
-    )
+    )?;
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -614,8 +1061,8 @@ fn _redeem_reserve_collateral<'a>(
     user_transfer_authority_info: &AccountInfo<'a>,
     clock: &Clock,
     token_program_id: &AccountInfo<'a>,
-) -> ProgramResult {
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+) -> Result<u64, ProgramError> {
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -669,6 +1116,13 @@ fn _redeem_reserve_collateral<'a>(
     }
 
     let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    update_outflow_rate_limiters(
+        &mut lending_market,
+        lending_market_info,
+        &mut reserve,
+        Decimal::from(liquidity_amount),
+        clock,
+    )?;
     reserve.last_update.mark_stale();
     Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
@@ -690,7 +1144,14 @@ fn _redeem_reserve_collateral<'a>(
         token_program: token_program_id.clone(),
     })?;
 
-    Ok(())
+    LendingEvent::RedeemReserveCollateral {
+        reserve: *reserve_info.key,
+        collateral_amount,
+        liquidity_amount,
+    }
+    .emit();
+
+    Ok(liquidity_amount)
 }
 
 #[inline(never)] // avoid stack frame limit
@@ -702,6 +1163,7 @@ fn process_init_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
+    let market_member_info = next_account_info(account_info_iter).ok();
 
     assert_rent_exempt(rent, obligation_info)?;
     let mut obligation = assert_uninitialized::<Obligation>(obligation_info)?;
@@ -724,6 +1186,13 @@ fn process_init_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
         msg!("Obligation owner provided must be a signer");
         return Err(LendingError::InvalidSigner.into());
     }
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        obligation_owner_info,
+        market_member_info,
+    )?;
 
     obligation.init(InitObligationParams {
         current_slot: clock.slot,
@@ -734,6 +1203,13 @@ fn process_init_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
     });
     Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
 
+    LendingEvent::InitObligation {
+        obligation: *obligation_info.key,
+        lending_market: *lending_market_info.key,
+        owner: *obligation_owner_info.key,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -741,17 +1217,52 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
     let account_info_iter = &mut accounts.iter().peekable();
     let obligation_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    _refresh_obligation(program_id, obligation_info, account_info_iter, clock)
+}
 
+#[inline(never)] // avoid stack frame limit
+fn _refresh_obligation<'a, 'b>(
+    program_id: &Pubkey,
+    obligation_info: &AccountInfo<'b>,
+    account_info_iter: &mut Peekable<Iter<'a, AccountInfo<'b>>>,
+    clock: &Clock,
+) -> ProgramResult {
     let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
         msg!("Obligation provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
     }
 
+    // the lending market follows the deposit and borrow reserves, and is only needed to price
+    // obligations in an efficiency mode group
+    let lending_market_info = account_info_iter
+        .clone()
+        .nth(obligation.deposits.len() + obligation.borrows.len());
+    let lending_market = match lending_market_info {
+        Some(lending_market_info) => {
+            if lending_market_info.owner != program_id {
+                msg!("Lending market provided is not owned by the lending program");
+                return Err(LendingError::InvalidAccountOwner.into());
+            }
+            if &obligation.lending_market != lending_market_info.key {
+                msg!("Obligation lending market does not match the lending market provided");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+            Some(LendingMarket::unpack(&lending_market_info.data.borrow())?)
+        }
+        None => None,
+    };
+
     let mut deposited_value = Decimal::zero();
     let mut borrowed_value = Decimal::zero();
+    let mut borrowed_value_upper_bound = Decimal::zero();
     let mut allowed_borrow_value = Decimal::zero();
     let mut unhealthy_borrow_value = Decimal::zero();
+    let mut emode_allowed_borrow_value = Decimal::zero();
+    let mut emode_unhealthy_borrow_value = Decimal::zero();
+    let mut borrowing_isolated_asset = false;
+    // efficiency mode group shared by every deposit and borrow reserve, Some(0) if there is none
+    let mut emode_group = None;
 
     for (index, collateral) in obligation.deposits.iter_mut().enumerate() {
         let deposit_reserve_info = next_account_info(account_info_iter)?;
@@ -784,22 +1295,38 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             .checked_pow(deposit_reserve.liquidity.mint_decimals as u32)
             .ok_or(LendingError::MathOverflow)?;
 
-        let market_value = deposit_reserve
+        let liquidity_amount = deposit_reserve
             .collateral_exchange_rate()?
-            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
+            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?;
+        let market_value = liquidity_amount
             .try_mul(deposit_reserve.liquidity.market_price)?
             .try_div(decimals)?;
+        let market_value_lower_bound = deposit_reserve
+            .liquidity
+            .market_value_lower_bound(liquidity_amount)?;
         collateral.market_value = market_value;
 
-        let loan_to_value_rate = Rate::from_percent(deposit_reserve.config.loan_to_value_ratio);
-        let liquidation_threshold_rate =
-            Rate::from_percent(deposit_reserve.config.liquidation_threshold);
+        let (loan_to_value_rate, liquidation_threshold_rate) =
+            deposit_reserve.collateral_rates(clock.slot, None)?;
+        // used instead if every deposit and borrow reserve shares the group of this reserve
+        let (emode_loan_to_value_rate, emode_liquidation_threshold_rate) = deposit_reserve
+            .collateral_rates(
+                clock.slot,
+                lending_market
+                    .as_ref()
+                    .and_then(|market| market.emode_group(deposit_reserve.config.emode_group)),
+            )?;
 
         deposited_value = deposited_value.try_add(market_value)?;
         allowed_borrow_value =
-            allowed_borrow_value.try_add(market_value.try_mul(loan_to_value_rate)?)?;
+            allowed_borrow_value.try_add(market_value_lower_bound.try_mul(loan_to_value_rate)?)?;
         unhealthy_borrow_value =
             unhealthy_borrow_value.try_add(market_value.try_mul(liquidation_threshold_rate)?)?;
+        emode_allowed_borrow_value = emode_allowed_borrow_value
+            .try_add(market_value_lower_bound.try_mul(emode_loan_to_value_rate)?)?;
+        emode_unhealthy_borrow_value = emode_unhealthy_borrow_value
+            .try_add(market_value.try_mul(emode_liquidation_threshold_rate)?)?;
+        emode_group = shared_emode_group(emode_group, &deposit_reserve.config);
     }
 
     for (index, liquidity) in obligation.borrows.iter_mut().enumerate() {
@@ -819,7 +1346,7 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             return Err(LendingError::InvalidAccountInput.into());
         }
 
-        let borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+        let mut borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
         if borrow_reserve.last_update.is_stale(clock.slot)? {
             msg!(
                 "Borrow reserve provided for liquidity {} is stale and must be refreshed in the current slot",
@@ -828,7 +1355,26 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             return Err(LendingError::ReserveStale.into());
         }
 
-        liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+        if liquidity.is_fixed_rate() && clock.slot >= liquidity.maturity_slot {
+            // matured borrows are settled here so that the reserve stops accruing fixed rate
+            // interest on them
+            borrow_reserve.settle_fixed_borrow(liquidity, clock.slot)?;
+            Reserve::pack(
+                borrow_reserve.clone(),
+                &mut borrow_reserve_info.data.borrow_mut(),
+            )?;
+
+            LendingEvent::SettleFixedTermBorrow {
+                obligation: *obligation_info.key,
+                reserve: *borrow_reserve_info.key,
+                borrowed_amount_wads: liquidity.borrowed_amount_wads.to_scaled_val()?,
+            }
+            .emit();
+        } else if liquidity.is_fixed_rate() {
+            liquidity.accrue_fixed_interest(clock.slot)?;
+        } else {
+            liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+        }
 
         // @TODO: add lookup table https://git.io/JOCYq
         let decimals = 10u64
@@ -841,7 +1387,35 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             .try_div(decimals)?;
         liquidity.market_value = market_value;
 
-        borrowed_value = borrowed_value.try_add(market_value)?;
+        let borrow_weight = borrow_reserve.config.borrow_weight()?;
+        borrowed_value = borrowed_value.try_add(market_value.try_mul(borrow_weight)?)?;
+        borrowed_value_upper_bound = borrowed_value_upper_bound.try_add(
+            borrow_reserve
+                .liquidity
+                .market_value_upper_bound(liquidity.borrowed_amount_wads)?
+                .try_mul(borrow_weight)?,
+        )?;
+        if borrow_reserve.config.isolated {
+            borrowing_isolated_asset = true;
+        }
+        emode_group = shared_emode_group(emode_group, &borrow_reserve.config);
+    }
+
+    // the lending market was read before the deposits
+    account_info_iter.next();
+
+    if let Some(group) = emode_group.filter(|group| *group != 0) {
+        let lending_market = match lending_market {
+            Some(lending_market) => lending_market,
+            None => {
+                msg!("Lending market must be provided for obligations in an efficiency mode group");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+        };
+        if lending_market.emode_group(group).is_some() {
+            allowed_borrow_value = emode_allowed_borrow_value;
+            unhealthy_borrow_value = emode_unhealthy_borrow_value;
+        }
     }
 
     if account_info_iter.peek().is_some() {
@@ -851,12 +1425,24 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
 
     obligation.deposited_value = deposited_value;
     obligation.borrowed_value = borrowed_value;
+    obligation.borrowed_value_upper_bound = borrowed_value_upper_bound;
     obligation.allowed_borrow_value = allowed_borrow_value;
     obligation.unhealthy_borrow_value = unhealthy_borrow_value;
+    obligation.borrowing_isolated_asset = borrowing_isolated_asset;
 
     obligation.last_update.update_slot(clock.slot);
     Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
 
+    LendingEvent::RefreshObligation {
+        obligation: *obligation_info.key,
+        slot: clock.slot,
+        deposited_value_wads: deposited_value.to_scaled_val()?,
+        borrowed_value_wads: borrowed_value.to_scaled_val()?,
+        allowed_borrow_value_wads: allowed_borrow_value.to_scaled_val()?,
+        unhealthy_borrow_value_wads: unhealthy_borrow_value.to_scaled_val()?,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -947,7 +1533,13 @@ fn _deposit_obligation_collateral<'a>(
         msg!("Deposit reserve has collateral disabled for borrowing");
         return Err(LendingError::ReserveCollateralDisabled.into());
     }
-
+    if deposit_reserve.config.borrow_only {
+        msg!("Deposit reserve is borrow-only and cannot be used as collateral");
+        return Err(LendingError::ReserveCollateralDisabled.into());
+    }
+    assert_not_paused(&lending_market, &deposit_reserve, PAUSE_DEPOSITS)?;
+    assert_not_deprecated(&deposit_reserve)?;
+
     let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
         msg!("Obligation provided is not owned by the lending program");
@@ -981,6 +1573,13 @@ fn _deposit_obligation_collateral<'a>(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::DepositObligationCollateral {
+        obligation: *obligation_info.key,
+        reserve: *deposit_reserve_info.key,
+        collateral_amount,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -1012,6 +1611,26 @@ fn process_deposit_reserve_liquidity_and_obligation_collateral(
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    // the market member account precedes the oracle accounts, and is only expected when the
+    // lending market has an allowlist admin
+    let market_member_info = if lending_market.is_allowlisted() {
+        next_account_info(account_info_iter).ok()
+    } else {
+        None
+    };
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        obligation_owner_info,
+        market_member_info,
+    )?;
+
     let collateral_amount = _deposit_reserve_liquidity(
         program_id,
         liquidity_amount,
@@ -1031,6 +1650,7 @@ fn process_deposit_reserve_liquidity_and_obligation_collateral(
         reserve_info,
         pyth_price_info,
         switchboard_feed_info,
+        account_info_iter.as_slice(),
         clock,
     )?;
     _deposit_obligation_collateral(
@@ -1228,13 +1848,24 @@ fn _withdraw_obligation_collateral<'a>(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::WithdrawObligationCollateral {
+        obligation: *obligation_info.key,
+        reserve: *withdraw_reserve_info.key,
+        collateral_amount: withdraw_amount,
+    }
+    .emit();
+
     Ok(withdraw_amount)
 }
 
+/// Borrows at the variable rate, or at a fixed rate for `fixed_term_slots` if provided. Delegated
+/// borrows are signed by a delegate of the obligation owner with a credit delegation.
 #[inline(never)] // avoid stack frame limit
 fn process_borrow_obligation_liquidity(
     program_id: &Pubkey,
     liquidity_amount: u64,
+    fixed_term_slots: Option<u64>,
+    delegated: bool,
     accounts: &[AccountInfo],
 ) -> ProgramResult {
     if liquidity_amount == 0 {
@@ -1251,10 +1882,15 @@ fn process_borrow_obligation_liquidity(
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
     let obligation_owner_info = next_account_info(account_info_iter)?;
+    let credit_delegation_info = if delegated {
+        Some(next_account_info(account_info_iter)?)
+    } else {
+        None
+    };
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
     let token_program_id = next_account_info(account_info_iter)?;
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         msg!("Lending market provided is not owned by the lending program");
         return Err(LendingError::InvalidAccountOwner.into());
@@ -1263,6 +1899,13 @@ fn process_borrow_obligation_liquidity(
         msg!("Lending market token program does not match the token program provided");
         return Err(LendingError::InvalidTokenProgram.into());
     }
+    // the market member account of the delegate precedes the host fee receiver, and is only
+    // expected when the lending market has an allowlist admin
+    let market_member_info = if delegated && lending_market.is_allowlisted() {
+        next_account_info(account_info_iter).ok()
+    } else {
+        None
+    };
 
     let mut borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
     if borrow_reserve_info.owner != program_id {
@@ -1291,15 +1934,30 @@ fn process_borrow_obligation_liquidity(
         msg!("Borrow reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
     }
+    assert_not_paused(&lending_market, &borrow_reserve, PAUSE_BORROWS)?;
+    assert_not_deprecated(&borrow_reserve)?;
     if liquidity_amount != u64::MAX
         && Decimal::from(liquidity_amount)
-            .try_add(borrow_reserve.liquidity.borrowed_amount_wads)?
+            .try_add(borrow_reserve.liquidity.total_borrowed()?)?
             .try_floor_u64()?
             > borrow_reserve.config.borrow_limit
     {
         msg!("Cannot borrow above the borrow limit");
         return Err(LendingError::InvalidAmount.into());
     }
+    if let Some(fixed_term_slots) = fixed_term_slots {
+        if borrow_reserve.config.max_fixed_term_slots == 0 {
+            msg!("Borrow reserve does not allow fixed term borrows");
+            return Err(LendingError::InvalidFixedTerm.into());
+        }
+        if fixed_term_slots == 0 || fixed_term_slots > borrow_reserve.config.max_fixed_term_slots {
+            msg!(
+                "Fixed term must be in range [1, {}] slots",
+                borrow_reserve.config.max_fixed_term_slots
+            );
+            return Err(LendingError::InvalidFixedTerm.into());
+        }
+    }
 
     let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
@@ -1310,14 +1968,65 @@ fn process_borrow_obligation_liquidity(
         msg!("Obligation lending market does not match the lending market provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
-    if &obligation.owner != obligation_owner_info.key {
-        msg!("Obligation owner does not match the obligation owner provided");
-        return Err(LendingError::InvalidObligationOwner.into());
-    }
-    if !obligation_owner_info.is_signer {
-        msg!("Obligation owner provided must be a signer");
-        return Err(LendingError::InvalidSigner.into());
-    }
+    let mut credit_delegation = match credit_delegation_info {
+        Some(credit_delegation_info) => {
+            let credit_delegation =
+                CreditDelegation::unpack(&credit_delegation_info.data.borrow())?;
+            if credit_delegation_info.owner != program_id {
+                msg!("Credit delegation provided is not owned by the lending program");
+                return Err(LendingError::InvalidAccountOwner.into());
+            }
+            if &credit_delegation.obligation != obligation_info.key {
+                msg!("Credit delegation obligation does not match the obligation provided");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+            if &credit_delegation.borrow_reserve != borrow_reserve_info.key {
+                msg!("Credit delegation borrow reserve does not match the borrow reserve provided");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+            if &credit_delegation.delegate != obligation_owner_info.key {
+                msg!("Credit delegation delegate does not match the delegate provided");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+            if credit_delegation.delegator != obligation.owner {
+                msg!("Credit delegation was approved by a previous obligation owner");
+                return Err(LendingError::InvalidObligationOwner.into());
+            }
+            let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+                program_id,
+                obligation_info.key,
+                obligation_owner_info.key,
+                borrow_reserve_info.key,
+            );
+            if &credit_delegation_pubkey != credit_delegation_info.key {
+                msg!("Credit delegation provided does not match the address derived from the obligation, delegate and borrow reserve");
+                return Err(LendingError::InvalidAccountInput.into());
+            }
+            if !obligation_owner_info.is_signer {
+                msg!("Delegate provided must be a signer");
+                return Err(LendingError::InvalidSigner.into());
+            }
+            assert_market_member(
+                program_id,
+                &lending_market,
+                lending_market_info,
+                obligation_owner_info,
+                market_member_info,
+            )?;
+            Some(credit_delegation)
+        }
+        None => {
+            if &obligation.owner != obligation_owner_info.key {
+                msg!("Obligation owner does not match the obligation owner provided");
+                return Err(LendingError::InvalidObligationOwner.into());
+            }
+            if !obligation_owner_info.is_signer {
+                msg!("Obligation owner provided must be a signer");
+                return Err(LendingError::InvalidSigner.into());
+            }
+            None
+        }
+    };
     if obligation.last_update.is_stale(clock.slot)? {
         msg!("Obligation is stale and must be refreshed in the current slot");
         return Err(LendingError::ObligationStale.into());
@@ -1330,6 +2039,19 @@ fn process_borrow_obligation_liquidity(
         msg!("Obligation deposits have zero value");
         return Err(LendingError::ObligationDepositsZero.into());
     }
+    if borrow_reserve.config.isolated
+        && obligation
+            .borrows
+            .iter()
+            .any(|liquidity| &liquidity.borrow_reserve != borrow_reserve_info.key)
+    {
+        msg!("Isolated reserve cannot be borrowed alongside borrows from other reserves");
+        return Err(LendingError::IsolatedReserveViolation.into());
+    }
+    if !borrow_reserve.config.isolated && obligation.borrowing_isolated_asset {
+        msg!("Obligation borrowing from an isolated reserve cannot borrow from other reserves");
+        return Err(LendingError::IsolatedReserveViolation.into());
+    }
 
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
@@ -1350,9 +2072,13 @@ fn process_borrow_obligation_liquidity(
         return Err(LendingError::BorrowTooLarge.into());
     }
 
-    let remaining_reserve_capacity = Decimal::from(borrow_reserve.config.borrow_limit)
-        .try_sub(borrow_reserve.liquidity.borrowed_amount_wads)
+    let mut remaining_reserve_capacity = Decimal::from(borrow_reserve.config.borrow_limit)
+        .try_sub(borrow_reserve.liquidity.total_borrowed()?)
         .unwrap_or_else(|_| Decimal::zero());
+    if let Some(credit_delegation) = &credit_delegation {
+        remaining_reserve_capacity =
+            remaining_reserve_capacity.min(credit_delegation.remaining_borrow_amount.into());
+    }
 
     let CalculateBorrowResult {
         borrow_amount,
@@ -1370,16 +2096,65 @@ fn process_borrow_obligation_liquidity(
         return Err(LendingError::BorrowTooSmall.into());
     }
 
-    let cumulative_borrow_rate_wads = borrow_reserve.liquidity.cumulative_borrow_rate_wads;
+    if let (Some(credit_delegation), Some(credit_delegation_info)) =
+        (&mut credit_delegation, credit_delegation_info)
+    {
+        credit_delegation.borrow(borrow_amount.try_ceil_u64()?)?;
+        CreditDelegation::pack(
+            credit_delegation.clone(),
+            &mut credit_delegation_info.data.borrow_mut(),
+        )?;
+    }
 
-    borrow_reserve.liquidity.borrow(borrow_amount)?;
+    let cumulative_borrow_rate_wads = borrow_reserve.liquidity.cumulative_borrow_rate_wads;
+    let borrow_reserve_isolated = borrow_reserve.config.isolated;
+    let fixed_borrow_rate = borrow_reserve.fixed_borrow_rate(borrow_amount)?;
+    let fixed_interest_per_slot = borrow_amount
+        .try_mul(fixed_borrow_rate)?
+        .try_div(SLOTS_PER_YEAR)?;
+
+    update_outflow_rate_limiters(
+        &mut lending_market,
+        lending_market_info,
+        &mut borrow_reserve,
+        borrow_amount,
+        clock,
+    )?;
+    if fixed_term_slots.is_some() {
+        borrow_reserve
+            .liquidity
+            .borrow_fixed(borrow_amount, fixed_interest_per_slot)?;
+    } else {
+        borrow_reserve.liquidity.borrow(borrow_amount)?;
+    }
     borrow_reserve.last_update.mark_stale();
     Reserve::pack(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
 
     let obligation_liquidity = obligation
         .find_or_add_liquidity_to_borrows(*borrow_reserve_info.key, cumulative_borrow_rate_wads)?;
 
-    obligation_liquidity.borrow(borrow_amount)?;
+    let maturity_slot = match fixed_term_slots {
+        Some(fixed_term_slots) => {
+            let maturity_slot = clock
+                .slot
+                .checked_add(fixed_term_slots)
+                .ok_or(LendingError::MathOverflow)?;
+            obligation_liquidity.borrow_fixed(
+                borrow_amount,
+                fixed_interest_per_slot,
+                clock.slot,
+                maturity_slot,
+            )?;
+            Some(maturity_slot)
+        }
+        None => {
+            obligation_liquidity.borrow(borrow_amount)?;
+            None
+        }
+    };
+    if borrow_reserve_isolated {
+        obligation.borrowing_isolated_asset = true;
+    }
     obligation.last_update.mark_stale();
     Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
 
@@ -1420,6 +2195,264 @@ fn process_borrow_obligation_liquidity(
         token_program: token_program_id.clone(),
     })?;
 
+    match maturity_slot {
+        Some(maturity_slot) => LendingEvent::BorrowObligationLiquidityFixedTerm {
+            obligation: *obligation_info.key,
+            reserve: *borrow_reserve_info.key,
+            liquidity_amount: receive_amount,
+            borrow_fee,
+            host_fee,
+            fixed_borrow_rate_wads: fixed_borrow_rate.to_scaled_val(),
+            maturity_slot,
+        },
+        None if delegated => LendingEvent::BorrowObligationLiquidityDelegated {
+            obligation: *obligation_info.key,
+            reserve: *borrow_reserve_info.key,
+            delegate: *obligation_owner_info.key,
+            liquidity_amount: receive_amount,
+            borrow_fee,
+            host_fee,
+        },
+        None => LendingEvent::BorrowObligationLiquidity {
+            obligation: *obligation_info.key,
+            reserve: *borrow_reserve_info.key,
+            liquidity_amount: receive_amount,
+            borrow_fee,
+            host_fee,
+        },
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_settle_fixed_term_borrow(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let mut borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+    if borrow_reserve_info.owner != program_id {
+        msg!("Borrow reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if borrow_reserve.lending_market != obligation.lending_market {
+        msg!("Borrow reserve lending market does not match the obligation lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if borrow_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Borrow reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let (liquidity, liquidity_index) =
+        obligation.find_liquidity_in_borrows(*borrow_reserve_info.key)?;
+    if !liquidity.is_fixed_rate() {
+        msg!("Obligation liquidity is not borrowed at a fixed rate");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if clock.slot < liquidity.maturity_slot {
+        msg!(
+            "Fixed term borrow matures at slot {}",
+            liquidity.maturity_slot
+        );
+        return Err(LendingError::FixedTermNotMatured.into());
+    }
+
+    let liquidity = &mut obligation.borrows[liquidity_index];
+    borrow_reserve.settle_fixed_borrow(liquidity, clock.slot)?;
+    let borrowed_amount_wads = liquidity.borrowed_amount_wads;
+    Reserve::pack(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
+
+    obligation.last_update.mark_stale();
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    LendingEvent::SettleFixedTermBorrow {
+        obligation: *obligation_info.key,
+        reserve: *borrow_reserve_info.key,
+        borrowed_amount_wads: borrowed_amount_wads.to_scaled_val()?,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_approve_credit_delegation(
+    program_id: &Pubkey,
+    borrow_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let credit_delegation_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let delegate_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let market_member_info = next_account_info(account_info_iter).ok();
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        delegate_info,
+        market_member_info,
+    )?;
+
+    let borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+    if borrow_reserve_info.owner != program_id {
+        msg!("Borrow reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if borrow_reserve.lending_market != obligation.lending_market {
+        msg!("Borrow reserve lending market does not match the obligation lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let (credit_delegation_pubkey, bump_seed) = credit_delegation_address(
+        program_id,
+        obligation_info.key,
+        delegate_info.key,
+        borrow_reserve_info.key,
+    );
+    if &credit_delegation_pubkey != credit_delegation_info.key {
+        msg!("Credit delegation provided does not match the address derived from the obligation, delegate and borrow reserve");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if credit_delegation_info.data_is_empty() {
+        create_program_derived_account(
+            program_id,
+            credit_delegation_info,
+            obligation_owner_info,
+            system_program_info,
+            rent,
+            CreditDelegation::LEN,
+            &[
+                obligation_info.key.as_ref(),
+                delegate_info.key.as_ref(),
+                borrow_reserve_info.key.as_ref(),
+                CREDIT_DELEGATION_SEED,
+                &[bump_seed],
+            ],
+        )?;
+    } else if credit_delegation_info.owner != program_id {
+        msg!("Credit delegation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    CreditDelegation::pack(
+        CreditDelegation {
+            version: PROGRAM_VERSION,
+            obligation: *obligation_info.key,
+            delegate: *delegate_info.key,
+            borrow_reserve: *borrow_reserve_info.key,
+            remaining_borrow_amount: borrow_amount,
+            delegator: *obligation_owner_info.key,
+        },
+        &mut credit_delegation_info.data.borrow_mut(),
+    )?;
+
+    LendingEvent::ApproveCreditDelegation {
+        obligation: *obligation_info.key,
+        delegate: *delegate_info.key,
+        reserve: *borrow_reserve_info.key,
+        borrow_amount,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_revoke_credit_delegation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let credit_delegation_info = next_account_info(account_info_iter)?;
+    let obligation_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let delegator_info = next_account_info(account_info_iter)?;
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let credit_delegation = CreditDelegation::unpack(&credit_delegation_info.data.borrow())?;
+    if credit_delegation_info.owner != program_id {
+        msg!("Credit delegation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &credit_delegation.obligation != obligation_info.key {
+        msg!("Credit delegation obligation does not match the obligation provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &credit_delegation.delegator != delegator_info.key {
+        msg!("Credit delegation delegator does not match the delegator provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    credit_delegation_info.data.borrow_mut().fill(0);
+
+    // the rent is refunded to the obligation owner that approved the delegation
+    let credit_delegation_lamports = credit_delegation_info.lamports();
+    **credit_delegation_info.lamports.borrow_mut() = 0;
+    **delegator_info.lamports.borrow_mut() = delegator_info
+        .lamports()
+        .checked_add(credit_delegation_lamports)
+        .ok_or(LendingError::MathOverflow)?;
+
+    LendingEvent::RevokeCreditDelegation {
+        obligation: *obligation_info.key,
+        delegate: credit_delegation.delegate,
+        reserve: credit_delegation.borrow_reserve,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -1506,7 +2539,9 @@ fn process_repay_obligation_liquidity(
         return Err(LendingError::RepayTooSmall.into());
     }
 
-    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
+    repay_reserve
+        .liquidity
+        .repay_obligation_liquidity(liquidity, repay_amount, settle_amount)?;
     repay_reserve.last_update.mark_stale();
     Reserve::pack(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
 
@@ -1523,6 +2558,13 @@ fn process_repay_obligation_liquidity(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::RepayObligationLiquidity {
+        obligation: *obligation_info.key,
+        reserve: *repay_reserve_info.key,
+        liquidity_amount: repay_amount,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -1588,6 +2630,7 @@ fn process_liquidate_obligation(
         msg!("Repay reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
     }
+    assert_not_paused(&lending_market, &repay_reserve, PAUSE_LIQUIDATIONS)?;
 
     let withdraw_reserve = Reserve::unpack(&withdraw_reserve_info.data.borrow())?;
     if withdraw_reserve_info.owner != program_id {
@@ -1614,6 +2657,7 @@ fn process_liquidate_obligation(
         msg!("Withdraw reserve is stale and must be refreshed in the current slot");
         return Err(LendingError::ReserveStale.into());
     }
+    assert_not_paused(&lending_market, &withdraw_reserve, PAUSE_LIQUIDATIONS)?;
 
     let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
     if obligation_info.owner != program_id {
@@ -1677,6 +2721,7 @@ fn process_liquidate_obligation(
         &obligation,
         liquidity,
         collateral,
+        repay_reserve.config.borrow_weight()?,
     )?;
 
     if repay_amount == 0 {
@@ -1688,7 +2733,9 @@ fn process_liquidate_obligation(
         return Err(LendingError::LiquidationTooSmall.into());
     }
 
-    repay_reserve.liquidity.repay(repay_amount, settle_amount)?;
+    repay_reserve
+        .liquidity
+        .repay_obligation_liquidity(liquidity, repay_amount, settle_amount)?;
     repay_reserve.last_update.mark_stale();
     Reserve::pack(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
 
@@ -1715,6 +2762,15 @@ fn process_liquidate_obligation(
         token_program: token_program_id.clone(),
     })?;
 
+    LendingEvent::LiquidateObligation {
+        obligation: *obligation_info.key,
+        repay_reserve: *repay_reserve_info.key,
+        withdraw_reserve: *withdraw_reserve_info.key,
+        liquidity_amount: repay_amount,
+        collateral_amount: withdraw_amount,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -1745,7 +2801,7 @@ fn process_flash_loan(
         return Err(LendingError::InvalidFlashLoanReceiverProgram.into());
     }
 
-    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
     if lending_market_info.owner != program_id {
         return Err(LendingError::InvalidAccountOwner.into());
     }
@@ -1784,6 +2840,7 @@ fn process_flash_loan(
         msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    assert_not_paused(&lending_market, &reserve, PAUSE_FLASH_LOANS)?;
 
     // @FIXME: if u64::MAX is flash loaned, fees should be inclusive as with ordinary borrows
     let flash_loan_amount = if liquidity_amount == u64::MAX {
@@ -1826,6 +2883,13 @@ fn process_flash_loan(
         flash_loan_instruction_account_infos.push(account_info.clone());
     }
 
+    update_outflow_rate_limiters(
+        &mut lending_market,
+        lending_market_info,
+        &mut reserve,
+        flash_loan_amount_decimal,
+        &Clock::get()?,
+    )?;
     reserve.liquidity.borrow(flash_loan_amount_decimal)?;
     Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
@@ -1894,6 +2958,13 @@ fn process_flash_loan(
         })?;
     }
 
+    LendingEvent::FlashLoan {
+        reserve: *reserve_info.key,
+        liquidity_amount: flash_loan_amount,
+        fee: origination_fee,
+    }
+    .emit();
+
     Ok(())
 }
 
@@ -1945,7 +3016,8 @@ fn process_withdraw_obligation_collateral_and_redeem_reserve_liquidity(
         user_transfer_authority_info,
         clock,
         token_program_id,
-    )
+    )?;
+    Ok(())
 }
 
 #[inline(never)] // avoid stack frame limit
@@ -1960,7 +3032,7 @@ fn process_update_reserve_config(
     let lending_market_info = next_account_info(account_info_iter)?;
     let lending_market_authority_info = next_account_info(account_info_iter)?;
     let lending_market_owner_info = next_account_info(account_info_iter)?;
-    let pyth_product_info = next_account_info(account_info_iter)?;
+    let _pyth_product_info = next_account_info(account_info_iter)?;
     let pyth_price_info = next_account_info(account_info_iter)?;
     let switchboard_feed_info = next_account_info(account_info_iter)?;
 
@@ -2009,37 +3081,2212 @@ fn process_update_reserve_config(
         return Err(LendingError::InvalidMarketAuthority.into());
     }
 
-    if *pyth_price_info.key != reserve.liquidity.pyth_oracle_pubkey {
-        validate_pyth_keys(&lending_market, pyth_product_info, pyth_price_info)?;
-        reserve.liquidity.pyth_oracle_pubkey = *pyth_price_info.key;
-    }
-
-    if *switchboard_feed_info.key != reserve.liquidity.switchboard_oracle_pubkey {
-        validate_switchboard_keys(&lending_market, switchboard_feed_info)?;
-        reserve.liquidity.switchboard_oracle_pubkey = *switchboard_feed_info.key;
-    }
-    if reserve.liquidity.switchboard_oracle_pubkey == spl_token_lending::NULL_PUBKEY
-        && (*pyth_price_info.key == spl_token_lending::NULL_PUBKEY
-            || *pyth_product_info.key == spl_token_lending::NULL_PUBKEY)
+    if *pyth_price_info.key != reserve.liquidity.pyth_oracle_pubkey
+        || *switchboard_feed_info.key != reserve.liquidity.switchboard_oracle_pubkey
+        || !reserve.config.is_risk_reducing_change(&config)
     {
-        msg!("At least one price oracle must have a non-null pubkey");
-        return Err(LendingError::InvalidOracleConfig.into());
+        msg!("Reserve oracle changes and config changes that add risk must be proposed with ProposeReserveConfig");
+        return Err(LendingError::ReserveConfigChangeNotAllowed.into());
     }
 
     reserve.config = config;
     Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    LendingEvent::UpdateReserveConfig {
+        reserve: *reserve_info.key,
+    }
+    .emit();
+
     Ok(())
 }
 
-fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
-    if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
-        msg!(
-            "Rent exempt balance insufficient got {} expected {}",
-            &account_info.lamports().to_string(),
-            &rent.minimum_balance(account_info.data_len()).to_string(),
-        );
-        Err(LendingError::NotRentExempt.into())
-    } else {
+#[inline(never)] // avoid stack frame limit
+fn process_flash_borrow_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let sysvar_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != source_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == destination_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    assert_not_paused(&lending_market, &reserve, PAUSE_FLASH_LOANS)?;
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    // the instructions sysvar only lists top-level instructions, so the current instruction
+    // belonging to another program means this one was invoked through CPI
+    let current_index = load_current_index_checked(sysvar_info)? as usize;
+    if &load_instruction_at_checked(current_index, sysvar_info)?.program_id != program_id {
+        msg!("Flash borrow must be a top-level instruction");
+        return Err(LendingError::FlashLoanCpiNotAllowed.into());
+    }
+
+    let mut found_repay = false;
+    let mut index = current_index + 1;
+    loop {
+        let instruction = match load_instruction_at_checked(index, sysvar_info) {
+            Ok(instruction) => instruction,
+            // out of bounds, no instructions left
+            Err(ProgramError::InvalidArgument) => break,
+            Err(e) => return Err(e),
+        };
+        index += 1;
+
+        if &instruction.program_id != program_id {
+            continue;
+        }
+        match LendingInstruction::unpack(&instruction.data)? {
+            LendingInstruction::FlashBorrowReserveLiquidity { .. } => {
+                msg!("Multiple flash borrows are not allowed in the same transaction");
+                return Err(LendingError::MultipleFlashBorrows.into());
+            }
+            LendingInstruction::FlashRepayReserveLiquidity {
+                liquidity_amount: repay_liquidity_amount,
+                borrow_instruction_index,
+            } => {
+                if found_repay {
+                    msg!("Multiple flash repays are not allowed in the same transaction");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                if instruction.accounts.get(4).map(|account| &account.pubkey)
+                    != Some(reserve_info.key)
+                {
+                    msg!("Flash repay reserve does not match the flash borrow reserve");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                if repay_liquidity_amount != liquidity_amount {
+                    msg!("Flash repay amount does not match the flash borrow amount");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                if borrow_instruction_index as usize != current_index {
+                    msg!("Flash repay borrow instruction index does not match the flash borrow");
+                    return Err(LendingError::InvalidFlashRepay.into());
+                }
+                found_repay = true;
+            }
+            _ => {}
+        }
+    }
+    if !found_repay {
+        msg!("Flash borrow must be followed by a matching flash repay");
+        return Err(LendingError::NoFlashRepayFound.into());
+    }
+
+    update_outflow_rate_limiters(
+        &mut lending_market,
+        lending_market_info,
+        &mut reserve,
+        Decimal::from(liquidity_amount),
+        &Clock::get()?,
+    )?;
+    reserve.liquidity.borrow(Decimal::from(liquidity_amount))?;
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::FlashBorrowReserveLiquidity {
+        reserve: *reserve_info.key,
+        liquidity_amount,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_flash_repay_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    borrow_instruction_index: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let host_fee_receiver_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let sysvar_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != destination_liquidity_info.key {
+        msg!("Reserve liquidity supply must be used as the destination liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey == source_liquidity_info.key {
+        msg!("Reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.config.fee_receiver != reserve_liquidity_fee_receiver_info.key {
+        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let current_index = load_current_index_checked(sysvar_info)? as usize;
+    if &load_instruction_at_checked(current_index, sysvar_info)?.program_id != program_id {
+        msg!("Flash repay must be a top-level instruction");
+        return Err(LendingError::FlashLoanCpiNotAllowed.into());
+    }
+    if borrow_instruction_index as usize >= current_index {
+        msg!("Flash repay must come after its flash borrow");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+
+    let borrow_instruction =
+        load_instruction_at_checked(borrow_instruction_index as usize, sysvar_info)?;
+    if &borrow_instruction.program_id != program_id {
+        msg!("Flash repay borrow instruction index does not point to the lending program");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+    match LendingInstruction::unpack(&borrow_instruction.data)? {
+        LendingInstruction::FlashBorrowReserveLiquidity {
+            liquidity_amount: borrow_liquidity_amount,
+        } => {
+            if borrow_liquidity_amount != liquidity_amount {
+                msg!("Flash repay amount does not match the flash borrow amount");
+                return Err(LendingError::InvalidFlashRepay.into());
+            }
+        }
+        _ => {
+            msg!("Flash repay borrow instruction index does not point to a flash borrow");
+            return Err(LendingError::InvalidFlashRepay.into());
+        }
+    }
+    if borrow_instruction
+        .accounts
+        .get(2)
+        .map(|account| &account.pubkey)
+        != Some(reserve_info.key)
+    {
+        msg!("Flash repay reserve does not match the flash borrow reserve");
+        return Err(LendingError::InvalidFlashRepay.into());
+    }
+
+    let flash_loan_amount_decimal = Decimal::from(liquidity_amount);
+    let (origination_fee, host_fee) = reserve
+        .config
+        .fees
+        .calculate_flash_loan_fees(flash_loan_amount_decimal)?;
+
+    reserve
+        .liquidity
+        .repay(liquidity_amount, flash_loan_amount_decimal)?;
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: source_liquidity_info.clone(),
+        destination: destination_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: user_transfer_authority_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    let mut owner_fee = origination_fee;
+    if host_fee > 0 {
+        owner_fee = owner_fee
+            .checked_sub(host_fee)
+            .ok_or(LendingError::MathOverflow)?;
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: host_fee_receiver_info.clone(),
+            amount: host_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: source_liquidity_info.clone(),
+            destination: reserve_liquidity_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: user_transfer_authority_info.clone(),
+            authority_signer_seeds: &[],
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    LendingEvent::FlashRepayReserveLiquidity {
+        reserve: *reserve_info.key,
+        liquidity_amount,
+        fee: origination_fee,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_forgive_debt(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+    if !obligation.deposits.is_empty() {
+        msg!("Obligation debt can only be forgiven once all of its collateral is gone");
+        return Err(LendingError::ObligationDepositsNotEmpty.into());
+    }
+
+    let (liquidity, liquidity_index) = obligation.find_liquidity_in_borrows(*reserve_info.key)?;
+    if liquidity.borrowed_amount_wads == Decimal::zero() {
+        msg!("Liquidity borrowed amount is zero");
+        return Err(LendingError::ObligationLiquidityEmpty.into());
+    }
+
+    let CalculateRepayResult { settle_amount, .. } =
+        reserve.calculate_repay(liquidity_amount, liquidity.borrowed_amount_wads)?;
+    let forgiven_value = liquidity
+        .market_value
+        .try_mul(settle_amount)?
+        .try_div(liquidity.borrowed_amount_wads)?;
+
+    reserve
+        .liquidity
+        .forgive_obligation_debt(liquidity, settle_amount)?;
+    reserve.last_update.mark_stale();
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    obligation.repay(settle_amount, liquidity_index)?;
+    obligation.last_update.mark_stale();
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    msg!(
+        "Debt forgiven: obligation {}, reserve {}, liquidity amount {}, market value {}",
+        obligation_info.key,
+        reserve_info.key,
+        settle_amount,
+        forgiven_value
+    );
+
+    LendingEvent::ForgiveDebt {
+        obligation: *obligation_info.key,
+        reserve: *reserve_info.key,
+        liquidity_amount_wads: settle_amount.to_scaled_val()?,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_guardian(
+    program_id: &Pubkey,
+    new_guardian: Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    lending_market.guardian = new_guardian;
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::SetLendingMarketGuardian {
+        lending_market: *lending_market_info.key,
+        new_guardian,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_pause_flags(
+    program_id: &Pubkey,
+    pause_flags: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let pause_authority_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    validate_pause_flags(
+        &lending_market,
+        pause_authority_info,
+        lending_market.pause_flags,
+        pause_flags,
+    )?;
+
+    lending_market.pause_flags = pause_flags;
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::SetLendingMarketPauseFlags {
+        lending_market: *lending_market_info.key,
+        pause_flags,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_reserve_pause_flags(
+    program_id: &Pubkey,
+    pause_flags: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let pause_authority_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    validate_pause_flags(
+        &lending_market,
+        pause_authority_info,
+        reserve.pause_flags,
+        pause_flags,
+    )?;
+
+    reserve.pause_flags = pause_flags;
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    LendingEvent::SetReservePauseFlags {
+        reserve: *reserve_info.key,
+        pause_flags,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_deprecate_reserve(
+    program_id: &Pubkey,
+    ltv_decay_slots: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    assert_not_deprecated(&reserve)?;
+
+    reserve.deprecate(clock.slot, ltv_decay_slots);
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    LendingEvent::DeprecateReserve {
+        reserve: *reserve_info.key,
+        ltv_decay_slots,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_rate_limiter_config(
+    program_id: &Pubkey,
+    config: RateLimiterConfig,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    lending_market.rate_limiter = RateLimiter::new(config, clock.slot);
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::SetLendingMarketRateLimiterConfig {
+        lending_market: *lending_market_info.key,
+        window_duration: config.window_duration,
+        max_outflow: config.max_outflow,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_reserve_rate_limiter_config(
+    program_id: &Pubkey,
+    config: RateLimiterConfig,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    reserve.rate_limiter = RateLimiter::new(config, clock.slot);
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    LendingEvent::SetReserveRateLimiterConfig {
+        reserve: *reserve_info.key,
+        window_duration: config.window_duration,
+        max_outflow: config.max_outflow,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_repay_with_collateral(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    minimum_liquidity_out: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let withdraw_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let user_collateral_info = next_account_info(account_info_iter)?;
+    let user_withdraw_liquidity_info = next_account_info(account_info_iter)?;
+    let repay_reserve_info = next_account_info(account_info_iter)?;
+    let repay_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let user_repay_liquidity_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+    let token_swap_program_id = next_account_info(account_info_iter)?;
+
+    if program_id == token_swap_program_id.key {
+        msg!("Lending program cannot be used as the token swap program provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    if withdraw_reserve_info.key == repay_reserve_info.key {
+        msg!("Withdraw reserve and repay reserve must be different");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let withdraw_reserve = Reserve::unpack(&withdraw_reserve_info.data.borrow())?;
+    if withdraw_reserve_info.owner != program_id {
+        msg!("Withdraw reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &withdraw_reserve.lending_market != lending_market_info.key {
+        msg!("Withdraw reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &withdraw_reserve.collateral.supply_pubkey != withdraw_reserve_collateral_supply_info.key {
+        msg!("Withdraw reserve collateral supply does not match the withdraw reserve collateral supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if withdraw_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Withdraw reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let repay_reserve = Reserve::unpack(&repay_reserve_info.data.borrow())?;
+    if repay_reserve_info.owner != program_id {
+        msg!("Repay reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &repay_reserve.lending_market != lending_market_info.key {
+        msg!("Repay reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey != repay_reserve_liquidity_supply_info.key {
+        msg!("Repay reserve liquidity supply does not match the repay reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &repay_reserve.liquidity.supply_pubkey == user_repay_liquidity_info.key {
+        msg!("Repay reserve liquidity supply cannot be used as the source liquidity provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if repay_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Repay reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+
+    let initial_borrowed_value_upper_bound = obligation.borrowed_value_upper_bound;
+    let initial_allowed_borrow_value = obligation.allowed_borrow_value;
+
+    // withdraw collateral, skipping the health check until the end
+    let (collateral, collateral_index) =
+        obligation.find_collateral_in_deposits(*withdraw_reserve_info.key)?;
+    if collateral.deposited_amount == 0 {
+        msg!("Collateral deposited amount is zero");
+        return Err(LendingError::ObligationCollateralEmpty.into());
+    }
+    let withdraw_amount = collateral_amount.min(collateral.deposited_amount);
+    let withdraw_value = collateral
+        .market_value
+        .try_mul(Decimal::from(withdraw_amount))?
+        .try_div(Decimal::from(collateral.deposited_amount))?;
+
+    obligation.withdraw(withdraw_amount, collateral_index)?;
+    // the obligation may be in the efficiency mode group of the withdraw reserve, so the
+    // collateral is assumed to have counted at the higher of the group and reserve rates
+    let (loan_to_value_rate, liquidation_threshold_rate) = withdraw_reserve.collateral_rates(
+        clock.slot,
+        lending_market.emode_group(withdraw_reserve.config.emode_group),
+    )?;
+    let allowed_borrow_value_decrease = withdraw_value
+        .try_mul(loan_to_value_rate)?
+        .min(obligation.allowed_borrow_value);
+    let unhealthy_borrow_value_decrease = withdraw_value
+        .try_mul(liquidation_threshold_rate)?
+        .min(obligation.unhealthy_borrow_value);
+    obligation.deposited_value = obligation
+        .deposited_value
+        .try_sub(withdraw_value.min(obligation.deposited_value))?;
+    obligation.allowed_borrow_value = obligation
+        .allowed_borrow_value
+        .try_sub(allowed_borrow_value_decrease)?;
+    obligation.unhealthy_borrow_value = obligation
+        .unhealthy_borrow_value
+        .try_sub(unhealthy_borrow_value_decrease)?;
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    spl_token_transfer(TokenTransferParams {
+        source: withdraw_reserve_collateral_supply_info.clone(),
+        destination: user_collateral_info.clone(),
+        amount: withdraw_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::WithdrawObligationCollateral {
+        obligation: *obligation_info.key,
+        reserve: *withdraw_reserve_info.key,
+        collateral_amount: withdraw_amount,
+    }
+    .emit();
+
+    let withdraw_liquidity_amount = _redeem_reserve_collateral(
+        program_id,
+        withdraw_amount,
+        user_collateral_info,
+        user_withdraw_liquidity_info,
+        withdraw_reserve_info,
+        withdraw_reserve_collateral_mint_info,
+        withdraw_reserve_liquidity_supply_info,
+        lending_market_info,
+        lending_market_authority_info,
+        obligation_owner_info,
+        clock,
+        token_program_id,
+    )?;
+
+    let liquidity_out = swap_liquidity(
+        token_swap_program_id,
+        account_info_iter,
+        user_withdraw_liquidity_info,
+        user_repay_liquidity_info,
+        obligation_owner_info,
+        token_program_id,
+        withdraw_liquidity_amount,
+        minimum_liquidity_out,
+    )?;
+
+    // repay the borrow with the swap output, leaving any excess with the user
+    let mut repay_reserve = Reserve::unpack(&repay_reserve_info.data.borrow())?;
+    let (liquidity, liquidity_index) =
+        obligation.find_liquidity_in_borrows(*repay_reserve_info.key)?;
+    if liquidity.borrowed_amount_wads == Decimal::zero() {
+        msg!("Liquidity borrowed amount is zero");
+        return Err(LendingError::ObligationLiquidityEmpty.into());
+    }
+
+    let CalculateRepayResult {
+        settle_amount,
+        repay_amount,
+    } = repay_reserve.calculate_repay(liquidity_out, liquidity.borrowed_amount_wads)?;
+    if repay_amount == 0 {
+        msg!("Repay amount is too small to transfer liquidity");
+        return Err(LendingError::RepayTooSmall.into());
+    }
+
+    let repay_value = liquidity
+        .market_value
+        .try_mul(settle_amount)?
+        .try_div(liquidity.borrowed_amount_wads)?
+        .try_mul(repay_reserve.config.borrow_weight()?)?;
+
+    repay_reserve
+        .liquidity
+        .repay_obligation_liquidity(liquidity, repay_amount, settle_amount)?;
+    repay_reserve.last_update.mark_stale();
+    Reserve::pack(repay_reserve, &mut repay_reserve_info.data.borrow_mut())?;
+
+    obligation.repay(settle_amount, liquidity_index)?;
+    obligation.borrowed_value = obligation
+        .borrowed_value
+        .try_sub(repay_value.min(obligation.borrowed_value))?;
+    obligation.borrowed_value_upper_bound = obligation
+        .borrowed_value_upper_bound
+        .try_sub(repay_value.min(obligation.borrowed_value_upper_bound))?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: user_repay_liquidity_info.clone(),
+        destination: repay_reserve_liquidity_supply_info.clone(),
+        amount: repay_amount,
+        authority: obligation_owner_info.clone(),
+        authority_signer_seeds: &[],
+        token_program: token_program_id.clone(),
+    })?;
+
+    LendingEvent::RepayObligationLiquidity {
+        obligation: *obligation_info.key,
+        reserve: *repay_reserve_info.key,
+        liquidity_amount: repay_amount,
+    }
+    .emit();
+
+    // an obligation above its allowed borrow value can only be deleveraged
+    if obligation.borrowed_value_upper_bound > obligation.allowed_borrow_value
+        && obligation
+            .borrowed_value_upper_bound
+            .try_mul(initial_allowed_borrow_value)?
+            > initial_borrowed_value_upper_bound.try_mul(obligation.allowed_borrow_value)?
+    {
+        msg!("Obligation health cannot worsen while its borrowed value exceeds the allowed borrow value");
+        return Err(LendingError::ObligationHealthWorsened.into());
+    }
+
+    obligation.last_update.mark_stale();
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_leverage_deposit(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    minimum_liquidity_out: u64,
+    target_ltv: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 || liquidity_amount == u64::MAX {
+        msg!("Liquidity amount provided must be an explicit non-zero amount");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let borrow_reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let user_borrow_liquidity_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_collateral_supply_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_pyth_price_info = next_account_info(account_info_iter)?;
+    let deposit_reserve_switchboard_feed_info = next_account_info(account_info_iter)?;
+    let user_deposit_liquidity_info = next_account_info(account_info_iter)?;
+    let user_collateral_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+    let token_swap_program_id = next_account_info(account_info_iter)?;
+
+    if program_id == token_swap_program_id.key {
+        msg!("Lending program cannot be used as the token swap program provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    if borrow_reserve_info.key == deposit_reserve_info.key {
+        msg!("Borrow reserve and deposit reserve must be different");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+    if borrow_reserve_info.owner != program_id {
+        msg!("Borrow reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &borrow_reserve.lending_market != lending_market_info.key {
+        msg!("Borrow reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.liquidity.supply_pubkey != borrow_reserve_liquidity_supply_info.key {
+        msg!("Borrow reserve liquidity supply does not match the borrow reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.liquidity.supply_pubkey == user_borrow_liquidity_info.key {
+        msg!(
+            "Borrow reserve liquidity supply cannot be used as the destination liquidity provided"
+        );
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &borrow_reserve.config.fee_receiver != borrow_reserve_liquidity_fee_receiver_info.key {
+        msg!("Borrow reserve liquidity fee receiver does not match the borrow reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if borrow_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Borrow reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    assert_not_paused(&lending_market, &borrow_reserve, PAUSE_BORROWS)?;
+    assert_not_deprecated(&borrow_reserve)?;
+    if Decimal::from(liquidity_amount)
+        .try_add(borrow_reserve.liquidity.total_borrowed()?)?
+        .try_floor_u64()?
+        > borrow_reserve.config.borrow_limit
+    {
+        msg!("Cannot borrow above the borrow limit");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let deposit_reserve = Reserve::unpack(&deposit_reserve_info.data.borrow())?;
+    if deposit_reserve_info.owner != program_id {
+        msg!("Deposit reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &deposit_reserve.lending_market != lending_market_info.key {
+        msg!("Deposit reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if target_ltv == 0 || target_ltv >= deposit_reserve.config.loan_to_value_ratio {
+        msg!("Target loan to value must be below the deposit reserve loan to value ratio");
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+    if borrow_reserve.config.isolated
+        && obligation
+            .borrows
+            .iter()
+            .any(|liquidity| &liquidity.borrow_reserve != borrow_reserve_info.key)
+    {
+        msg!("Isolated reserve cannot be borrowed alongside borrows from other reserves");
+        return Err(LendingError::IsolatedReserveViolation.into());
+    }
+    if !borrow_reserve.config.isolated && obligation.borrowing_isolated_asset {
+        msg!("Obligation borrowing from an isolated reserve cannot borrow from other reserves");
+        return Err(LendingError::IsolatedReserveViolation.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    // flash borrow the liquidity, settled by the obligation borrow below
+    spl_token_transfer(TokenTransferParams {
+        source: borrow_reserve_liquidity_supply_info.clone(),
+        destination: user_borrow_liquidity_info.clone(),
+        amount: liquidity_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    let deposit_liquidity_amount = swap_liquidity(
+        token_swap_program_id,
+        account_info_iter,
+        user_borrow_liquidity_info,
+        user_deposit_liquidity_info,
+        obligation_owner_info,
+        token_program_id,
+        liquidity_amount,
+        minimum_liquidity_out,
+    )?;
+
+    // the market member account precedes the deposit reserve oracle accounts, and is only
+    // expected when the lending market has an allowlist admin
+    let market_member_info = if lending_market.is_allowlisted() {
+        next_account_info(account_info_iter).ok()
+    } else {
+        None
+    };
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        obligation_owner_info,
+        market_member_info,
+    )?;
+
+    let deposit_reserve_oracle_infos = next_account_infos(
+        account_info_iter,
+        additional_oracle_accounts_len(&deposit_reserve),
+    )?;
+
+    let collateral_amount = _deposit_reserve_liquidity(
+        program_id,
+        deposit_liquidity_amount,
+        user_deposit_liquidity_info,
+        user_collateral_info,
+        deposit_reserve_info,
+        deposit_reserve_liquidity_supply_info,
+        deposit_reserve_collateral_mint_info,
+        lending_market_info,
+        lending_market_authority_info,
+        obligation_owner_info,
+        clock,
+        token_program_id,
+    )?;
+    _refresh_reserve(
+        program_id,
+        deposit_reserve_info,
+        deposit_reserve_pyth_price_info,
+        deposit_reserve_switchboard_feed_info,
+        deposit_reserve_oracle_infos,
+        clock,
+    )?;
+    _deposit_obligation_collateral(
+        program_id,
+        collateral_amount,
+        user_collateral_info,
+        deposit_reserve_collateral_supply_info,
+        deposit_reserve_info,
+        obligation_info,
+        lending_market_info,
+        obligation_owner_info,
+        obligation_owner_info,
+        clock,
+        token_program_id,
+    )?;
+
+    // the obligation is refreshed with the deposit and the borrow reserve, so that the borrow is
+    // limited the same way RefreshObligation prices the obligation after it
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    obligation.find_or_add_liquidity_to_borrows(
+        *borrow_reserve_info.key,
+        borrow_reserve.liquidity.cumulative_borrow_rate_wads,
+    )?;
+    let obligation_reserves_len = obligation.deposits.len() + obligation.borrows.len();
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    let mut refresh_infos =
+        next_account_infos(account_info_iter, obligation_reserves_len)?.to_vec();
+    refresh_infos.push(lending_market_info.clone());
+    _refresh_obligation(
+        program_id,
+        obligation_info,
+        &mut refresh_infos.iter().peekable(),
+        clock,
+    )?;
+
+    // the refresh writes the borrow reserve when it settles a matured fixed term borrow
+    let mut borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    let remaining_borrow_value = obligation
+        .allowed_borrow_value
+        .try_sub(obligation.borrowed_value_upper_bound)
+        .unwrap_or_else(|_| Decimal::zero());
+
+    let remaining_reserve_capacity = Decimal::from(borrow_reserve.config.borrow_limit)
+        .try_sub(borrow_reserve.liquidity.total_borrowed()?)
+        .unwrap_or_else(|_| Decimal::zero());
+
+    let CalculateBorrowResult {
+        borrow_amount,
+        receive_amount: _,
+        borrow_fee,
+        host_fee,
+    } = borrow_reserve.calculate_borrow(
+        liquidity_amount,
+        remaining_borrow_value,
+        remaining_reserve_capacity,
+    )?;
+
+    let borrowed_value = obligation.borrowed_value.try_add(
+        borrow_reserve
+            .liquidity
+            .market_value(borrow_amount)?
+            .try_mul(borrow_reserve.config.borrow_weight()?)?,
+    )?;
+    if borrowed_value
+        > obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(target_ltv))?
+    {
+        msg!("Obligation loan to value after the leverage deposit exceeds the target");
+        return Err(LendingError::LeverageTargetExceeded.into());
+    }
+
+    let cumulative_borrow_rate_wads = borrow_reserve.liquidity.cumulative_borrow_rate_wads;
+    let borrow_reserve_isolated = borrow_reserve.config.isolated;
+
+    update_outflow_rate_limiters(
+        &mut lending_market,
+        lending_market_info,
+        &mut borrow_reserve,
+        borrow_amount,
+        clock,
+    )?;
+    borrow_reserve.liquidity.borrow(borrow_amount)?;
+    borrow_reserve.last_update.mark_stale();
+    Reserve::pack(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
+
+    obligation
+        .find_or_add_liquidity_to_borrows(*borrow_reserve_info.key, cumulative_borrow_rate_wads)?
+        .borrow(borrow_amount)?;
+    if borrow_reserve_isolated {
+        obligation.borrowing_isolated_asset = true;
+    }
+    obligation.last_update.mark_stale();
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    let mut owner_fee = borrow_fee;
+    if let Ok(host_fee_receiver_info) = next_account_info(account_info_iter) {
+        if host_fee > 0 {
+            owner_fee = owner_fee
+                .checked_sub(host_fee)
+                .ok_or(LendingError::MathOverflow)?;
+
+            spl_token_transfer(TokenTransferParams {
+                source: borrow_reserve_liquidity_supply_info.clone(),
+                destination: host_fee_receiver_info.clone(),
+                amount: host_fee,
+                authority: lending_market_authority_info.clone(),
+                authority_signer_seeds,
+                token_program: token_program_id.clone(),
+            })?;
+        }
+    }
+    if owner_fee > 0 {
+        spl_token_transfer(TokenTransferParams {
+            source: borrow_reserve_liquidity_supply_info.clone(),
+            destination: borrow_reserve_liquidity_fee_receiver_info.clone(),
+            amount: owner_fee,
+            authority: lending_market_authority_info.clone(),
+            authority_signer_seeds,
+            token_program: token_program_id.clone(),
+        })?;
+    }
+
+    LendingEvent::BorrowObligationLiquidity {
+        obligation: *obligation_info.key,
+        reserve: *borrow_reserve_info.key,
+        liquidity_amount,
+        borrow_fee,
+        host_fee,
+    }
+    .emit();
+
+    Ok(())
+}
+
+fn process_close_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+    if !obligation.deposits.is_empty() || !obligation.borrows.is_empty() {
+        msg!("Obligation must have no deposits or borrows to be closed");
+        return Err(LendingError::ObligationNotEmpty.into());
+    }
+
+    obligation_info.data.borrow_mut().fill(0);
+
+    let obligation_lamports = obligation_info.lamports();
+    **obligation_info.lamports.borrow_mut() = 0;
+    **obligation_owner_info.lamports.borrow_mut() = obligation_owner_info
+        .lamports()
+        .checked_add(obligation_lamports)
+        .ok_or(LendingError::MathOverflow)?;
+
+    LendingEvent::CloseObligation {
+        obligation: *obligation_info.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_obligation_owner(
+    program_id: &Pubkey,
+    require_new_owner_signature: bool,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let new_obligation_owner_info = next_account_info(account_info_iter)?;
+    let new_owner_obligation_info = next_account_info(account_info_iter)?;
+    let market_member_info = next_account_info(account_info_iter).ok();
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if require_new_owner_signature && !new_obligation_owner_info.is_signer {
+        msg!("New obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let new_owner_obligation_pubkey = obligation_address_with_seed(
+        new_obligation_owner_info.key,
+        lending_market_info.key,
+        program_id,
+    )?;
+    if &new_owner_obligation_pubkey != new_owner_obligation_info.key {
+        msg!("New owner seeded obligation does not match the new owner seeded obligation provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if new_owner_obligation_info.owner == program_id
+        && Obligation::unpack_unchecked(&new_owner_obligation_info.data.borrow())?.is_initialized()
+    {
+        msg!("New obligation owner already has an obligation at its seeded address");
+        return Err(LendingError::ObligationOwnerConflict.into());
+    }
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    assert_market_member(
+        program_id,
+        &lending_market,
+        lending_market_info,
+        new_obligation_owner_info,
+        market_member_info,
+    )?;
+
+    obligation.owner = *new_obligation_owner_info.key;
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    LendingEvent::SetObligationOwner {
+        obligation: *obligation_info.key,
+        new_owner: *new_obligation_owner_info.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_resize_obligation(
+    program_id: &Pubkey,
+    max_reserves: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if (max_reserves as usize) <= obligation.max_reserves()
+        || (max_reserves as usize) > MAX_EXTENDED_OBLIGATION_RESERVES
+    {
+        msg!(
+            "Max reserves must be greater than {} and at most {}",
+            obligation.max_reserves(),
+            MAX_EXTENDED_OBLIGATION_RESERVES
+        );
+        return Err(LendingError::InvalidAmount.into());
+    }
+
+    obligation.extra_reserves = max_reserves - MAX_OBLIGATION_RESERVES as u8;
+    let obligation_len = Obligation::packed_len(obligation.extra_reserves);
+
+    let rent_lamports = rent
+        .minimum_balance(obligation_len)
+        .saturating_sub(obligation_info.lamports());
+    if rent_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                obligation_owner_info.key,
+                obligation_info.key,
+                rent_lamports,
+            ),
+            &[
+                obligation_owner_info.clone(),
+                obligation_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    obligation_info.realloc(obligation_len, true)?;
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    LendingEvent::ResizeObligation {
+        obligation: *obligation_info.key,
+        max_reserves,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_lending_market_emode_group(
+    program_id: &Pubkey,
+    group: u8,
+    config: EModeGroup,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    validate_emode_group(group, config)?;
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    if !lending_market.emode_groups[group as usize - 1].is_risk_reducing_change(&config) {
+        msg!("Efficiency mode group changes that add risk must be proposed with ProposeLendingMarketEModeGroup");
+        return Err(LendingError::EModeGroupChangeNotAllowed.into());
+    }
+
+    lending_market.emode_groups[group as usize - 1] = config;
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    LendingEvent::SetLendingMarketEModeGroup {
+        lending_market: *lending_market_info.key,
+        group,
+        loan_to_value_ratio: config.loan_to_value_ratio,
+        liquidation_threshold: config.liquidation_threshold,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_propose_lending_market_emode_group(
+    program_id: &Pubkey,
+    group: u8,
+    config: EModeGroup,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    validate_emode_group(group, config)?;
+    let account_info_iter = &mut accounts.iter();
+    let pending_emode_group_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    let (pending_emode_group_pubkey, bump_seed) =
+        pending_emode_group_address(program_id, lending_market_info.key, group);
+    if &pending_emode_group_pubkey != pending_emode_group_info.key {
+        msg!("Pending efficiency mode group provided does not match the address derived from the lending market and group");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if pending_emode_group_info.data_is_empty() {
+        create_program_derived_account(
+            program_id,
+            pending_emode_group_info,
+            lending_market_owner_info,
+            system_program_info,
+            rent,
+            PendingEModeGroup::LEN,
+            &[
+                lending_market_info.key.as_ref(),
+                &[group],
+                PENDING_EMODE_GROUP_SEED,
+                &[bump_seed],
+            ],
+        )?;
+    } else if pending_emode_group_info.owner != program_id {
+        msg!("Pending efficiency mode group provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let activation_slot = clock
+        .slot
+        .checked_add(RESERVE_CONFIG_DELAY_SLOTS)
+        .ok_or(LendingError::MathOverflow)?;
+    PendingEModeGroup::pack(
+        PendingEModeGroup {
+            version: PROGRAM_VERSION,
+            lending_market: *lending_market_info.key,
+            group,
+            config,
+            activation_slot,
+        },
+        &mut pending_emode_group_info.data.borrow_mut(),
+    )?;
+
+    LendingEvent::ProposeLendingMarketEModeGroup {
+        lending_market: *lending_market_info.key,
+        group,
+        activation_slot,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_apply_lending_market_emode_group(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pending_emode_group_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    if pending_emode_group_info.owner != program_id {
+        msg!("Pending efficiency mode group provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    let pending_emode_group = PendingEModeGroup::unpack(&pending_emode_group_info.data.borrow())?;
+    if &pending_emode_group.lending_market != lending_market_info.key {
+        msg!("Pending efficiency mode group lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+
+    if clock.slot < pending_emode_group.activation_slot {
+        msg!(
+            "Pending efficiency mode group cannot be applied before slot {}",
+            pending_emode_group.activation_slot
+        );
+        return Err(LendingError::EModeGroupNotActive.into());
+    }
+
+    let PendingEModeGroup { group, config, .. } = pending_emode_group;
+    lending_market.emode_groups[group as usize - 1] = config;
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    pending_emode_group_info.data.borrow_mut().fill(0);
+
+    let pending_emode_group_lamports = pending_emode_group_info.lamports();
+    **pending_emode_group_info.lamports.borrow_mut() = 0;
+    **lending_market_owner_info.lamports.borrow_mut() = lending_market_owner_info
+        .lamports()
+        .checked_add(pending_emode_group_lamports)
+        .ok_or(LendingError::MathOverflow)?;
+
+    LendingEvent::SetLendingMarketEModeGroup {
+        lending_market: *lending_market_info.key,
+        group,
+        loan_to_value_ratio: config.loan_to_value_ratio,
+        liquidation_threshold: config.liquidation_threshold,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_propose_reserve_config(
+    program_id: &Pubkey,
+    config: ReserveConfig,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    validate_reserve_config(config)?;
+    let account_info_iter = &mut accounts.iter();
+    let pending_reserve_config_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let pyth_product_info = next_account_info(account_info_iter)?;
+    let pyth_price_info = next_account_info(account_info_iter)?;
+    let switchboard_feed_info = next_account_info(account_info_iter)?;
+    let token_swap_info = next_account_info(account_info_iter)?;
+    let stake_pool_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    let reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    if *pyth_price_info.key != reserve.liquidity.pyth_oracle_pubkey {
+        validate_pyth_keys(&lending_market, pyth_product_info, pyth_price_info)?;
+    }
+    if *switchboard_feed_info.key != reserve.liquidity.switchboard_oracle_pubkey {
+        validate_switchboard_keys(&lending_market, switchboard_feed_info)?;
+    }
+    if *switchboard_feed_info.key == spl_token_lending::NULL_PUBKEY
+        && (*pyth_price_info.key == spl_token_lending::NULL_PUBKEY
+            || *pyth_product_info.key == spl_token_lending::NULL_PUBKEY)
+    {
+        msg!("At least one price oracle must have a non-null pubkey");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let token_swap_oracle_pubkey = if *token_swap_info.key == spl_token_lending::NULL_PUBKEY {
+        Pubkey::default()
+    } else {
+        if *token_swap_info.key != reserve.liquidity.token_swap_oracle_pubkey {
+            validate_token_swap_oracle(&reserve, token_swap_info)?;
+        }
+        *token_swap_info.key
+    };
+    let stake_pool_oracle_pubkey = if *stake_pool_info.key == spl_token_lending::NULL_PUBKEY {
+        Pubkey::default()
+    } else {
+        if *stake_pool_info.key != reserve.liquidity.stake_pool_oracle_pubkey {
+            validate_stake_pool_oracle(&reserve, stake_pool_info)?;
+        }
+        *stake_pool_info.key
+    };
+    if token_swap_oracle_pubkey != Pubkey::default()
+        && stake_pool_oracle_pubkey != Pubkey::default()
+    {
+        msg!("Reserve cannot be priced by both a token swap and a stake pool oracle");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let (pending_reserve_config_pubkey, bump_seed) =
+        pending_reserve_config_address(program_id, reserve_info.key);
+    if &pending_reserve_config_pubkey != pending_reserve_config_info.key {
+        msg!("Pending reserve config provided does not match the address derived from the reserve");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if pending_reserve_config_info.data_is_empty() {
+        create_program_derived_account(
+            program_id,
+            pending_reserve_config_info,
+            lending_market_owner_info,
+            system_program_info,
+            rent,
+            PendingReserveConfig::LEN,
+            &[
+                reserve_info.key.as_ref(),
+                PENDING_RESERVE_CONFIG_SEED,
+                &[bump_seed],
+            ],
+        )?;
+    } else if pending_reserve_config_info.owner != program_id {
+        msg!("Pending reserve config provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let activation_slot = clock
+        .slot
+        .checked_add(RESERVE_CONFIG_DELAY_SLOTS)
+        .ok_or(LendingError::MathOverflow)?;
+    PendingReserveConfig::pack(
+        PendingReserveConfig {
+            version: PROGRAM_VERSION,
+            reserve: *reserve_info.key,
+            config,
+            pyth_oracle_pubkey: *pyth_price_info.key,
+            switchboard_oracle_pubkey: *switchboard_feed_info.key,
+            activation_slot,
+            token_swap_oracle_pubkey,
+            stake_pool_oracle_pubkey,
+        },
+        &mut pending_reserve_config_info.data.borrow_mut(),
+    )?;
+
+    LendingEvent::ProposeReserveConfig {
+        reserve: *reserve_info.key,
+        activation_slot,
+    }
+    .emit();
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_apply_reserve_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pending_reserve_config_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    if pending_reserve_config_info.owner != program_id {
+        msg!("Pending reserve config provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    let pending_reserve_config =
+        PendingReserveConfig::unpack(&pending_reserve_config_info.data.borrow())?;
+    if &pending_reserve_config.reserve != reserve_info.key {
+        msg!("Pending reserve config reserve does not match the reserve provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+
+    if clock.slot < pending_reserve_config.activation_slot {
+        msg!(
+            "Pending reserve config cannot be applied before slot {}",
+            pending_reserve_config.activation_slot
+        );
+        return Err(LendingError::ReserveConfigNotActive.into());
+    }
+
+    reserve.config = pending_reserve_config.config;
+    if reserve.liquidity.pyth_oracle_pubkey != pending_reserve_config.pyth_oracle_pubkey
+        || reserve.liquidity.switchboard_oracle_pubkey
+            != pending_reserve_config.switchboard_oracle_pubkey
+    {
+        reserve.liquidity.pyth_oracle_pubkey = pending_reserve_config.pyth_oracle_pubkey;
+        reserve.liquidity.switchboard_oracle_pubkey =
+            pending_reserve_config.switchboard_oracle_pubkey;
+        // the market price must be read from the new oracles before it is used
+        reserve.last_update.mark_stale();
+    }
+    if reserve.liquidity.token_swap_oracle_pubkey != pending_reserve_config.token_swap_oracle_pubkey
+    {
+        reserve.liquidity.token_swap_oracle_pubkey =
+            pending_reserve_config.token_swap_oracle_pubkey;
+        reserve.last_update.mark_stale();
+
+        LendingEvent::SetReserveTokenSwapOracle {
+            reserve: *reserve_info.key,
+            token_swap: reserve.liquidity.token_swap_oracle_pubkey,
+        }
+        .emit();
+    }
+    if reserve.liquidity.stake_pool_oracle_pubkey != pending_reserve_config.stake_pool_oracle_pubkey
+    {
+        reserve.liquidity.stake_pool_oracle_pubkey =
+            pending_reserve_config.stake_pool_oracle_pubkey;
+        reserve.last_update.mark_stale();
+
+        LendingEvent::SetReserveStakePoolOracle {
+            reserve: *reserve_info.key,
+            stake_pool: reserve.liquidity.stake_pool_oracle_pubkey,
+        }
+        .emit();
+    }
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    pending_reserve_config_info.data.borrow_mut().fill(0);
+
+    let pending_reserve_config_lamports = pending_reserve_config_info.lamports();
+    **pending_reserve_config_info.lamports.borrow_mut() = 0;
+    **lending_market_owner_info.lamports.borrow_mut() = lending_market_owner_info
+        .lamports()
+        .checked_add(pending_reserve_config_lamports)
+        .ok_or(LendingError::MathOverflow)?;
+
+    LendingEvent::UpdateReserveConfig {
+        reserve: *reserve_info.key,
+    }
+    .emit();
+
+    Ok(())
+}
+
+/// Returns an error if the operation is paused on either the lending market or the reserve
+fn assert_not_paused(lending_market: &LendingMarket, reserve: &Reserve, flag: u8) -> ProgramResult {
+    if lending_market.is_paused(flag) {
+        msg!("Operation is paused on the lending market");
+        return Err(LendingError::OperationPaused.into());
+    }
+    if reserve.is_paused(flag) {
+        msg!("Operation is paused on the reserve");
+        return Err(LendingError::OperationPaused.into());
+    }
+    Ok(())
+}
+
+/// Returns an error if the reserve is deprecated
+fn assert_not_deprecated(reserve: &Reserve) -> ProgramResult {
+    if reserve.deprecated {
+        msg!("Reserve is deprecated");
+        return Err(LendingError::ReserveDeprecated.into());
+    }
+    Ok(())
+}
+
+/// The owner can set any pause flags, while the guardian can only add to the current flags
+fn validate_pause_flags(
+    lending_market: &LendingMarket,
+    pause_authority_info: &AccountInfo,
+    current_pause_flags: u8,
+    new_pause_flags: u8,
+) -> ProgramResult {
+    if !pause_authority_info.is_signer {
+        msg!("Pause authority provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if new_pause_flags & !PAUSE_ALL != 0 {
+        msg!("Pause flags contain unknown bits");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if &lending_market.owner == pause_authority_info.key {
+        return Ok(());
+    }
+    if lending_market.guardian == Pubkey::default()
+        || &lending_market.guardian != pause_authority_info.key
+    {
+        msg!("Pause authority must be the lending market owner or guardian");
+        return Err(LendingError::InvalidPauseAuthority.into());
+    }
+    if new_pause_flags & current_pause_flags != current_pause_flags {
+        msg!("Lending market guardian cannot clear pause flags");
+        return Err(LendingError::InvalidPauseAuthority.into());
+    }
+    Ok(())
+}
+
+fn assert_allowlist_admin(
+    lending_market: &LendingMarket,
+    allowlist_admin_info: &AccountInfo,
+) -> ProgramResult {
+    if !lending_market.is_allowlisted()
+        || &lending_market.allowlist_admin != allowlist_admin_info.key
+    {
+        msg!("Allowlist admin does not match the lending market allowlist admin provided");
+        return Err(LendingError::InvalidAllowlistAdmin.into());
+    }
+    if !allowlist_admin_info.is_signer {
+        msg!("Allowlist admin provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    Ok(())
+}
+
+/// Wallets using an allowlisted lending market must provide the membership issued to them
+fn assert_market_member(
+    program_id: &Pubkey,
+    lending_market: &LendingMarket,
+    lending_market_info: &AccountInfo,
+    wallet_info: &AccountInfo,
+    market_member_info: Option<&AccountInfo>,
+) -> ProgramResult {
+    if !lending_market.is_allowlisted() {
+        return Ok(());
+    }
+    let market_member_info = match market_member_info {
+        Some(market_member_info) if market_member_info.owner == program_id => market_member_info,
+        _ => {
+            msg!("Wallets must provide their market member account to use an allowlisted lending market");
+            return Err(LendingError::NotMarketMember.into());
+        }
+    };
+    let (market_member_pubkey, _bump_seed) =
+        market_member_address(program_id, lending_market_info.key, wallet_info.key);
+    if &market_member_pubkey != market_member_info.key {
+        msg!("Market member address does not match the market member provided");
+        return Err(LendingError::NotMarketMember.into());
+    }
+    let market_member = MarketMember::unpack(&market_member_info.data.borrow())?;
+    if &market_member.lending_market != lending_market_info.key
+        || &market_member.member != wallet_info.key
+    {
+        msg!("Market member provided does not match the lending market and wallet provided");
+        return Err(LendingError::NotMarketMember.into());
+    }
+    Ok(())
+}
+
+/// Lending markets created before the pending owner and allowlist admin were stored are
+/// reallocated to hold them, with the payer funding the additional rent
+fn realloc_legacy_lending_market<'a>(
+    lending_market_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    rent: &Rent,
+    system_program_info: &AccountInfo<'a>,
+) -> ProgramResult {
+    if lending_market_info.data_len() >= LendingMarket::LEN {
+        return Ok(());
+    }
+
+    let rent_lamports = rent
+        .minimum_balance(LendingMarket::LEN)
+        .saturating_sub(lending_market_info.lamports());
+    if rent_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, lending_market_info.key, rent_lamports),
+            &[
+                payer_info.clone(),
+                lending_market_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    lending_market_info.realloc(LendingMarket::LEN, true)?;
+    Ok(())
+}
+
+/// Creates a program derived account owned by the lending program, with the payer funding its
+/// rent. The account is allocated and assigned rather than created, which would fail if anyone
+/// has already sent lamports to its address.
+fn create_program_derived_account<'a>(
+    program_id: &Pubkey,
+    account_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    rent: &Rent,
+    len: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = rent
+        .minimum_balance(len)
+        .saturating_sub(account_info.lamports());
+    if rent_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, account_info.key, rent_lamports),
+            &[
+                payer_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    invoke_signed(
+        &system_instruction::allocate(account_info.key, len as u64),
+        &[account_info.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account_info.key, program_id),
+        &[account_info.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Records a liquidity outflow against the reserve and lending market rate limiters.
+/// The lending market is only written back when its rate limiter is enabled.
+fn update_outflow_rate_limiters(
+    lending_market: &mut LendingMarket,
+    lending_market_info: &AccountInfo,
+    reserve: &mut Reserve,
+    liquidity_amount: Decimal,
+    clock: &Clock,
+) -> ProgramResult {
+    let outflow_value = reserve.liquidity.market_value(liquidity_amount)?;
+    reserve.rate_limiter.update(clock.slot, outflow_value)?;
+
+    if lending_market.rate_limiter.is_enabled() {
+        lending_market
+            .rate_limiter
+            .update(clock.slot, outflow_value)?;
+        LendingMarket::pack(
+            lending_market.clone(),
+            &mut lending_market_info.data.borrow_mut(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Swaps liquidity through spl-token-swap and returns the amount received
+#[allow(clippy::too_many_arguments)]
+fn swap_liquidity<'a, 'b>(
+    token_swap_program_id: &AccountInfo<'a>,
+    account_info_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+    source_info: &AccountInfo<'a>,
+    destination_info: &AccountInfo<'a>,
+    user_transfer_authority_info: &AccountInfo<'a>,
+    token_program_id: &AccountInfo<'a>,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Result<u64, ProgramError> {
+    let token_swap_info = next_account_info(account_info_iter)?;
+    let token_swap_authority_info = next_account_info(account_info_iter)?;
+    let token_swap_source_info = next_account_info(account_info_iter)?;
+    let token_swap_destination_info = next_account_info(account_info_iter)?;
+    let token_swap_pool_mint_info = next_account_info(account_info_iter)?;
+    let token_swap_pool_fee_info = next_account_info(account_info_iter)?;
+
+    let initial_destination_amount = Account::unpack(&destination_info.data.borrow())?.amount;
+
+    invoke(
+        &token_swap::swap(
+            *token_swap_program_id.key,
+            *token_program_id.key,
+            *token_swap_info.key,
+            *token_swap_authority_info.key,
+            *user_transfer_authority_info.key,
+            *source_info.key,
+            *token_swap_source_info.key,
+            *token_swap_destination_info.key,
+            *destination_info.key,
+            *token_swap_pool_mint_info.key,
+            *token_swap_pool_fee_info.key,
+            amount_in,
+            minimum_amount_out,
+        ),
+        &[
+            token_swap_info.clone(),
+            token_swap_authority_info.clone(),
+            user_transfer_authority_info.clone(),
+            source_info.clone(),
+            token_swap_source_info.clone(),
+            token_swap_destination_info.clone(),
+            destination_info.clone(),
+            token_swap_pool_mint_info.clone(),
+            token_swap_pool_fee_info.clone(),
+            token_program_id.clone(),
+            token_swap_program_id.clone(),
+        ],
+    )?;
+
+    let amount_out = Account::unpack(&destination_info.data.borrow())?
+        .amount
+        .checked_sub(initial_destination_amount)
+        .ok_or(LendingError::MathOverflow)?;
+    if amount_out < minimum_amount_out {
+        msg!("Swap output is below the minimum liquidity out");
+        return Err(LendingError::InsufficientSwapOutput.into());
+    }
+
+    LendingEvent::SwapLiquidity {
+        token_swap: *token_swap_info.key,
+        amount_in,
+        amount_out,
+    }
+    .emit();
+
+    Ok(amount_out)
+}
+
+fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
+    if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
+        msg!(
+            "Rent exempt balance insufficient got {} expected {}",
+            &account_info.lamports().to_string(),
+            &rent.minimum_balance(account_info.data_len()).to_string(),
+        );
+        Err(LendingError::NotRentExempt.into())
+    } else {
         Ok(())
     }
 }
@@ -2114,6 +5361,168 @@ fn get_price(
     get_switchboard_price(switchboard_feed_info, clock)
 }
 
+/// Prices a constant product pool token from the oracle prices of the underlying reserves.
+///
+/// Uses fair reserves rather than the pool balances: with a constant product `k = a * b` and
+/// underlying prices `p_a` and `p_b`, the pool value at the oracle prices is
+/// `2 * sqrt(k * p_a * p_b)`, which can't be moved by trading against the pool.
+fn get_token_swap_pool_price(
+    program_id: &Pubkey,
+    reserve: &Reserve,
+    token_swap_oracle_infos: &[AccountInfo],
+    clock: &Clock,
+) -> Result<Decimal, ProgramError> {
+    let account_info_iter = &mut token_swap_oracle_infos.iter();
+    let token_swap_info = next_account_info(account_info_iter)?;
+    let token_a_info = next_account_info(account_info_iter)?;
+    let token_b_info = next_account_info(account_info_iter)?;
+    let pool_mint_info = next_account_info(account_info_iter)?;
+    let token_a_reserve_info = next_account_info(account_info_iter)?;
+    let token_b_reserve_info = next_account_info(account_info_iter)?;
+
+    if &reserve.liquidity.token_swap_oracle_pubkey != token_swap_info.key {
+        msg!("Reserve liquidity token swap oracle does not match the token swap provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if token_swap_info.owner != &token_swap::id() {
+        msg!("Token swap account provided is not owned by the token swap program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let token_swap = token_swap::SwapV1::unpack(&token_swap_info.data.borrow())?;
+    if &token_swap.token_a != token_a_info.key || &token_swap.token_b != token_b_info.key {
+        msg!("Token swap token accounts do not match the token accounts provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if &token_swap.pool_mint != pool_mint_info.key
+        || token_swap.pool_mint != reserve.liquidity.mint_pubkey
+    {
+        msg!("Token swap pool mint does not match the pool mint provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if token_a_info.owner != &spl_token::id()
+        || token_b_info.owner != &spl_token::id()
+        || pool_mint_info.owner != &spl_token::id()
+    {
+        msg!("Token swap token accounts and pool mint must be owned by the token program");
+        return Err(LendingError::InvalidTokenOwner.into());
+    }
+
+    let token_a_reserve = unpack_token_swap_underlying_reserve(
+        program_id,
+        reserve,
+        token_a_reserve_info,
+        &token_swap.token_a_mint,
+        clock,
+    )?;
+    let token_b_reserve = unpack_token_swap_underlying_reserve(
+        program_id,
+        reserve,
+        token_b_reserve_info,
+        &token_swap.token_b_mint,
+        clock,
+    )?;
+
+    let pool_supply = unpack_mint(&pool_mint_info.data.borrow())?.supply;
+    if pool_supply == 0 {
+        msg!("Token swap pool has no supply to price");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let token_a_value = token_a_reserve.liquidity.market_value(Decimal::from(
+        Account::unpack(&token_a_info.data.borrow())?.amount,
+    ))?;
+    let token_b_value = token_b_reserve.liquidity.market_value(Decimal::from(
+        Account::unpack(&token_b_info.data.borrow())?.amount,
+    ))?;
+    let pool_value = token_a_value
+        .try_sqrt()?
+        .try_mul(token_b_value.try_sqrt()?)?
+        .try_mul(2u64)?;
+
+    let decimals = 10u64
+        .checked_pow(reserve.liquidity.mint_decimals as u32)
+        .ok_or(LendingError::MathOverflow)?;
+    pool_value.try_mul(decimals)?.try_div(pool_supply)
+}
+
+fn unpack_token_swap_underlying_reserve(
+    program_id: &Pubkey,
+    reserve: &Reserve,
+    underlying_reserve_info: &AccountInfo,
+    underlying_mint: &Pubkey,
+    clock: &Clock,
+) -> Result<Reserve, ProgramError> {
+    let underlying_reserve = Reserve::unpack(&underlying_reserve_info.data.borrow())?;
+    if underlying_reserve_info.owner != program_id {
+        msg!("Token swap underlying reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if underlying_reserve.lending_market != reserve.lending_market {
+        msg!("Token swap underlying reserve lending market does not match the reserve lending market");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &underlying_reserve.liquidity.mint_pubkey != underlying_mint {
+        msg!("Token swap underlying reserve liquidity mint does not match the token swap mint");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if underlying_reserve.liquidity.token_swap_oracle_pubkey != Pubkey::default() {
+        msg!("Token swap underlying reserve cannot be priced by a token swap oracle");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if underlying_reserve.last_update.is_stale(clock.slot)? {
+        msg!("Token swap underlying reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+    Ok(underlying_reserve)
+}
+
+/// Prices a stake pool token from the pool exchange rate and the SOL oracle price.
+///
+/// The pool must have been updated in the current epoch, otherwise its total lamports don't
+/// include the latest staking rewards or slashing.
+fn get_stake_pool_price(
+    reserve: &Reserve,
+    switchboard_feed_info: &AccountInfo,
+    pyth_price_info: &AccountInfo,
+    stake_pool_oracle_infos: &[AccountInfo],
+    clock: &Clock,
+) -> Result<Decimal, ProgramError> {
+    let account_info_iter = &mut stake_pool_oracle_infos.iter();
+    let stake_pool_info = next_account_info(account_info_iter)?;
+
+    if &reserve.liquidity.stake_pool_oracle_pubkey != stake_pool_info.key {
+        msg!("Reserve liquidity stake pool oracle does not match the stake pool provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if stake_pool_info.owner != &stake_pool::id() {
+        msg!("Stake pool account provided is not owned by the stake pool program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let stake_pool = stake_pool::StakePool::unpack(&stake_pool_info.data.borrow())?;
+    if stake_pool.pool_mint != reserve.liquidity.mint_pubkey {
+        msg!("Stake pool mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if stake_pool.last_update_epoch < clock.epoch {
+        msg!("Stake pool must be updated in the current epoch");
+        return Err(LendingError::StakePoolStale.into());
+    }
+    if stake_pool.pool_token_supply == 0 {
+        msg!("Stake pool has no supply to price");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let sol_price = get_price(switchboard_feed_info, pyth_price_info, clock)?;
+    let decimals = 10u64
+        .checked_pow(reserve.liquidity.mint_decimals as u32)
+        .ok_or(LendingError::MathOverflow)?;
+    sol_price
+        .try_mul(stake_pool.total_lamports)?
+        .try_mul(decimals)?
+        .try_div(stake_pool.pool_token_supply)?
+        .try_div(LAMPORTS_PER_SOL)
+}
+
 fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decimal, ProgramError> {
     const STALE_AFTER_SLOTS_ELAPSED: u64 = 20;
 
@@ -2367,6 +5776,15 @@ fn spl_token_burn(params: TokenBurnParams<'_, '_>) -> ProgramResult {
     result.map_err(|_| LendingError::TokenBurnFailed.into())
 }
 
+/// Efficiency mode group shared by the reserves seen so far and the next reserve, Some(0) once two
+/// reserves differ
+fn shared_emode_group(emode_group: Option<u8>, config: &ReserveConfig) -> Option<u8> {
+    match emode_group {
+        Some(group) if group != config.emode_group => Some(0),
+        _ => Some(config.emode_group),
+    }
+}
+
 /// validates reserve configs
 #[inline(always)]
 fn validate_reserve_config(config: ReserveConfig) -> ProgramResult {
@@ -2382,6 +5800,17 @@ fn validate_reserve_config(config: ReserveConfig) -> ProgramResult {
         msg!("Liquidation bonus must be in range [0, 100]");
         return Err(LendingError::InvalidConfig.into());
     }
+    if config.max_liquidation_bonus > 100 {
+        msg!("Max liquidation bonus must be in range [0, 100]");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.max_fixed_term_slots > SLOTS_PER_YEAR {
+        msg!(
+            "Max fixed term must be in range [0, {}] slots",
+            SLOTS_PER_YEAR
+        );
+        return Err(LendingError::InvalidConfig.into());
+    }
     if config.liquidation_threshold <= config.loan_to_value_ratio
         || config.liquidation_threshold > 100
     {
@@ -2404,6 +5833,37 @@ fn validate_reserve_config(config: ReserveConfig) -> ProgramResult {
         msg!("Host fee percentage must be in range [0, 100]");
         return Err(LendingError::InvalidConfig.into());
     }
+    if config.emode_group as usize > MAX_EMODE_GROUPS {
+        msg!(
+            "Efficiency mode group must be in range [0, {}]",
+            MAX_EMODE_GROUPS
+        );
+        return Err(LendingError::InvalidConfig.into());
+    }
+    Ok(())
+}
+
+/// validates efficiency mode groups
+#[inline(always)]
+fn validate_emode_group(group: u8, config: EModeGroup) -> ProgramResult {
+    if group == 0 || group as usize > MAX_EMODE_GROUPS {
+        msg!(
+            "Efficiency mode group must be in range [1, {}]",
+            MAX_EMODE_GROUPS
+        );
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.loan_to_value_ratio >= 100 {
+        msg!("Loan to value ratio must be in range [0, 100)");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.is_enabled()
+        && (config.liquidation_threshold <= config.loan_to_value_ratio
+            || config.liquidation_threshold > 100)
+    {
+        msg!("Liquidation threshold must be in range (LTV, 100], or 0 to disable the group");
+        return Err(LendingError::InvalidConfig.into());
+    }
     Ok(())
 }
 
@@ -2475,6 +5935,38 @@ fn validate_switchboard_keys(
     Ok(())
 }
 
+/// validates a token swap oracle AccountInfo for a reserve
+fn validate_token_swap_oracle(reserve: &Reserve, token_swap_info: &AccountInfo) -> ProgramResult {
+    if token_swap_info.owner != &token_swap::id() {
+        msg!("Token swap account provided is not owned by the token swap program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let token_swap = token_swap::SwapV1::unpack(&token_swap_info.data.borrow())?;
+    if token_swap.curve_type != token_swap::CONSTANT_PRODUCT_CURVE {
+        msg!("Token swap oracle must be a constant product pool");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if token_swap.pool_mint != reserve.liquidity.mint_pubkey {
+        msg!("Token swap pool mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    Ok(())
+}
+
+/// validates a stake pool oracle AccountInfo for a reserve
+fn validate_stake_pool_oracle(reserve: &Reserve, stake_pool_info: &AccountInfo) -> ProgramResult {
+    if stake_pool_info.owner != &stake_pool::id() {
+        msg!("Stake pool account provided is not owned by the stake pool program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let stake_pool = stake_pool::StakePool::unpack(&stake_pool_info.data.borrow())?;
+    if stake_pool.pool_mint != reserve.liquidity.mint_pubkey {
+        msg!("Stake pool mint does not match the reserve liquidity mint");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    Ok(())
+}
+
 struct TokenInitializeMintParams<'a: 'b, 'b> {
     mint: AccountInfo<'a>,
     rent: AccountInfo<'a>,
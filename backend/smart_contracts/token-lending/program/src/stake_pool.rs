@@ -0,0 +1,81 @@
+//! Minimal interface to the spl-stake-pool program, used to price stake pool tokens
+
+use crate::error::LendingError;
+use arrayref::{array_ref, array_refs};
+use solana_program::{
+    clock::Epoch,
+    program_error::ProgramError,
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+solana_program::declare_id!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// Account type of a `StakePool` account
+pub const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+
+/// Size of the leading `StakePool` fields read by the lending program
+pub const STAKE_POOL_PREFIX_LEN: usize = 282; // 1 + 32 + 32 + 32 + 1 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 8
+
+/// Fields of a spl-stake-pool `StakePool` account used by the lending program
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StakePool {
+    /// Pool token mint
+    pub pool_mint: Pubkey,
+    /// Total stake under management, in lamports
+    pub total_lamports: u64,
+    /// Total supply of pool tokens
+    pub pool_token_supply: u64,
+    /// Last epoch the total lamports were updated
+    pub last_update_epoch: Epoch,
+}
+
+impl StakePool {
+    /// Unpacks the leading fields of a `StakePool` account
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < STAKE_POOL_PREFIX_LEN {
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        let input = array_ref![input, 0, STAKE_POOL_PREFIX_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            account_type,
+            _manager,
+            _staker,
+            _stake_deposit_authority,
+            _stake_withdraw_bump_seed,
+            _validator_list,
+            _reserve_stake,
+            pool_mint,
+            _manager_fee_account,
+            _token_program_id,
+            total_lamports,
+            pool_token_supply,
+            last_update_epoch,
+        ) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            8,
+            8
+        ];
+        if account_type[0] != STAKE_POOL_ACCOUNT_TYPE {
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        Ok(Self {
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            total_lamports: u64::from_le_bytes(*total_lamports),
+            pool_token_supply: u64::from_le_bytes(*pool_token_supply),
+            last_update_epoch: u64::from_le_bytes(*last_update_epoch),
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use super::*;
+use crate::error::LendingError;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+/// Seed of the credit delegation address derived from an obligation, a delegate and a reserve
+pub const CREDIT_DELEGATION_SEED: &[u8] = b"credit_delegation";
+
+/// Approval of a delegate by an obligation owner to borrow liquidity from a reserve against the
+/// obligation. The debt is recorded on the obligation and can be repaid by anyone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CreditDelegation {
+    /// Version of the struct
+    pub version: u8,
+    /// Obligation the delegate borrows against
+    pub obligation: Pubkey,
+    /// Delegate wallet
+    pub delegate: Pubkey,
+    /// Reserve the delegate can borrow from
+    pub borrow_reserve: Pubkey,
+    /// Liquidity the delegate can still borrow, including borrow fees. Repaying the debt does not
+    /// restore the allowance.
+    pub remaining_borrow_amount: u64,
+    /// Obligation owner that approved the delegation. The delegation lapses once the obligation
+    /// is transferred to another owner.
+    pub delegator: Pubkey,
+}
+
+impl CreditDelegation {
+    /// Use part of the allowance for a borrow
+    pub fn borrow(&mut self, borrow_amount: u64) -> ProgramResult {
+        self.remaining_borrow_amount = self
+            .remaining_borrow_amount
+            .checked_sub(borrow_amount)
+            .ok_or_else(|| {
+                msg!("Borrow amount exceeds the remaining credit delegation allowance");
+                LendingError::CreditDelegationExceeded
+            })?;
+        Ok(())
+    }
+}
+
+/// Address of the credit delegation of an obligation to a delegate for a reserve and its bump seed
+pub fn credit_delegation_address(
+    program_id: &Pubkey,
+    obligation: &Pubkey,
+    delegate: &Pubkey,
+    borrow_reserve: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            obligation.as_ref(),
+            delegate.as_ref(),
+            borrow_reserve.as_ref(),
+            CREDIT_DELEGATION_SEED,
+        ],
+        program_id,
+    )
+}
+
+impl Sealed for CreditDelegation {}
+impl IsInitialized for CreditDelegation {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const CREDIT_DELEGATION_LEN: usize = 169; // 1 + 32 + 32 + 32 + 8 + 32 + 32
+impl Pack for CreditDelegation {
+    const LEN: usize = CREDIT_DELEGATION_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, CREDIT_DELEGATION_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            obligation,
+            delegate,
+            borrow_reserve,
+            remaining_borrow_amount,
+            delegator,
+            _padding,
+        ) = mut_array_refs![
+            output,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            32
+        ];
+
+        *version = self.version.to_le_bytes();
+        obligation.copy_from_slice(self.obligation.as_ref());
+        delegate.copy_from_slice(self.delegate.as_ref());
+        borrow_reserve.copy_from_slice(self.borrow_reserve.as_ref());
+        *remaining_borrow_amount = self.remaining_borrow_amount.to_le_bytes();
+        delegator.copy_from_slice(self.delegator.as_ref());
+    }
+
+    /// Unpacks a byte buffer into a [CreditDelegation](struct.CreditDelegation.html)
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, CREDIT_DELEGATION_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            obligation,
+            delegate,
+            borrow_reserve,
+            remaining_borrow_amount,
+            delegator,
+            _padding,
+        ) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            PUBKEY_BYTES,
+            32
+        ];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Credit delegation version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            obligation: Pubkey::new_from_array(*obligation),
+            delegate: Pubkey::new_from_array(*delegate),
+            borrow_reserve: Pubkey::new_from_array(*borrow_reserve),
+            remaining_borrow_amount: u64::from_le_bytes(*remaining_borrow_amount),
+            delegator: Pubkey::new_from_array(*delegator),
+        })
+    }
+}
@@ -7,6 +7,9 @@ use solana_program::{
     pubkey::{Pubkey, PUBKEY_BYTES},
 };
 
+/// Number of efficiency mode groups a lending market can define
+pub const MAX_EMODE_GROUPS: usize = 8;
+
 /// Lending market state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct LendingMarket {
@@ -25,6 +28,20 @@ pub struct LendingMarket {
     pub oracle_program_id: Pubkey,
     /// Oracle (Switchboard) program id
     pub switchboard_oracle_program_id: Pubkey,
+    /// Guardian authority which can pause the market and its reserves
+    pub guardian: Pubkey,
+    /// Operations paused across the whole market, see `PAUSE_*` flags
+    pub pause_flags: u8,
+    /// Outflow rate limiter on borrows, redemptions and flash loans across the market
+    pub rate_limiter: RateLimiter,
+    /// Efficiency mode groups of correlated assets. Group `n` is stored at index `n - 1`, as
+    /// reserves use group 0 for none.
+    pub emode_groups: [EModeGroup; MAX_EMODE_GROUPS],
+    /// Proposed owner which can accept ownership of the market, default if none is proposed
+    pub pending_owner: Pubkey,
+    /// Admin issuing the memberships required to create obligations and deposit liquidity,
+    /// default if the market is open to every wallet
+    pub allowlist_admin: Pubkey,
 }
 
 impl LendingMarket {
@@ -44,6 +61,60 @@ impl LendingMarket {
         self.token_program_id = params.token_program_id;
         self.oracle_program_id = params.oracle_program_id;
         self.switchboard_oracle_program_id = params.switchboard_oracle_program_id;
+        self.guardian = Pubkey::default();
+        self.pause_flags = 0;
+        self.rate_limiter = RateLimiter::default();
+        self.emode_groups = [EModeGroup::default(); MAX_EMODE_GROUPS];
+        self.pending_owner = Pubkey::default();
+        self.allowlist_admin = Pubkey::default();
+    }
+
+    /// Check if an operation is paused across the market
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    /// Get an efficiency mode group, None if the group is 0 or not enabled
+    pub fn emode_group(&self, group: u8) -> Option<&EModeGroup> {
+        (group as usize)
+            .checked_sub(1)
+            .and_then(|index| self.emode_groups.get(index))
+            .filter(|emode_group| emode_group.is_enabled())
+    }
+
+    /// Check if only members issued by the allowlist admin can use the market
+    pub fn is_allowlisted(&self) -> bool {
+        self.allowlist_admin != Pubkey::default()
+    }
+
+    /// Fields stored after the legacy layout must be default to pack into a legacy account
+    fn fits_legacy_layout(&self) -> bool {
+        self.pending_owner == Pubkey::default() && !self.is_allowlisted()
+    }
+}
+
+/// Efficiency mode group of correlated assets. An obligation whose deposits and borrows all
+/// belong to the same group counts each deposit at the higher of the group's and the reserve's
+/// loan to value ratio and liquidation threshold.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EModeGroup {
+    /// Target ratio of the value of borrows to deposits, as a percentage
+    pub loan_to_value_ratio: u8,
+    /// Loan to value ratio at which an obligation can be liquidated, as a percentage
+    pub liquidation_threshold: u8,
+}
+
+impl EModeGroup {
+    /// A group with a zero liquidation threshold is disabled
+    pub fn is_enabled(&self) -> bool {
+        self.liquidation_threshold != 0
+    }
+
+    /// Whether changing to `config` only reduces risk: a lower or equal loan to value ratio with
+    /// the liquidation threshold unchanged
+    pub fn is_risk_reducing_change(&self, config: &EModeGroup) -> bool {
+        config.loan_to_value_ratio <= self.loan_to_value_ratio
+            && config.liquidation_threshold == self.liquidation_threshold
     }
 }
 
@@ -71,12 +142,35 @@ impl IsInitialized for LendingMarket {
     }
 }
 
-const LENDING_MARKET_LEN: usize = 290; // 1 + 1 + 32 + 32 + 32 + 32 + 32 + 128
+const EMODE_GROUP_LEN: usize = 2; // 1 + 1
+const LEGACY_LENDING_MARKET_LEN: usize = 290; // 1 + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 1 + 56 + 16 + 23
+const LENDING_MARKET_LEN: usize = 418; // 290 + 32 + 32 + 64
+
+/// Lending markets created before the pending owner was added are `LEGACY_LENDING_MARKET_LEN`
+/// long and have no pending owner or allowlist admin until they are reallocated to `LENDING_MARKET_LEN`.
 impl Pack for LendingMarket {
     const LEN: usize = LENDING_MARKET_LEN;
 
-    fn pack_into_slice(&self, output: &mut [u8]) {
-        let output = array_mut_ref![output, 0, LENDING_MARKET_LEN];
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() != LENDING_MARKET_LEN && input.len() != LEGACY_LENDING_MARKET_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unpack_from_slice(input)
+    }
+
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != LENDING_MARKET_LEN
+            && (dst.len() != LEGACY_LENDING_MARKET_LEN || !src.fits_legacy_layout())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let (output, extension) = dst.split_at_mut(LEGACY_LENDING_MARKET_LEN);
+        let output = array_mut_ref![output, 0, LEGACY_LENDING_MARKET_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -86,6 +180,10 @@ impl Pack for LendingMarket {
             token_program_id,
             oracle_program_id,
             switchboard_oracle_program_id,
+            guardian,
+            pause_flags,
+            rate_limiter,
+            emode_groups,
             _padding,
         ) = mut_array_refs![
             output,
@@ -96,7 +194,11 @@ impl Pack for LendingMarket {
             PUBKEY_BYTES,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            1,
+            RATE_LIMITER_LEN,
+            EMODE_GROUP_LEN * MAX_EMODE_GROUPS,
+            23
         ];
 
         *version = self.version.to_le_bytes();
@@ -106,11 +208,33 @@ impl Pack for LendingMarket {
         token_program_id.copy_from_slice(self.token_program_id.as_ref());
         oracle_program_id.copy_from_slice(self.oracle_program_id.as_ref());
         switchboard_oracle_program_id.copy_from_slice(self.switchboard_oracle_program_id.as_ref());
+        guardian.copy_from_slice(self.guardian.as_ref());
+        *pause_flags = self.pause_flags.to_le_bytes();
+        self.rate_limiter.pack_into_slice(rate_limiter);
+        for (emode_group, output) in self
+            .emode_groups
+            .iter()
+            .zip(emode_groups.chunks_exact_mut(EMODE_GROUP_LEN))
+        {
+            output[0] = emode_group.loan_to_value_ratio;
+            output[1] = emode_group.liquidation_threshold;
+        }
+
+        if !extension.is_empty() {
+            let extension =
+                array_mut_ref![extension, 0, LENDING_MARKET_LEN - LEGACY_LENDING_MARKET_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (pending_owner, allowlist_admin, _padding) =
+                mut_array_refs![extension, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+            pending_owner.copy_from_slice(self.pending_owner.as_ref());
+            allowlist_admin.copy_from_slice(self.allowlist_admin.as_ref());
+        }
     }
 
     /// Unpacks a byte buffer into a [LendingMarketInfo](struct.LendingMarketInfo.html)
-    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![input, 0, LENDING_MARKET_LEN];
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let (input, extension) = src.split_at(LEGACY_LENDING_MARKET_LEN);
+        let input = array_ref![input, 0, LEGACY_LENDING_MARKET_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -120,6 +244,10 @@ impl Pack for LendingMarket {
             token_program_id,
             oracle_program_id,
             switchboard_oracle_program_id,
+            guardian,
+            pause_flags,
+            rate_limiter,
+            emode_groups,
             _padding,
         ) = array_refs![
             input,
@@ -130,7 +258,11 @@ impl Pack for LendingMarket {
             PUBKEY_BYTES,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            PUBKEY_BYTES,
+            1,
+            RATE_LIMITER_LEN,
+            EMODE_GROUP_LEN * MAX_EMODE_GROUPS,
+            23
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -139,6 +271,29 @@ impl Pack for LendingMarket {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let mut unpacked_emode_groups = [EModeGroup::default(); MAX_EMODE_GROUPS];
+        for (emode_group, input) in unpacked_emode_groups
+            .iter_mut()
+            .zip(emode_groups.chunks_exact(EMODE_GROUP_LEN))
+        {
+            emode_group.loan_to_value_ratio = input[0];
+            emode_group.liquidation_threshold = input[1];
+        }
+
+        let (pending_owner, allowlist_admin) = if extension.is_empty() {
+            (Pubkey::default(), Pubkey::default())
+        } else {
+            let extension =
+                array_ref![extension, 0, LENDING_MARKET_LEN - LEGACY_LENDING_MARKET_LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (pending_owner, allowlist_admin, _padding) =
+                array_refs![extension, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+            (
+                Pubkey::new_from_array(*pending_owner),
+                Pubkey::new_from_array(*allowlist_admin),
+            )
+        };
+
         Ok(Self {
             version,
             bump_seed: u8::from_le_bytes(*bump_seed),
@@ -147,6 +302,86 @@ impl Pack for LendingMarket {
             token_program_id: Pubkey::new_from_array(*token_program_id),
             oracle_program_id: Pubkey::new_from_array(*oracle_program_id),
             switchboard_oracle_program_id: Pubkey::new_from_array(*switchboard_oracle_program_id),
+            guardian: Pubkey::new_from_array(*guardian),
+            pause_flags: u8::from_le_bytes(*pause_flags),
+            rate_limiter: RateLimiter::unpack_from_slice(rate_limiter),
+            emode_groups: unpacked_emode_groups,
+            pending_owner,
+            allowlist_admin,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_and_unpack_legacy_lending_market() {
+        let mut lending_market = LendingMarket::new(InitLendingMarketParams {
+            bump_seed: 255,
+            owner: Pubkey::new_unique(),
+            quote_currency: [1; 32],
+            token_program_id: Pubkey::new_unique(),
+            oracle_program_id: Pubkey::new_unique(),
+            switchboard_oracle_program_id: Pubkey::new_unique(),
+        });
+
+        let mut legacy_data = [0; LEGACY_LENDING_MARKET_LEN];
+        LendingMarket::pack(lending_market.clone(), &mut legacy_data).unwrap();
+        assert_eq!(
+            LendingMarket::unpack(&legacy_data),
+            Ok(lending_market.clone())
+        );
+
+        // legacy lending markets must be reallocated to hold a pending owner or allowlist admin
+        lending_market.pending_owner = Pubkey::new_unique();
+        assert_eq!(
+            LendingMarket::pack(lending_market.clone(), &mut legacy_data),
+            Err(ProgramError::InvalidAccountData)
+        );
+        lending_market.pending_owner = Pubkey::default();
+        lending_market.allowlist_admin = Pubkey::new_unique();
+        assert_eq!(
+            LendingMarket::pack(lending_market.clone(), &mut legacy_data),
+            Err(ProgramError::InvalidAccountData)
+        );
+        lending_market.pending_owner = Pubkey::new_unique();
+
+        let mut data = [0; LENDING_MARKET_LEN];
+        data[..LEGACY_LENDING_MARKET_LEN].copy_from_slice(&legacy_data);
+        LendingMarket::pack(lending_market.clone(), &mut data).unwrap();
+        assert_eq!(LendingMarket::unpack(&data), Ok(lending_market));
+
+        assert_eq!(
+            LendingMarket::unpack(&data[..LENDING_MARKET_LEN - 1]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn emode_group_risk_reducing_change() {
+        let emode_group = EModeGroup {
+            loan_to_value_ratio: 90,
+            liquidation_threshold: 95,
+        };
+        assert!(emode_group.is_risk_reducing_change(&emode_group));
+        assert!(emode_group.is_risk_reducing_change(&EModeGroup {
+            loan_to_value_ratio: 80,
+            ..emode_group
+        }));
+
+        assert!(!emode_group.is_risk_reducing_change(&EModeGroup {
+            loan_to_value_ratio: 91,
+            ..emode_group
+        }));
+        // a lower liquidation threshold or disabling the group would make existing obligations
+        // liquidatable
+        assert!(!emode_group.is_risk_reducing_change(&EModeGroup {
+            loan_to_value_ratio: 80,
+            liquidation_threshold: 90,
+        }));
+        assert!(!emode_group.is_risk_reducing_change(&EModeGroup::default()));
+        assert!(!EModeGroup::default().is_risk_reducing_change(&emode_group));
+    }
+}
@@ -0,0 +1,77 @@
+use super::*;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    msg,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+/// Seed of the member address derived from a lending market and a wallet
+pub const MARKET_MEMBER_SEED: &[u8] = b"market_member";
+
+/// Membership of a wallet in a lending market with an allowlist, issued by the allowlist admin
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarketMember {
+    /// Version of the struct
+    pub version: u8,
+    /// Lending market the wallet is a member of
+    pub lending_market: Pubkey,
+    /// Member wallet
+    pub member: Pubkey,
+}
+
+/// Address of the membership of a wallet in a lending market and its bump seed
+pub fn market_member_address(
+    program_id: &Pubkey,
+    lending_market: &Pubkey,
+    member: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[lending_market.as_ref(), member.as_ref(), MARKET_MEMBER_SEED],
+        program_id,
+    )
+}
+
+impl Sealed for MarketMember {}
+impl IsInitialized for MarketMember {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const MARKET_MEMBER_LEN: usize = 129; // 1 + 32 + 32 + 64
+impl Pack for MarketMember {
+    const LEN: usize = MARKET_MEMBER_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, MARKET_MEMBER_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, lending_market, member, _padding) =
+            mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+
+        *version = self.version.to_le_bytes();
+        lending_market.copy_from_slice(self.lending_market.as_ref());
+        member.copy_from_slice(self.member.as_ref());
+    }
+
+    /// Unpacks a byte buffer into a [MarketMember](struct.MarketMember.html)
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, MARKET_MEMBER_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, lending_market, member, _padding) =
+            array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 64];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Market member version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            lending_market: Pubkey::new_from_array(*lending_market),
+            member: Pubkey::new_from_array(*member),
+        })
+    }
+}
@@ -1,13 +1,23 @@
 //! State types
 
+mod credit_delegation;
 mod last_update;
 mod lending_market;
+mod market_member;
 mod obligation;
+mod pending_emode_group;
+mod pending_reserve_config;
+mod rate_limiter;
 mod reserve;
 
+pub use credit_delegation::*;
 pub use last_update::*;
 pub use lending_market::*;
+pub use market_member::*;
 pub use obligation::*;
+pub use pending_emode_group::*;
+pub use pending_reserve_config::*;
+pub use rate_limiter::*;
 pub use reserve::*;
 
 use crate::math::{Decimal, WAD};
@@ -29,6 +39,17 @@ pub const UNINITIALIZED_VERSION: u8 = 0;
 // 2 (slots per second) * 60 * 60 * 24 * 365 = 63072000
 pub const SLOTS_PER_YEAR: u64 = 63072000;
 
+/// Pause flag blocking deposits of liquidity and obligation collateral
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;
+/// Pause flag blocking borrows
+pub const PAUSE_BORROWS: u8 = 1 << 1;
+/// Pause flag blocking liquidations
+pub const PAUSE_LIQUIDATIONS: u8 = 1 << 2;
+/// Pause flag blocking flash loans
+pub const PAUSE_FLASH_LOANS: u8 = 1 << 3;
+/// All pause flags
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSITS | PAUSE_BORROWS | PAUSE_LIQUIDATIONS | PAUSE_FLASH_LOANS;
+
 // Helpers
 fn pack_decimal(decimal: Decimal, dst: &mut [u8; 16]) {
     *dst = decimal
@@ -10,7 +10,7 @@ use solana_program::{
     msg,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
-    pubkey::{Pubkey, PUBKEY_BYTES},
+    pubkey::{Pubkey, PubkeyError, PUBKEY_BYTES},
 };
 use std::{
     cmp::Ordering,
@@ -20,6 +20,10 @@ use std::{
 /// Max number of collateral and liquidity reserve accounts combined for an obligation
 pub const MAX_OBLIGATION_RESERVES: usize = 10;
 
+/// Max number of collateral and liquidity reserve accounts combined for a reallocated obligation,
+/// bounded by the reserve accounts that fit in a `RefreshObligation` transaction
+pub const MAX_EXTENDED_OBLIGATION_RESERVES: usize = 30;
+
 /// Lending market obligation state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Obligation {
@@ -37,12 +41,19 @@ pub struct Obligation {
     pub borrows: Vec<ObligationLiquidity>,
     /// Market value of deposits
     pub deposited_value: Decimal,
-    /// Market value of borrows
+    /// Market value of borrows, weighted by the borrow weight of each borrow reserve
     pub borrowed_value: Decimal,
-    /// The maximum borrow value at the weighted average loan to value ratio
+    /// Borrowed value at the higher of the market and smoothed price of each borrow reserve
+    pub borrowed_value_upper_bound: Decimal,
+    /// The maximum borrow value at the weighted average loan to value ratio, with deposits
+    /// valued at the lower of the market and smoothed price of each deposit reserve
     pub allowed_borrow_value: Decimal,
     /// The dangerous borrow value at the weighted average liquidation threshold
     pub unhealthy_borrow_value: Decimal,
+    /// True if the obligation is borrowing from an isolated reserve
+    pub borrowing_isolated_asset: bool,
+    /// Reserve slots beyond MAX_OBLIGATION_RESERVES the account was reallocated to hold
+    pub extra_reserves: u8,
 }
 
 impl Obligation {
@@ -63,6 +74,16 @@ impl Obligation {
         self.borrows = params.borrows;
     }
 
+    /// Max number of deposits and borrows combined the obligation account can hold
+    pub fn max_reserves(&self) -> usize {
+        MAX_OBLIGATION_RESERVES + self.extra_reserves as usize
+    }
+
+    /// Packed length of an obligation account holding extra reserve slots
+    pub fn packed_len(extra_reserves: u8) -> usize {
+        OBLIGATION_LEN + extra_reserves as usize * OBLIGATION_LIQUIDITY_LEN
+    }
+
     /// Calculate the current ratio of borrowed value to deposited value
     pub fn loan_to_value(&self) -> Result<Decimal, ProgramError> {
         self.borrowed_value.try_div(self.deposited_value)
@@ -93,7 +114,7 @@ impl Obligation {
     /// Calculate the maximum collateral value that can be withdrawn
     pub fn max_withdraw_value(&self) -> Result<Decimal, ProgramError> {
         let required_deposit_value = self
-            .borrowed_value
+            .borrowed_value_upper_bound
             .try_mul(self.deposited_value)?
             .try_div(self.allowed_borrow_value)?;
         if required_deposit_value >= self.deposited_value {
@@ -104,19 +125,41 @@ impl Obligation {
 
     /// Calculate the maximum liquidity value that can be borrowed
     pub fn remaining_borrow_value(&self) -> Result<Decimal, ProgramError> {
-        self.allowed_borrow_value.try_sub(self.borrowed_value)
+        Ok(self
+            .allowed_borrow_value
+            .try_sub(self.borrowed_value_upper_bound)
+            .unwrap_or_else(|_| Decimal::zero()))
     }
 
-    /// Calculate the maximum liquidation amount for a given liquidity
+    /// Percentage of the borrowed value that can be repaid in one liquidation. Obligations whose
+    /// borrowed value is dust, or exceeds the unhealthy borrow value by at least
+    /// `FULL_LIQUIDATION_THRESHOLD` percent, can be liquidated in full.
+    pub fn liquidation_close_factor(&self) -> Result<Rate, ProgramError> {
+        let full_liquidation_value = self
+            .unhealthy_borrow_value
+            .try_mul(Rate::from_percent(100 + FULL_LIQUIDATION_THRESHOLD))?;
+        if self.borrowed_value < Decimal::from(LIQUIDATION_DUST_VALUE)
+            || self.borrowed_value >= full_liquidation_value
+        {
+            Ok(Rate::one())
+        } else {
+            Ok(Rate::from_percent(LIQUIDATION_CLOSE_FACTOR))
+        }
+    }
+
+    /// Calculate the maximum liquidation amount for a given liquidity, where `borrow_weight` is
+    /// the borrow weight of the liquidity's reserve
     pub fn max_liquidation_amount(
         &self,
         liquidity: &ObligationLiquidity,
+        borrow_weight: Decimal,
     ) -> Result<Decimal, ProgramError> {
+        let weighted_market_value = liquidity.market_value.try_mul(borrow_weight)?;
         let max_liquidation_value = self
             .borrowed_value
-            .try_mul(Rate::from_percent(LIQUIDATION_CLOSE_FACTOR))?
-            .min(liquidity.market_value);
-        let max_liquidation_pct = max_liquidation_value.try_div(liquidity.market_value)?;
+            .try_mul(self.liquidation_close_factor()?)?
+            .min(weighted_market_value);
+        let max_liquidation_pct = max_liquidation_value.try_div(weighted_market_value)?;
         liquidity.borrowed_amount_wads.try_mul(max_liquidation_pct)
     }
 
@@ -143,10 +186,10 @@ impl Obligation {
         if let Some(collateral_index) = self._find_collateral_index_in_deposits(deposit_reserve) {
             return Ok(&mut self.deposits[collateral_index]);
         }
-        if self.deposits.len() + self.borrows.len() >= MAX_OBLIGATION_RESERVES {
+        if self.deposits.len() + self.borrows.len() >= self.max_reserves() {
             msg!(
                 "Obligation cannot have more than {} deposits and borrows combined",
-                MAX_OBLIGATION_RESERVES
+                self.max_reserves()
             );
             return Err(LendingError::ObligationReserveLimit.into());
         }
@@ -185,10 +228,10 @@ impl Obligation {
         if let Some(liquidity_index) = self._find_liquidity_index_in_borrows(borrow_reserve) {
             return Ok(&mut self.borrows[liquidity_index]);
         }
-        if self.deposits.len() + self.borrows.len() >= MAX_OBLIGATION_RESERVES {
+        if self.deposits.len() + self.borrows.len() >= self.max_reserves() {
             msg!(
                 "Obligation cannot have more than {} deposits and borrows combined",
-                MAX_OBLIGATION_RESERVES
+                self.max_reserves()
             );
             return Err(LendingError::ObligationReserveLimit.into());
         }
@@ -218,6 +261,16 @@ pub struct InitObligationParams {
     pub borrows: Vec<ObligationLiquidity>,
 }
 
+/// Address of the obligation clients create for an owner in a lending market, seeded with the
+/// first 32 characters of the lending market address
+pub fn obligation_address_with_seed(
+    owner: &Pubkey,
+    lending_market: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_with_seed(owner, &lending_market.to_string()[..32], program_id)
+}
+
 impl Sealed for Obligation {}
 impl IsInitialized for Obligation {
     fn is_initialized(&self) -> bool {
@@ -276,6 +329,12 @@ pub struct ObligationLiquidity {
     pub borrowed_amount_wads: Decimal,
     /// Liquidity market value in quote currency
     pub market_value: Decimal,
+    /// Slot a fixed rate borrow matures at, 0 for variable rate borrows
+    pub maturity_slot: Slot,
+    /// Interest accrued each slot by a fixed rate borrow
+    pub fixed_interest_per_slot_wads: Decimal,
+    /// Last slot fixed rate interest was accrued
+    pub last_accrual_slot: Slot,
 }
 
 impl ObligationLiquidity {
@@ -286,21 +345,106 @@ impl ObligationLiquidity {
             cumulative_borrow_rate_wads,
             borrowed_amount_wads: Decimal::zero(),
             market_value: Decimal::zero(),
+            maturity_slot: 0,
+            fixed_interest_per_slot_wads: Decimal::zero(),
+            last_accrual_slot: 0,
         }
     }
 
+    /// Check if the liquidity is borrowed at a fixed rate
+    pub fn is_fixed_rate(&self) -> bool {
+        self.maturity_slot != 0
+    }
+
     /// Decrease borrowed liquidity
     pub fn repay(&mut self, settle_amount: Decimal) -> ProgramResult {
+        let settle_interest_per_slot = self.fixed_interest_per_slot_to_settle(settle_amount)?;
+        self.fixed_interest_per_slot_wads = self
+            .fixed_interest_per_slot_wads
+            .try_sub(settle_interest_per_slot)?;
         self.borrowed_amount_wads = self.borrowed_amount_wads.try_sub(settle_amount)?;
         Ok(())
     }
 
     /// Increase borrowed liquidity
     pub fn borrow(&mut self, borrow_amount: Decimal) -> ProgramResult {
+        if self.is_fixed_rate() {
+            msg!("Liquidity borrowed at a fixed rate cannot be increased");
+            return Err(LendingError::FixedRateBorrowConflict.into());
+        }
         self.borrowed_amount_wads = self.borrowed_amount_wads.try_add(borrow_amount)?;
         Ok(())
     }
 
+    /// Borrow liquidity at a fixed rate, accruing `interest_per_slot` from the current slot
+    pub fn borrow_fixed(
+        &mut self,
+        borrow_amount: Decimal,
+        interest_per_slot: Decimal,
+        current_slot: Slot,
+        maturity_slot: Slot,
+    ) -> ProgramResult {
+        if self.borrowed_amount_wads != Decimal::zero() {
+            msg!("Fixed rate borrows cannot be combined with other borrows from the same reserve");
+            return Err(LendingError::FixedRateBorrowConflict.into());
+        }
+        self.borrowed_amount_wads = borrow_amount;
+        self.fixed_interest_per_slot_wads = interest_per_slot;
+        self.last_accrual_slot = current_slot;
+        self.maturity_slot = maturity_slot;
+        Ok(())
+    }
+
+    /// Part of the fixed rate interest accrued each slot that is settled with `settle_amount`
+    pub fn fixed_interest_per_slot_to_settle(
+        &self,
+        settle_amount: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        if settle_amount >= self.borrowed_amount_wads {
+            return Ok(self.fixed_interest_per_slot_wads);
+        }
+        self.fixed_interest_per_slot_wads
+            .try_mul(settle_amount)?
+            .try_div(self.borrowed_amount_wads)
+    }
+
+    /// Accrue fixed rate interest up to the current slot, or up to the maturity slot once the
+    /// borrow matures
+    pub fn accrue_fixed_interest(&mut self, current_slot: Slot) -> ProgramResult {
+        let slots_elapsed = current_slot
+            .min(self.maturity_slot)
+            .checked_sub(self.last_accrual_slot.min(self.maturity_slot))
+            .ok_or(LendingError::MathOverflow)?;
+        self.borrowed_amount_wads = self
+            .borrowed_amount_wads
+            .try_add(self.fixed_interest_per_slot_wads.try_mul(slots_elapsed)?)?;
+        self.last_accrual_slot = current_slot;
+        Ok(())
+    }
+
+    /// Interest the reserve accrued on `interest_per_slot` between the maturity slot and the
+    /// last accrual slot, which matured borrows don't owe
+    pub fn interest_accrued_after_maturity(
+        &self,
+        interest_per_slot: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        interest_per_slot.try_mul(self.last_accrual_slot.saturating_sub(self.maturity_slot))
+    }
+
+    /// Convert a matured fixed rate borrow of `borrowed_amount_wads` into a variable rate borrow
+    /// following `cumulative_borrow_rate_wads`
+    pub fn settle_fixed_rate(
+        &mut self,
+        cumulative_borrow_rate_wads: Decimal,
+        borrowed_amount_wads: Decimal,
+    ) {
+        self.cumulative_borrow_rate_wads = cumulative_borrow_rate_wads;
+        self.borrowed_amount_wads = borrowed_amount_wads;
+        self.maturity_slot = 0;
+        self.fixed_interest_per_slot_wads = Decimal::zero();
+        self.last_accrual_slot = 0;
+    }
+
     /// Accrue interest
     pub fn accrue_interest(&mut self, cumulative_borrow_rate_wads: Decimal) -> ProgramResult {
         match cumulative_borrow_rate_wads.cmp(&self.cumulative_borrow_rate_wads) {
@@ -327,13 +471,38 @@ impl ObligationLiquidity {
 
 const OBLIGATION_COLLATERAL_LEN: usize = 88; // 32 + 8 + 16 + 32
 const OBLIGATION_LIQUIDITY_LEN: usize = 112; // 32 + 16 + 16 + 16 + 32
-const OBLIGATION_LEN: usize = 1300; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 64 + 1 + 1 + (88 * 1) + (112 * 9)
+const OBLIGATION_LEN: usize = 1300; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + 16 + 1 + 45 + 1 + 1 + (88 * 1) + (112 * 9)
                                     // @TODO: break this up by obligation / collateral / liquidity https://git.io/JOCca
+const OBLIGATION_HEADER_LEN: usize = 204; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + 16 + 1 + 45 + 1 + 1
+
+/// Obligations are packed as a fixed header followed by deposits and borrows. Accounts
+/// reallocated to hold extra reserves are longer by one liquidity slot per extra reserve, so the
+/// length of the account determines `extra_reserves`.
 impl Pack for Obligation {
     const LEN: usize = OBLIGATION_LEN;
 
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < OBLIGATION_LEN
+            || !(input.len() - OBLIGATION_LEN).is_multiple_of(OBLIGATION_LIQUIDITY_LEN)
+            || (input.len() - OBLIGATION_LEN) / OBLIGATION_LIQUIDITY_LEN
+                > MAX_EXTENDED_OBLIGATION_RESERVES - MAX_OBLIGATION_RESERVES
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unpack_from_slice(input)
+    }
+
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != Self::packed_len(src.extra_reserves) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        src.pack_into_slice(dst);
+        Ok(())
+    }
+
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let output = array_mut_ref![dst, 0, OBLIGATION_LEN];
+        let (output, data_flat) = dst.split_at_mut(OBLIGATION_HEADER_LEN);
+        let output = array_mut_ref![output, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -345,10 +514,13 @@ impl Pack for Obligation {
             borrowed_value,
             allowed_borrow_value,
             unhealthy_borrow_value,
+            borrowing_isolated_asset,
+            extra_reserves,
+            borrowed_value_upper_bound,
+            fixed_term_layout,
             _padding,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = mut_array_refs![
             output,
             1,
@@ -360,10 +532,13 @@ impl Pack for Obligation {
             16,
             16,
             16,
-            64,
             1,
             1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            16,
+            1,
+            45,
+            1,
+            1
         ];
 
         // obligation
@@ -376,8 +551,12 @@ impl Pack for Obligation {
         pack_decimal(self.borrowed_value, borrowed_value);
         pack_decimal(self.allowed_borrow_value, allowed_borrow_value);
         pack_decimal(self.unhealthy_borrow_value, unhealthy_borrow_value);
+        pack_bool(self.borrowing_isolated_asset, borrowing_isolated_asset);
+        *extra_reserves = self.extra_reserves.to_le_bytes();
+        pack_decimal(self.borrowed_value_upper_bound, borrowed_value_upper_bound);
         *deposits_len = u8::try_from(self.deposits.len()).unwrap().to_le_bytes();
         *borrows_len = u8::try_from(self.borrows.len()).unwrap().to_le_bytes();
+        pack_bool(true, fixed_term_layout);
 
         let mut offset = 0;
 
@@ -402,8 +581,10 @@ impl Pack for Obligation {
                 cumulative_borrow_rate_wads,
                 borrowed_amount_wads,
                 market_value,
-                _padding_borrow,
-            ) = mut_array_refs![borrows_flat, PUBKEY_BYTES, 16, 16, 16, 32];
+                maturity_slot,
+                fixed_interest_per_slot_wads,
+                last_accrual_slot,
+            ) = mut_array_refs![borrows_flat, PUBKEY_BYTES, 16, 16, 16, 8, 16, 8];
             borrow_reserve.copy_from_slice(liquidity.borrow_reserve.as_ref());
             pack_decimal(
                 liquidity.cumulative_borrow_rate_wads,
@@ -411,13 +592,24 @@ impl Pack for Obligation {
             );
             pack_decimal(liquidity.borrowed_amount_wads, borrowed_amount_wads);
             pack_decimal(liquidity.market_value, market_value);
+            *maturity_slot = liquidity.maturity_slot.to_le_bytes();
+            pack_decimal(
+                liquidity.fixed_interest_per_slot_wads,
+                fixed_interest_per_slot_wads,
+            );
+            *last_accrual_slot = liquidity.last_accrual_slot.to_le_bytes();
             offset += OBLIGATION_LIQUIDITY_LEN;
         }
     }
 
     /// Unpacks a byte buffer into an [ObligationInfo](struct.ObligationInfo.html).
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let input = array_ref![src, 0, OBLIGATION_LEN];
+        let extra_len = src
+            .len()
+            .checked_sub(OBLIGATION_LEN)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let (input, data_flat) = src.split_at(OBLIGATION_HEADER_LEN);
+        let input = array_ref![input, 0, OBLIGATION_HEADER_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
         let (
             version,
@@ -429,10 +621,13 @@ impl Pack for Obligation {
             borrowed_value,
             allowed_borrow_value,
             unhealthy_borrow_value,
+            borrowing_isolated_asset,
+            _extra_reserves,
+            borrowed_value_upper_bound,
+            fixed_term_layout,
             _padding,
             deposits_len,
             borrows_len,
-            data_flat,
         ) = array_refs![
             input,
             1,
@@ -444,10 +639,13 @@ impl Pack for Obligation {
             16,
             16,
             16,
-            64,
             1,
             1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            16,
+            1,
+            45,
+            1,
+            1
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -456,8 +654,20 @@ impl Pack for Obligation {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let extra_reserves = u8::try_from(extra_len / OBLIGATION_LIQUIDITY_LEN)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
         let deposits_len = u8::from_le_bytes(*deposits_len);
         let borrows_len = u8::from_le_bytes(*borrows_len);
+        // borrows packed before fixed term borrows may hold stale bytes where the fixed term
+        // fields are now stored
+        let fixed_term_layout = unpack_bool(fixed_term_layout)?;
+        if deposits_len as usize * OBLIGATION_COLLATERAL_LEN
+            + borrows_len as usize * OBLIGATION_LIQUIDITY_LEN
+            > data_flat.len()
+        {
+            msg!("Obligation deposits and borrows do not fit in the obligation account");
+            return Err(ProgramError::InvalidAccountData);
+        }
         let mut deposits = Vec::with_capacity(deposits_len as usize + 1);
         let mut borrows = Vec::with_capacity(borrows_len as usize + 1);
 
@@ -482,14 +692,24 @@ impl Pack for Obligation {
                 cumulative_borrow_rate_wads,
                 borrowed_amount_wads,
                 market_value,
-                _padding_borrow,
-            ) = array_refs![borrows_flat, PUBKEY_BYTES, 16, 16, 16, 32];
-            borrows.push(ObligationLiquidity {
+                maturity_slot,
+                fixed_interest_per_slot_wads,
+                last_accrual_slot,
+            ) = array_refs![borrows_flat, PUBKEY_BYTES, 16, 16, 16, 8, 16, 8];
+            let mut liquidity = ObligationLiquidity {
                 borrow_reserve: Pubkey::new(borrow_reserve),
                 cumulative_borrow_rate_wads: unpack_decimal(cumulative_borrow_rate_wads),
                 borrowed_amount_wads: unpack_decimal(borrowed_amount_wads),
                 market_value: unpack_decimal(market_value),
-            });
+                ..ObligationLiquidity::default()
+            };
+            if fixed_term_layout {
+                liquidity.maturity_slot = u64::from_le_bytes(*maturity_slot);
+                liquidity.fixed_interest_per_slot_wads =
+                    unpack_decimal(fixed_interest_per_slot_wads);
+                liquidity.last_accrual_slot = u64::from_le_bytes(*last_accrual_slot);
+            }
+            borrows.push(liquidity);
             offset += OBLIGATION_LIQUIDITY_LEN;
         }
 
@@ -505,8 +725,11 @@ impl Pack for Obligation {
             borrows,
             deposited_value: unpack_decimal(deposited_value),
             borrowed_value: unpack_decimal(borrowed_value),
+            borrowed_value_upper_bound: unpack_decimal(borrowed_value_upper_bound),
             allowed_borrow_value: unpack_decimal(allowed_borrow_value),
             unhealthy_borrow_value: unpack_decimal(unhealthy_borrow_value),
+            borrowing_isolated_asset: unpack_bool(borrowing_isolated_asset)?,
+            extra_reserves,
         })
     }
 }
@@ -519,6 +742,121 @@ mod test {
 
     const MAX_COMPOUNDED_INTEREST: u64 = 100; // 10,000%
 
+    #[test]
+    fn pack_extended_obligation() {
+        let mut obligation = Obligation {
+            version: PROGRAM_VERSION,
+            extra_reserves: 2,
+            ..Obligation::default()
+        };
+        obligation
+            .find_or_add_collateral_to_deposits(Pubkey::new_unique())
+            .unwrap();
+        for _ in 1..obligation.max_reserves() {
+            obligation
+                .find_or_add_liquidity_to_borrows(Pubkey::new_unique(), Decimal::one())
+                .unwrap();
+        }
+        assert_eq!(
+            obligation.find_or_add_collateral_to_deposits(Pubkey::new_unique()),
+            Err(LendingError::ObligationReserveLimit.into())
+        );
+
+        let mut data = vec![0; Obligation::packed_len(2)];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+        assert_eq!(Obligation::unpack(&data), Ok(obligation.clone()));
+
+        assert_eq!(
+            Obligation::pack(obligation, &mut [0; OBLIGATION_LEN]),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            Obligation::unpack(&data[..data.len() - 1]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn unpack_obligation_packed_before_fixed_term_borrows() {
+        let mut obligation = Obligation {
+            version: PROGRAM_VERSION,
+            ..Obligation::default()
+        };
+        obligation
+            .find_or_add_liquidity_to_borrows(Pubkey::new_unique(), Decimal::one())
+            .unwrap()
+            .borrow(Decimal::one())
+            .unwrap();
+        let mut data = [0; OBLIGATION_LEN];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+
+        // stale bytes where the fixed term fields are now stored are ignored in obligations packed
+        // before fixed term borrows
+        let fixed_term_layout_offset = OBLIGATION_HEADER_LEN - 48;
+        data[fixed_term_layout_offset] = 0;
+        data[OBLIGATION_HEADER_LEN + 80..OBLIGATION_HEADER_LEN + OBLIGATION_LIQUIDITY_LEN]
+            .fill(u8::MAX);
+        assert_eq!(Obligation::unpack(&data), Ok(obligation));
+    }
+
+    #[test]
+    fn fixed_rate_liquidity() {
+        let mut liquidity = ObligationLiquidity::new(Pubkey::new_unique(), Decimal::one());
+        liquidity
+            .borrow_fixed(Decimal::from(100u64), Decimal::one(), 10, 110)
+            .unwrap();
+        assert!(liquidity.is_fixed_rate());
+        assert_eq!(
+            liquidity.borrow(Decimal::one()),
+            Err(LendingError::FixedRateBorrowConflict.into())
+        );
+        assert_eq!(
+            liquidity.borrow_fixed(Decimal::one(), Decimal::one(), 10, 110),
+            Err(LendingError::FixedRateBorrowConflict.into())
+        );
+
+        liquidity.accrue_fixed_interest(30).unwrap();
+        assert_eq!(liquidity.borrowed_amount_wads, Decimal::from(120u64));
+
+        // interest stops accruing at maturity
+        let mut matured_liquidity = liquidity.clone();
+        matured_liquidity.accrue_fixed_interest(150).unwrap();
+        assert_eq!(
+            matured_liquidity.borrowed_amount_wads,
+            Decimal::from(200u64)
+        );
+        matured_liquidity.accrue_fixed_interest(160).unwrap();
+        assert_eq!(
+            matured_liquidity.borrowed_amount_wads,
+            Decimal::from(200u64)
+        );
+        assert_eq!(
+            matured_liquidity.interest_accrued_after_maturity(Decimal::one()),
+            Ok(Decimal::from(50u64))
+        );
+
+        let mut obligation = Obligation {
+            version: PROGRAM_VERSION,
+            borrows: vec![liquidity.clone()],
+            ..Obligation::default()
+        };
+        let mut data = [0; OBLIGATION_LEN];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+        assert_eq!(Obligation::unpack(&data), Ok(obligation.clone()));
+
+        obligation.repay(Decimal::from(60u64), 0).unwrap();
+        assert_eq!(
+            obligation.borrows[0].fixed_interest_per_slot_wads,
+            Decimal::from_percent(50)
+        );
+
+        liquidity.settle_fixed_rate(Decimal::from(2u64), Decimal::from(130u64));
+        assert!(!liquidity.is_fixed_rate());
+        assert_eq!(liquidity.borrowed_amount_wads, Decimal::from(130u64));
+        assert_eq!(liquidity.cumulative_borrow_rate_wads, Decimal::from(2u64));
+        assert_eq!(liquidity.fixed_interest_per_slot_wads, Decimal::zero());
+    }
+
     #[test]
     fn obligation_accrue_interest_failure() {
         assert_eq!(
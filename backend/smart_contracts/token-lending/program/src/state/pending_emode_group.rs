@@ -0,0 +1,128 @@
+use super::*;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    clock::Slot,
+    msg,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+/// Seed of the pending efficiency mode group address derived from a lending market and group
+pub const PENDING_EMODE_GROUP_SEED: &[u8] = b"pending_emode_group";
+
+/// Efficiency mode group parameters proposed by the lending market owner, which anyone can apply
+/// to the lending market once the activation slot is reached
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PendingEModeGroup {
+    /// Version of the struct
+    pub version: u8,
+    /// Lending market the group belongs to
+    pub lending_market: Pubkey,
+    /// Group number, in range [1, MAX_EMODE_GROUPS]
+    pub group: u8,
+    /// Proposed group parameters
+    pub config: EModeGroup,
+    /// First slot the proposal can be applied in
+    pub activation_slot: Slot,
+}
+
+/// Address of the pending parameters of an efficiency mode group and its bump seed
+pub fn pending_emode_group_address(
+    program_id: &Pubkey,
+    lending_market: &Pubkey,
+    group: u8,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[lending_market.as_ref(), &[group], PENDING_EMODE_GROUP_SEED],
+        program_id,
+    )
+}
+
+impl Sealed for PendingEModeGroup {}
+impl IsInitialized for PendingEModeGroup {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const PENDING_EMODE_GROUP_LEN: usize = 64; // 1 + 32 + 1 + 1 + 1 + 8 + 20
+impl Pack for PendingEModeGroup {
+    const LEN: usize = PENDING_EMODE_GROUP_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, PENDING_EMODE_GROUP_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            lending_market,
+            group,
+            config_loan_to_value_ratio,
+            config_liquidation_threshold,
+            activation_slot,
+            _padding,
+        ) = mut_array_refs![output, 1, PUBKEY_BYTES, 1, 1, 1, 8, 20];
+
+        *version = self.version.to_le_bytes();
+        lending_market.copy_from_slice(self.lending_market.as_ref());
+        *group = self.group.to_le_bytes();
+        *config_loan_to_value_ratio = self.config.loan_to_value_ratio.to_le_bytes();
+        *config_liquidation_threshold = self.config.liquidation_threshold.to_le_bytes();
+        *activation_slot = self.activation_slot.to_le_bytes();
+    }
+
+    /// Unpacks a byte buffer into a [PendingEModeGroup](struct.PendingEModeGroup.html)
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, PENDING_EMODE_GROUP_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            lending_market,
+            group,
+            config_loan_to_value_ratio,
+            config_liquidation_threshold,
+            activation_slot,
+            _padding,
+        ) = array_refs![input, 1, PUBKEY_BYTES, 1, 1, 1, 8, 20];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Pending efficiency mode group version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            lending_market: Pubkey::new_from_array(*lending_market),
+            group: u8::from_le_bytes(*group),
+            config: EModeGroup {
+                loan_to_value_ratio: u8::from_le_bytes(*config_loan_to_value_ratio),
+                liquidation_threshold: u8::from_le_bytes(*config_liquidation_threshold),
+            },
+            activation_slot: u64::from_le_bytes(*activation_slot),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_and_unpack_pending_emode_group() {
+        let pending_emode_group = PendingEModeGroup {
+            version: PROGRAM_VERSION,
+            lending_market: Pubkey::new_unique(),
+            group: 2,
+            config: EModeGroup {
+                loan_to_value_ratio: 90,
+                liquidation_threshold: 95,
+            },
+            activation_slot: 6,
+        };
+
+        let mut data = [0; PENDING_EMODE_GROUP_LEN];
+        PendingEModeGroup::pack(pending_emode_group.clone(), &mut data).unwrap();
+        assert_eq!(PendingEModeGroup::unpack(&data), Ok(pending_emode_group));
+    }
+}
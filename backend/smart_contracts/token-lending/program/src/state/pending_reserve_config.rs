@@ -0,0 +1,299 @@
+use super::*;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+    clock::Slot,
+    msg,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+
+/// Seed of the pending config address derived from a reserve
+pub const PENDING_RESERVE_CONFIG_SEED: &[u8] = b"pending_reserve_config";
+
+/// Number of slots a proposed reserve config waits before it can be applied, about one day
+pub const RESERVE_CONFIG_DELAY_SLOTS: Slot = 216_000;
+
+/// Reserve config and oracles proposed by the lending market owner, which anyone can apply to the
+/// reserve once the activation slot is reached
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PendingReserveConfig {
+    /// Version of the struct
+    pub version: u8,
+    /// Reserve the config is proposed for
+    pub reserve: Pubkey,
+    /// Proposed reserve config
+    pub config: ReserveConfig,
+    /// Proposed Pyth price account
+    pub pyth_oracle_pubkey: Pubkey,
+    /// Proposed Switchboard feed account
+    pub switchboard_oracle_pubkey: Pubkey,
+    /// First slot the proposal can be applied in
+    pub activation_slot: Slot,
+    /// Proposed token swap oracle, default if the reserve is not priced by a token swap
+    pub token_swap_oracle_pubkey: Pubkey,
+    /// Proposed stake pool oracle, default if the reserve is not priced by a stake pool
+    pub stake_pool_oracle_pubkey: Pubkey,
+}
+
+/// Address of the pending config of a reserve and its bump seed
+pub fn pending_reserve_config_address(program_id: &Pubkey, reserve: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[reserve.as_ref(), PENDING_RESERVE_CONFIG_SEED], program_id)
+}
+
+impl Sealed for PendingReserveConfig {}
+impl IsInitialized for PendingReserveConfig {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+
+const PENDING_RESERVE_CONFIG_LEN: usize = 317; // 1 + 32 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 8 + 8 + 32 + 1 + 1 + 8 + 1 + 1 + 32 + 32 + 8 + 1 + 8 + 32 + 32 + 55
+impl Pack for PendingReserveConfig {
+    const LEN: usize = PENDING_RESERVE_CONFIG_LEN;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, PENDING_RESERVE_CONFIG_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            reserve,
+            config_optimal_utilization_rate,
+            config_loan_to_value_ratio,
+            config_liquidation_bonus,
+            config_liquidation_threshold,
+            config_min_borrow_rate,
+            config_optimal_borrow_rate,
+            config_max_borrow_rate,
+            config_fees_borrow_fee_wad,
+            config_fees_flash_loan_fee_wad,
+            config_fees_host_fee_percentage,
+            config_deposit_limit,
+            config_borrow_limit,
+            config_fee_receiver,
+            config_isolated,
+            config_borrow_only,
+            config_added_borrow_weight_bps,
+            config_emode_group,
+            config_max_liquidation_bonus,
+            pyth_oracle_pubkey,
+            switchboard_oracle_pubkey,
+            activation_slot,
+            config_fixed_rate_premium,
+            config_max_fixed_term_slots,
+            token_swap_oracle_pubkey,
+            stake_pool_oracle_pubkey,
+            _padding,
+        ) = mut_array_refs![
+            output,
+            1,
+            PUBKEY_BYTES,
+            1,
+            1,
+            1,
+            1,
+            1,
+            1,
+            1,
+            8,
+            8,
+            1,
+            8,
+            8,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            1,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            1,
+            8,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            55
+        ];
+
+        *version = self.version.to_le_bytes();
+        reserve.copy_from_slice(self.reserve.as_ref());
+        *config_optimal_utilization_rate = self.config.optimal_utilization_rate.to_le_bytes();
+        *config_loan_to_value_ratio = self.config.loan_to_value_ratio.to_le_bytes();
+        *config_liquidation_bonus = self.config.liquidation_bonus.to_le_bytes();
+        *config_liquidation_threshold = self.config.liquidation_threshold.to_le_bytes();
+        *config_min_borrow_rate = self.config.min_borrow_rate.to_le_bytes();
+        *config_optimal_borrow_rate = self.config.optimal_borrow_rate.to_le_bytes();
+        *config_max_borrow_rate = self.config.max_borrow_rate.to_le_bytes();
+        *config_fees_borrow_fee_wad = self.config.fees.borrow_fee_wad.to_le_bytes();
+        *config_fees_flash_loan_fee_wad = self.config.fees.flash_loan_fee_wad.to_le_bytes();
+        *config_fees_host_fee_percentage = self.config.fees.host_fee_percentage.to_le_bytes();
+        *config_deposit_limit = self.config.deposit_limit.to_le_bytes();
+        *config_borrow_limit = self.config.borrow_limit.to_le_bytes();
+        config_fee_receiver.copy_from_slice(self.config.fee_receiver.as_ref());
+        pack_bool(self.config.isolated, config_isolated);
+        pack_bool(self.config.borrow_only, config_borrow_only);
+        *config_added_borrow_weight_bps = self.config.added_borrow_weight_bps.to_le_bytes();
+        *config_emode_group = self.config.emode_group.to_le_bytes();
+        *config_max_liquidation_bonus = self.config.max_liquidation_bonus.to_le_bytes();
+        pyth_oracle_pubkey.copy_from_slice(self.pyth_oracle_pubkey.as_ref());
+        switchboard_oracle_pubkey.copy_from_slice(self.switchboard_oracle_pubkey.as_ref());
+        *activation_slot = self.activation_slot.to_le_bytes();
+        *config_fixed_rate_premium = self.config.fixed_rate_premium.to_le_bytes();
+        *config_max_fixed_term_slots = self.config.max_fixed_term_slots.to_le_bytes();
+        token_swap_oracle_pubkey.copy_from_slice(self.token_swap_oracle_pubkey.as_ref());
+        stake_pool_oracle_pubkey.copy_from_slice(self.stake_pool_oracle_pubkey.as_ref());
+    }
+
+    /// Unpacks a byte buffer into a [PendingReserveConfig](struct.PendingReserveConfig.html)
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, PENDING_RESERVE_CONFIG_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            reserve,
+            config_optimal_utilization_rate,
+            config_loan_to_value_ratio,
+            config_liquidation_bonus,
+            config_liquidation_threshold,
+            config_min_borrow_rate,
+            config_optimal_borrow_rate,
+            config_max_borrow_rate,
+            config_fees_borrow_fee_wad,
+            config_fees_flash_loan_fee_wad,
+            config_fees_host_fee_percentage,
+            config_deposit_limit,
+            config_borrow_limit,
+            config_fee_receiver,
+            config_isolated,
+            config_borrow_only,
+            config_added_borrow_weight_bps,
+            config_emode_group,
+            config_max_liquidation_bonus,
+            pyth_oracle_pubkey,
+            switchboard_oracle_pubkey,
+            activation_slot,
+            config_fixed_rate_premium,
+            config_max_fixed_term_slots,
+            token_swap_oracle_pubkey,
+            stake_pool_oracle_pubkey,
+            _padding,
+        ) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            1,
+            1,
+            1,
+            1,
+            1,
+            1,
+            1,
+            8,
+            8,
+            1,
+            8,
+            8,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            1,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            8,
+            1,
+            8,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            55
+        ];
+
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("Pending reserve config version does not match lending program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            version,
+            reserve: Pubkey::new_from_array(*reserve),
+            config: ReserveConfig {
+                optimal_utilization_rate: u8::from_le_bytes(*config_optimal_utilization_rate),
+                loan_to_value_ratio: u8::from_le_bytes(*config_loan_to_value_ratio),
+                liquidation_bonus: u8::from_le_bytes(*config_liquidation_bonus),
+                liquidation_threshold: u8::from_le_bytes(*config_liquidation_threshold),
+                min_borrow_rate: u8::from_le_bytes(*config_min_borrow_rate),
+                optimal_borrow_rate: u8::from_le_bytes(*config_optimal_borrow_rate),
+                max_borrow_rate: u8::from_le_bytes(*config_max_borrow_rate),
+                fees: ReserveFees {
+                    borrow_fee_wad: u64::from_le_bytes(*config_fees_borrow_fee_wad),
+                    flash_loan_fee_wad: u64::from_le_bytes(*config_fees_flash_loan_fee_wad),
+                    host_fee_percentage: u8::from_le_bytes(*config_fees_host_fee_percentage),
+                },
+                deposit_limit: u64::from_le_bytes(*config_deposit_limit),
+                borrow_limit: u64::from_le_bytes(*config_borrow_limit),
+                fee_receiver: Pubkey::new_from_array(*config_fee_receiver),
+                isolated: unpack_bool(config_isolated)?,
+                borrow_only: unpack_bool(config_borrow_only)?,
+                added_borrow_weight_bps: u64::from_le_bytes(*config_added_borrow_weight_bps),
+                emode_group: u8::from_le_bytes(*config_emode_group),
+                max_liquidation_bonus: u8::from_le_bytes(*config_max_liquidation_bonus),
+                fixed_rate_premium: u8::from_le_bytes(*config_fixed_rate_premium),
+                max_fixed_term_slots: u64::from_le_bytes(*config_max_fixed_term_slots),
+            },
+            pyth_oracle_pubkey: Pubkey::new_from_array(*pyth_oracle_pubkey),
+            switchboard_oracle_pubkey: Pubkey::new_from_array(*switchboard_oracle_pubkey),
+            activation_slot: u64::from_le_bytes(*activation_slot),
+            token_swap_oracle_pubkey: Pubkey::new_from_array(*token_swap_oracle_pubkey),
+            stake_pool_oracle_pubkey: Pubkey::new_from_array(*stake_pool_oracle_pubkey),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_and_unpack_pending_reserve_config() {
+        let pending_config = PendingReserveConfig {
+            version: PROGRAM_VERSION,
+            reserve: Pubkey::new_unique(),
+            config: ReserveConfig {
+                optimal_utilization_rate: 80,
+                loan_to_value_ratio: 50,
+                liquidation_bonus: 5,
+                liquidation_threshold: 55,
+                min_borrow_rate: 0,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                fees: ReserveFees {
+                    borrow_fee_wad: 1,
+                    flash_loan_fee_wad: 2,
+                    host_fee_percentage: 20,
+                },
+                deposit_limit: 3,
+                borrow_limit: 4,
+                fee_receiver: Pubkey::new_unique(),
+                isolated: true,
+                borrow_only: false,
+                added_borrow_weight_bps: 5,
+                emode_group: 1,
+                max_liquidation_bonus: 10,
+                fixed_rate_premium: 2,
+                max_fixed_term_slots: 216_000,
+            },
+            pyth_oracle_pubkey: Pubkey::new_unique(),
+            switchboard_oracle_pubkey: Pubkey::new_unique(),
+            activation_slot: 6,
+            token_swap_oracle_pubkey: Pubkey::new_unique(),
+            stake_pool_oracle_pubkey: Pubkey::new_unique(),
+        };
+
+        let mut data = [0; PENDING_RESERVE_CONFIG_LEN];
+        PendingReserveConfig::pack(pending_config.clone(), &mut data).unwrap();
+        assert_eq!(PendingReserveConfig::unpack(&data), Ok(pending_config));
+    }
+}
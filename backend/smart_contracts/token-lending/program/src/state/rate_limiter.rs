@@ -0,0 +1,198 @@
+use super::*;
+use crate::{
+    error::LendingError,
+    math::{Decimal, TryAdd, TryDiv, TryMul, TrySub},
+};
+use arrayref::{array_refs, mut_array_refs};
+use solana_program::{clock::Slot, entrypoint::ProgramResult, msg, program_error::ProgramError};
+
+/// Packed size of a rate limiter
+pub const RATE_LIMITER_LEN: usize = 56; // 8 + 8 + 16 + 8 + 16
+
+/// Rate limiter configuration
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimiterConfig {
+    /// Length of the window in slots, 0 disables the rate limiter
+    pub window_duration: u64,
+    /// Max outflow value per window, in whole units of the quote currency
+    pub max_outflow: u64,
+}
+
+/// Sliding window rate limiter on liquidity outflows, measured in quote currency.
+///
+/// Outflows in the previous window are weighted by how much of it still overlaps the
+/// sliding window, so the limit cannot be doubled by splitting outflows around a window edge.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateLimiter {
+    /// Rate limiter configuration
+    pub config: RateLimiterConfig,
+    /// Outflow value in the previous window
+    pub prev_qty: Decimal,
+    /// Start slot of the current window
+    pub window_start: Slot,
+    /// Outflow value in the current window
+    pub cur_qty: Decimal,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter
+    pub fn new(config: RateLimiterConfig, current_slot: Slot) -> Self {
+        Self {
+            config,
+            prev_qty: Decimal::zero(),
+            window_start: current_slot,
+            cur_qty: Decimal::zero(),
+        }
+    }
+
+    /// Check if the rate limiter is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.config.window_duration > 0
+    }
+
+    fn update_window(&mut self, current_slot: Slot) -> ProgramResult {
+        let slots_elapsed = current_slot
+            .checked_sub(self.window_start)
+            .ok_or(LendingError::MathOverflow)?;
+        let window_duration = self.config.window_duration;
+
+        if slots_elapsed >= window_duration.saturating_mul(2) {
+            self.prev_qty = Decimal::zero();
+            self.cur_qty = Decimal::zero();
+            self.window_start = current_slot - slots_elapsed % window_duration;
+        } else if slots_elapsed >= window_duration {
+            self.prev_qty = self.cur_qty;
+            self.cur_qty = Decimal::zero();
+            self.window_start += window_duration;
+        }
+
+        Ok(())
+    }
+
+    /// Calculate the outflow value remaining in the current sliding window
+    pub fn remaining_outflow(&mut self, current_slot: Slot) -> Result<Decimal, ProgramError> {
+        self.update_window(current_slot)?;
+
+        let window_duration = self.config.window_duration;
+        let slots_into_window = current_slot - self.window_start;
+        let prev_weight = Decimal::from(window_duration - slots_into_window)
+            .try_div(Decimal::from(window_duration))?;
+        let outflow = self.prev_qty.try_mul(prev_weight)?.try_add(self.cur_qty)?;
+
+        let max_outflow = Decimal::from(self.config.max_outflow);
+        if outflow >= max_outflow {
+            Ok(Decimal::zero())
+        } else {
+            max_outflow.try_sub(outflow)
+        }
+    }
+
+    /// Record an outflow, failing if it would exceed the max outflow of the sliding window
+    pub fn update(&mut self, current_slot: Slot, outflow_value: Decimal) -> ProgramResult {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        if outflow_value > self.remaining_outflow(current_slot)? {
+            msg!("Outflow exceeds the rate limit for the current window");
+            return Err(LendingError::OutflowRateLimitExceeded.into());
+        }
+        self.cur_qty = self.cur_qty.try_add(outflow_value)?;
+
+        Ok(())
+    }
+
+    /// Pack a rate limiter into a byte buffer
+    pub fn pack_into_slice(&self, output: &mut [u8; RATE_LIMITER_LEN]) {
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (window_duration, max_outflow, prev_qty, window_start, cur_qty) =
+            mut_array_refs![output, 8, 8, 16, 8, 16];
+
+        *window_duration = self.config.window_duration.to_le_bytes();
+        *max_outflow = self.config.max_outflow.to_le_bytes();
+        pack_decimal(self.prev_qty, prev_qty);
+        *window_start = self.window_start.to_le_bytes();
+        pack_decimal(self.cur_qty, cur_qty);
+    }
+
+    /// Unpack a byte buffer into a rate limiter
+    pub fn unpack_from_slice(input: &[u8; RATE_LIMITER_LEN]) -> Self {
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (window_duration, max_outflow, prev_qty, window_start, cur_qty) =
+            array_refs![input, 8, 8, 16, 8, 16];
+
+        Self {
+            config: RateLimiterConfig {
+                window_duration: u64::from_le_bytes(*window_duration),
+                max_outflow: u64::from_le_bytes(*max_outflow),
+            },
+            prev_qty: unpack_decimal(prev_qty),
+            window_start: u64::from_le_bytes(*window_start),
+            cur_qty: unpack_decimal(cur_qty),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_disabled() {
+        let mut rate_limiter = RateLimiter::default();
+        assert!(!rate_limiter.is_enabled());
+        assert_eq!(rate_limiter.update(100, Decimal::from(u64::MAX)), Ok(()));
+    }
+
+    #[test]
+    fn rate_limiter_sliding_window() {
+        let mut rate_limiter = RateLimiter::new(
+            RateLimiterConfig {
+                window_duration: 10,
+                max_outflow: 100,
+            },
+            10,
+        );
+
+        assert_eq!(rate_limiter.update(10, Decimal::from(100u64)), Ok(()));
+        assert_eq!(
+            rate_limiter.update(19, Decimal::from(1u64)),
+            Err(LendingError::OutflowRateLimitExceeded.into())
+        );
+
+        // 30% of the previous window still overlaps the sliding window
+        assert_eq!(
+            rate_limiter.remaining_outflow(27).unwrap(),
+            Decimal::from(70u64)
+        );
+        assert_eq!(
+            rate_limiter.update(27, Decimal::from(71u64)),
+            Err(LendingError::OutflowRateLimitExceeded.into())
+        );
+        assert_eq!(rate_limiter.update(27, Decimal::from(70u64)), Ok(()));
+
+        // both windows have passed
+        assert_eq!(
+            rate_limiter.remaining_outflow(45).unwrap(),
+            Decimal::from(100u64)
+        );
+        assert_eq!(rate_limiter.window_start, 40);
+    }
+
+    #[test]
+    fn rate_limiter_pack_roundtrip() {
+        let rate_limiter = RateLimiter {
+            config: RateLimiterConfig {
+                window_duration: 20,
+                max_outflow: 1_000,
+            },
+            prev_qty: Decimal::from(5u64),
+            window_start: 42,
+            cur_qty: Decimal::from(7u64),
+        };
+
+        let mut packed = [0u8; RATE_LIMITER_LEN];
+        rate_limiter.pack_into_slice(&mut packed);
+        assert_eq!(RateLimiter::unpack_from_slice(&packed), rate_limiter);
+    }
+}
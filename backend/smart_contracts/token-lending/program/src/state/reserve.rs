@@ -23,6 +23,21 @@ pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
 /// Obligation borrow amount that is small enough to close out
 pub const LIQUIDATION_CLOSE_AMOUNT: u64 = 2;
 
+/// Percentage by which an obligation's borrowed value must exceed its unhealthy borrow value for
+/// the obligation to be liquidated in full
+pub const FULL_LIQUIDATION_THRESHOLD: u8 = 10;
+
+/// Borrowed value, in the lending market's quote currency, below which an obligation can be
+/// liquidated in full
+pub const LIQUIDATION_DUST_VALUE: u64 = 1;
+
+/// Basis points in one, used for the added borrow weight
+pub const BPS_SCALER: u64 = 10_000;
+
+/// Number of slots over which the smoothed market price follows the market price, about two
+/// minutes
+pub const PRICE_EMA_SLOTS: u64 = 300;
+
 /// Lending market reserve state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Reserve {
@@ -38,6 +53,16 @@ pub struct Reserve {
     pub collateral: ReserveCollateral,
     /// Reserve configuration values
     pub config: ReserveConfig,
+    /// Operations paused on this reserve, see `PAUSE_*` flags
+    pub pause_flags: u8,
+    /// Outflow rate limiter on borrows, redemptions and flash loans from this reserve
+    pub rate_limiter: RateLimiter,
+    /// Reserve is being retired, blocking deposits and borrows
+    pub deprecated: bool,
+    /// Slot the reserve was deprecated at
+    pub deprecation_slot: Slot,
+    /// Slots over which the loan to value ratio of deposits decays to zero once deprecated
+    pub ltv_decay_slots: u64,
 }
 
 impl Reserve {
@@ -56,6 +81,71 @@ impl Reserve {
         self.liquidity = params.liquidity;
         self.collateral = params.collateral;
         self.config = params.config;
+        self.pause_flags = 0;
+        self.rate_limiter = RateLimiter::default();
+        self.deprecated = false;
+        self.deprecation_slot = 0;
+        self.ltv_decay_slots = 0;
+    }
+
+    /// Check if an operation is paused on this reserve
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    /// Deprecate the reserve, decaying the loan to value ratio of deposits to zero over
+    /// `ltv_decay_slots`
+    pub fn deprecate(&mut self, slot: Slot, ltv_decay_slots: u64) {
+        self.deprecated = true;
+        self.deprecation_slot = slot;
+        self.ltv_decay_slots = ltv_decay_slots;
+    }
+
+    /// Part of the loan to value ratio of deposits remaining at a slot, decaying linearly from one
+    /// to zero once deprecated
+    pub fn ltv_decay_rate(&self, slot: Slot) -> Result<Rate, ProgramError> {
+        if !self.deprecated {
+            return Ok(Rate::one());
+        }
+        let slots_elapsed = slot.saturating_sub(self.deprecation_slot);
+        if slots_elapsed >= self.ltv_decay_slots {
+            return Ok(Rate::zero());
+        }
+        Rate::one()
+            .try_mul(self.ltv_decay_slots - slots_elapsed)?
+            .try_div(self.ltv_decay_slots)
+    }
+
+    /// Loan to value rate of deposits at a slot
+    pub fn loan_to_value_rate(&self, slot: Slot) -> Result<Rate, ProgramError> {
+        Rate::from_percent(self.config.loan_to_value_ratio).try_mul(self.ltv_decay_rate(slot)?)
+    }
+
+    /// Loan to value and liquidation threshold rates of deposits at a slot. Deposits of an
+    /// obligation in an efficiency mode group count at the higher of the reserve and group rates,
+    /// unless the reserve collateral is disabled for borrowing.
+    pub fn collateral_rates(
+        &self,
+        slot: Slot,
+        emode_group: Option<&EModeGroup>,
+    ) -> Result<(Rate, Rate), ProgramError> {
+        let loan_to_value_rate = self.loan_to_value_rate(slot)?;
+        let liquidation_threshold_rate = Rate::from_percent(self.config.liquidation_threshold);
+        match emode_group {
+            Some(emode_group)
+                if self.config.loan_to_value_ratio > 0 && !self.config.borrow_only =>
+            {
+                Ok((
+                    loan_to_value_rate.max(
+                        Rate::from_percent(emode_group.loan_to_value_ratio)
+                            .try_mul(self.ltv_decay_rate(slot)?)?,
+                    ),
+                    liquidation_threshold_rate
+                        .max(Rate::from_percent(emode_group.liquidation_threshold)),
+                ))
+            }
+            _ => Ok((loan_to_value_rate, liquidation_threshold_rate)),
+        }
     }
 
     /// Record deposited liquidity and return amount of collateral tokens to mint
@@ -84,7 +174,11 @@ impl Reserve {
 
     /// Calculate the current borrow rate
     pub fn current_borrow_rate(&self) -> Result<Rate, ProgramError> {
-        let utilization_rate = self.liquidity.utilization_rate()?;
+        self.borrow_rate(self.liquidity.utilization_rate()?)
+    }
+
+    /// Calculate the borrow rate at a utilization rate
+    fn borrow_rate(&self, utilization_rate: Rate) -> Result<Rate, ProgramError> {
         let optimal_utilization_rate = Rate::from_percent(self.config.optimal_utilization_rate);
         let low_utilization = utilization_rate < optimal_utilization_rate;
         if low_utilization || self.config.optimal_utilization_rate == 100 {
@@ -118,6 +212,16 @@ impl Reserve {
         }
     }
 
+    /// Calculate the rate a fixed term borrow of `borrow_amount` locks in until maturity, priced
+    /// at the utilization rate after the borrow
+    pub fn fixed_borrow_rate(&self, borrow_amount: Decimal) -> Result<Rate, ProgramError> {
+        self.borrow_rate(
+            self.liquidity
+                .utilization_rate_after_borrow(borrow_amount)?,
+        )?
+        .try_add(Rate::from_percent(self.config.fixed_rate_premium))
+    }
+
     /// Collateral exchange rate
     pub fn collateral_exchange_rate(&self) -> Result<CollateralExchangeRate, ProgramError> {
         let total_liquidity = self.liquidity.total_supply()?;
@@ -131,10 +235,37 @@ impl Reserve {
             let current_borrow_rate = self.current_borrow_rate()?;
             self.liquidity
                 .compound_interest(current_borrow_rate, slots_elapsed)?;
+            self.liquidity.accrue_fixed_interest(slots_elapsed)?;
         }
         Ok(())
     }
 
+    /// Move a matured fixed rate borrow into variable rate borrows. The borrow accrues interest at
+    /// the current borrow rate from the maturity slot instead of the fixed rate.
+    pub fn settle_fixed_borrow(
+        &mut self,
+        liquidity: &mut ObligationLiquidity,
+        current_slot: Slot,
+    ) -> ProgramResult {
+        liquidity.accrue_fixed_interest(current_slot)?;
+
+        let slot_interest_rate = self.current_borrow_rate()?.try_div(SLOTS_PER_YEAR)?;
+        let compounded_interest_rate = Rate::one()
+            .try_add(slot_interest_rate)?
+            .try_pow(current_slot.saturating_sub(liquidity.maturity_slot))?;
+        let borrowed_amount_wads = liquidity
+            .borrowed_amount_wads
+            .try_mul(compounded_interest_rate)?;
+
+        self.liquidity
+            .settle_fixed_borrow(liquidity, borrowed_amount_wads)?;
+        liquidity.settle_fixed_rate(
+            self.liquidity.cumulative_borrow_rate_wads,
+            borrowed_amount_wads,
+        );
+        Ok(())
+    }
+
     /// Borrow liquidity up to a maximum market value
     pub fn calculate_borrow(
         &self,
@@ -146,10 +277,12 @@ impl Reserve {
         let decimals = 10u64
             .checked_pow(self.liquidity.mint_decimals as u32)
             .ok_or(LendingError::MathOverflow)?;
+        let borrow_weight = self.config.borrow_weight()?;
         if amount_to_borrow == u64::MAX {
             let borrow_amount = max_borrow_value
+                .try_div(borrow_weight)?
                 .try_mul(decimals)?
-                .try_div(self.liquidity.market_price)?
+                .try_div(self.liquidity.market_price_upper_bound())?
                 .min(remaining_reserve_borrow)
                 .min(self.liquidity.available_amount.into());
             let (borrow_fee, host_fee) = self
@@ -177,8 +310,9 @@ impl Reserve {
 
             let borrow_amount = borrow_amount.try_add(borrow_fee.into())?;
             let borrow_value = borrow_amount
-                .try_mul(self.liquidity.market_price)?
-                .try_div(decimals)?;
+                .try_mul(self.liquidity.market_price_upper_bound())?
+                .try_div(decimals)?
+                .try_mul(borrow_weight)?;
             if borrow_value > max_borrow_value {
                 msg!("Borrow value cannot exceed maximum borrow value");
                 return Err(LendingError::BorrowTooLarge.into());
@@ -212,15 +346,43 @@ impl Reserve {
         })
     }
 
+    /// Liquidation bonus paid when withdrawing this reserve's collateral from an unhealthy
+    /// obligation. Starts at the liquidation bonus and grows by one percent for every percent the
+    /// borrowed value exceeds the unhealthy borrow value, up to the max liquidation bonus.
+    pub fn liquidation_bonus(&self, obligation: &Obligation) -> Result<Rate, ProgramError> {
+        let min_bonus = Decimal::from_percent(self.config.liquidation_bonus);
+        let max_bonus = Decimal::from_percent(
+            self.config
+                .max_liquidation_bonus
+                .max(self.config.liquidation_bonus),
+        );
+        if obligation.borrowed_value <= obligation.unhealthy_borrow_value {
+            return Rate::try_from(min_bonus);
+        }
+        if obligation.unhealthy_borrow_value == Decimal::zero() {
+            return Rate::try_from(max_bonus);
+        }
+
+        let unhealthy_pct = obligation
+            .borrowed_value
+            .try_sub(obligation.unhealthy_borrow_value)?
+            .try_div(obligation.unhealthy_borrow_value)?;
+        Rate::try_from(min_bonus.try_add(unhealthy_pct)?.min(max_bonus))
+    }
+
     /// Liquidate some or all of an unhealthy obligation
+    ///
+    /// `borrow_weight` is the borrow weight of the repay reserve, used to bound the liquidation by
+    /// the close factor of the obligation's weighted borrowed value.
     pub fn calculate_liquidation(
         &self,
         amount_to_liquidate: u64,
         obligation: &Obligation,
         liquidity: &ObligationLiquidity,
         collateral: &ObligationCollateral,
+        borrow_weight: Decimal,
     ) -> Result<CalculateLiquidationResult, ProgramError> {
-        let bonus_rate = Rate::from_percent(self.config.liquidation_bonus).try_add(Rate::one())?;
+        let bonus_rate = self.liquidation_bonus(obligation)?.try_add(Rate::one())?;
 
         let max_amount = if amount_to_liquidate == u64::MAX {
             liquidity.borrowed_amount_wads
@@ -259,7 +421,7 @@ impl Reserve {
         } else {
             // calculate settle_amount and withdraw_amount, repay_amount is settle_amount rounded
             let liquidation_amount = obligation
-                .max_liquidation_amount(liquidity)?
+                .max_liquidation_amount(liquidity, borrow_weight)?
                 .min(max_amount);
             let liquidation_pct = liquidation_amount.try_div(liquidity.borrowed_amount_wads)?;
             let liquidation_value = liquidity
@@ -367,6 +529,19 @@ pub struct ReserveLiquidity {
     pub cumulative_borrow_rate_wads: Decimal,
     /// Reserve liquidity market price in quote currency
     pub market_price: Decimal,
+    /// Token swap pool of which the reserve liquidity is the pool token, used to price the
+    /// liquidity from the pool instead of the pyth and switchboard oracles when set
+    pub token_swap_oracle_pubkey: Pubkey,
+    /// Stake pool of which the reserve liquidity is the pool token, used to price the liquidity
+    /// from the pool exchange rate and the SOL price of the pyth and switchboard oracles when set
+    pub stake_pool_oracle_pubkey: Pubkey,
+    /// Exponential moving average of the market price, see `PRICE_EMA_SLOTS`
+    pub smoothed_market_price: Decimal,
+    /// Reserve liquidity borrowed at a fixed rate, plus interest. Not included in
+    /// `borrowed_amount_wads`, which only holds variable rate borrows.
+    pub fixed_borrowed_amount_wads: Decimal,
+    /// Interest accrued each slot by fixed rate borrows
+    pub fixed_interest_per_slot_wads: Decimal,
 }
 
 impl ReserveLiquidity {
@@ -382,12 +557,82 @@ impl ReserveLiquidity {
             borrowed_amount_wads: Decimal::zero(),
             cumulative_borrow_rate_wads: Decimal::one(),
             market_price: params.market_price,
+            token_swap_oracle_pubkey: Pubkey::default(),
+            stake_pool_oracle_pubkey: Pubkey::default(),
+            smoothed_market_price: params.market_price,
+            fixed_borrowed_amount_wads: Decimal::zero(),
+            fixed_interest_per_slot_wads: Decimal::zero(),
         }
     }
 
     /// Calculate the total reserve supply including active loans
     pub fn total_supply(&self) -> Result<Decimal, ProgramError> {
-        Decimal::from(self.available_amount).try_add(self.borrowed_amount_wads)
+        Decimal::from(self.available_amount).try_add(self.total_borrowed()?)
+    }
+
+    /// Calculate the total borrows at variable and fixed rates
+    pub fn total_borrowed(&self) -> Result<Decimal, ProgramError> {
+        self.borrowed_amount_wads
+            .try_add(self.fixed_borrowed_amount_wads)
+    }
+
+    /// Calculate the market value of a liquidity amount in the quote currency
+    pub fn market_value(&self, liquidity_amount: Decimal) -> Result<Decimal, ProgramError> {
+        self.value_at_price(liquidity_amount, self.market_price)
+    }
+
+    /// Calculate the market value of a liquidity amount at the lower bound of the market price
+    pub fn market_value_lower_bound(
+        &self,
+        liquidity_amount: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        self.value_at_price(liquidity_amount, self.market_price_lower_bound())
+    }
+
+    /// Calculate the market value of a liquidity amount at the upper bound of the market price
+    pub fn market_value_upper_bound(
+        &self,
+        liquidity_amount: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        self.value_at_price(liquidity_amount, self.market_price_upper_bound())
+    }
+
+    fn value_at_price(
+        &self,
+        liquidity_amount: Decimal,
+        price: Decimal,
+    ) -> Result<Decimal, ProgramError> {
+        let decimals = 10u64
+            .checked_pow(self.mint_decimals as u32)
+            .ok_or(LendingError::MathOverflow)?;
+        liquidity_amount.try_mul(price)?.try_div(decimals)
+    }
+
+    /// Lower of the market price and the smoothed market price, used to value collateral
+    pub fn market_price_lower_bound(&self) -> Decimal {
+        self.market_price.min(self.smoothed_market_price)
+    }
+
+    /// Higher of the market price and the smoothed market price, used to value borrows
+    pub fn market_price_upper_bound(&self) -> Decimal {
+        self.market_price.max(self.smoothed_market_price)
+    }
+
+    /// Move the smoothed market price towards the market price, as if the market price had been
+    /// sampled in each of the elapsed slots
+    pub fn update_smoothed_market_price(&mut self, slots_elapsed: u64) -> ProgramResult {
+        if self.smoothed_market_price == Decimal::zero() {
+            self.smoothed_market_price = self.market_price;
+            return Ok(());
+        }
+        let retained_rate = Rate::one()
+            .try_sub(Rate::one().try_div(PRICE_EMA_SLOTS)?)?
+            .try_pow(slots_elapsed)?;
+        self.smoothed_market_price = self.smoothed_market_price.try_mul(retained_rate)?.try_add(
+            self.market_price
+                .try_mul(Rate::one().try_sub(retained_rate)?)?,
+        )?;
+        Ok(())
     }
 
     /// Add liquidity to available amount
@@ -441,13 +686,151 @@ impl ReserveLiquidity {
         Ok(())
     }
 
+    /// Write off borrowed liquidity that will never be repaid
+    pub fn forgive_debt(&mut self, settle_amount: Decimal) -> ProgramResult {
+        let safe_settle_amount = settle_amount.min(self.borrowed_amount_wads);
+        self.borrowed_amount_wads = self.borrowed_amount_wads.try_sub(safe_settle_amount)?;
+
+        Ok(())
+    }
+
+    /// Subtract borrow amount from available liquidity and add to fixed rate borrows accruing
+    /// `interest_per_slot`
+    pub fn borrow_fixed(
+        &mut self,
+        borrow_decimal: Decimal,
+        interest_per_slot: Decimal,
+    ) -> ProgramResult {
+        let borrow_amount = borrow_decimal.try_floor_u64()?;
+        if borrow_amount > self.available_amount {
+            msg!("Borrow amount cannot exceed available amount");
+            return Err(LendingError::InsufficientLiquidity.into());
+        }
+
+        self.available_amount = self
+            .available_amount
+            .checked_sub(borrow_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        self.fixed_borrowed_amount_wads =
+            self.fixed_borrowed_amount_wads.try_add(borrow_decimal)?;
+        self.fixed_interest_per_slot_wads = self
+            .fixed_interest_per_slot_wads
+            .try_add(interest_per_slot)?;
+
+        Ok(())
+    }
+
+    /// Add repay amount to available liquidity and subtract settle amount and the interest it
+    /// accrues each slot from fixed rate borrows
+    pub fn repay_fixed(
+        &mut self,
+        repay_amount: u64,
+        settle_amount: Decimal,
+        settle_interest_per_slot: Decimal,
+    ) -> ProgramResult {
+        self.available_amount = self
+            .available_amount
+            .checked_add(repay_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        self.forgive_fixed_debt(settle_amount, settle_interest_per_slot)
+    }
+
+    /// Write off fixed rate borrowed liquidity that will never be repaid
+    pub fn forgive_fixed_debt(
+        &mut self,
+        settle_amount: Decimal,
+        settle_interest_per_slot: Decimal,
+    ) -> ProgramResult {
+        let safe_settle_amount = settle_amount.min(self.fixed_borrowed_amount_wads);
+        self.fixed_borrowed_amount_wads = self
+            .fixed_borrowed_amount_wads
+            .try_sub(safe_settle_amount)?;
+        let safe_settle_interest_per_slot =
+            settle_interest_per_slot.min(self.fixed_interest_per_slot_wads);
+        self.fixed_interest_per_slot_wads = self
+            .fixed_interest_per_slot_wads
+            .try_sub(safe_settle_interest_per_slot)?;
+
+        Ok(())
+    }
+
+    /// Repay liquidity borrowed by an obligation at either a variable or fixed rate
+    pub fn repay_obligation_liquidity(
+        &mut self,
+        liquidity: &ObligationLiquidity,
+        repay_amount: u64,
+        settle_amount: Decimal,
+    ) -> ProgramResult {
+        if liquidity.is_fixed_rate() {
+            let settle_interest_per_slot =
+                liquidity.fixed_interest_per_slot_to_settle(settle_amount)?;
+            let settle_amount = settle_amount
+                .try_add(liquidity.interest_accrued_after_maturity(settle_interest_per_slot)?)?;
+            self.repay_fixed(repay_amount, settle_amount, settle_interest_per_slot)
+        } else {
+            self.repay(repay_amount, settle_amount)
+        }
+    }
+
+    /// Write off liquidity borrowed by an obligation at either a variable or fixed rate
+    pub fn forgive_obligation_debt(
+        &mut self,
+        liquidity: &ObligationLiquidity,
+        settle_amount: Decimal,
+    ) -> ProgramResult {
+        if liquidity.is_fixed_rate() {
+            let settle_interest_per_slot =
+                liquidity.fixed_interest_per_slot_to_settle(settle_amount)?;
+            let settle_amount = settle_amount
+                .try_add(liquidity.interest_accrued_after_maturity(settle_interest_per_slot)?)?;
+            self.forgive_fixed_debt(settle_amount, settle_interest_per_slot)
+        } else {
+            self.forgive_debt(settle_amount)
+        }
+    }
+
+    /// Move a matured fixed rate borrow into variable rate borrows as `borrowed_amount_wads`
+    pub fn settle_fixed_borrow(
+        &mut self,
+        liquidity: &ObligationLiquidity,
+        borrowed_amount_wads: Decimal,
+    ) -> ProgramResult {
+        let interest_per_slot = liquidity.fixed_interest_per_slot_wads;
+        let settle_amount = liquidity
+            .borrowed_amount_wads
+            .try_add(liquidity.interest_accrued_after_maturity(interest_per_slot)?)?;
+        self.forgive_fixed_debt(settle_amount, interest_per_slot)?;
+        self.borrowed_amount_wads = self.borrowed_amount_wads.try_add(borrowed_amount_wads)?;
+
+        Ok(())
+    }
+
     /// Calculate the liquidity utilization rate of the reserve
     pub fn utilization_rate(&self) -> Result<Rate, ProgramError> {
+        self.utilization_rate_after_borrow(Decimal::zero())
+    }
+
+    /// Calculate the liquidity utilization rate of the reserve once `borrow_amount` is borrowed
+    pub fn utilization_rate_after_borrow(
+        &self,
+        borrow_amount: Decimal,
+    ) -> Result<Rate, ProgramError> {
         let total_supply = self.total_supply()?;
         if total_supply == Decimal::zero() {
             return Ok(Rate::zero());
         }
-        self.borrowed_amount_wads.try_div(total_supply)?.try_into()
+        self.total_borrowed()?
+            .try_add(borrow_amount)?
+            .try_div(total_supply)?
+            .try_into()
+    }
+
+    /// Accrue fixed rate interest over elapsed slots
+    fn accrue_fixed_interest(&mut self, slots_elapsed: u64) -> ProgramResult {
+        self.fixed_borrowed_amount_wads = self
+            .fixed_borrowed_amount_wads
+            .try_add(self.fixed_interest_per_slot_wads.try_mul(slots_elapsed)?)?;
+        Ok(())
     }
 
     /// Compound current borrow rate over elapsed slots
@@ -615,6 +998,45 @@ pub struct ReserveConfig {
     pub borrow_limit: u64,
     /// Reserve liquidity fee receiver address
     pub fee_receiver: Pubkey,
+    /// Reserve liquidity can only be borrowed alone, never alongside borrows from other reserves
+    pub isolated: bool,
+    /// Reserve collateral cannot be deposited into obligations, only liquidity can be borrowed
+    pub borrow_only: bool,
+    /// Added borrow weight in basis points. A borrow from this reserve counts as
+    /// `1 + added_borrow_weight_bps / 10_000` times its market value towards an obligation's
+    /// borrowed value.
+    pub added_borrow_weight_bps: u64,
+    /// Efficiency mode group of the lending market this reserve belongs to, 0 for none. See
+    /// `LendingMarket::emode_groups`.
+    pub emode_group: u8,
+    /// Max bonus a liquidator gets when repaying part of an obligation that is far past its
+    /// liquidation threshold, as a percentage. The bonus is fixed at `liquidation_bonus` if lower.
+    pub max_liquidation_bonus: u8,
+    /// Premium added to the current borrow rate to price fixed term borrows, as a percentage
+    pub fixed_rate_premium: u8,
+    /// Max term of fixed term borrows in slots, 0 if fixed term borrows are disabled
+    pub max_fixed_term_slots: u64,
+}
+
+impl ReserveConfig {
+    /// Borrow weight applied to the market value of borrows from this reserve
+    pub fn borrow_weight(&self) -> Result<Decimal, ProgramError> {
+        Decimal::one().try_add(Decimal::from(self.added_borrow_weight_bps).try_div(BPS_SCALER)?)
+    }
+
+    /// Whether changing to `config` only reduces risk: a lower or equal loan to value ratio and
+    /// lower or equal deposit and borrow limits, with the rest of the config unchanged
+    pub fn is_risk_reducing_change(&self, config: &ReserveConfig) -> bool {
+        config.loan_to_value_ratio <= self.loan_to_value_ratio
+            && config.deposit_limit <= self.deposit_limit
+            && config.borrow_limit <= self.borrow_limit
+            && ReserveConfig {
+                loan_to_value_ratio: self.loan_to_value_ratio,
+                deposit_limit: self.deposit_limit,
+                borrow_limit: self.borrow_limit,
+                ..*config
+            } == *self
+    }
 }
 
 /// Additional fee information on a reserve
@@ -725,7 +1147,7 @@ impl IsInitialized for Reserve {
     }
 }
 
-const RESERVE_LEN: usize = 619; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 8 + 8 + 32 + 248
+const RESERVE_LEN: usize = 619; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 8 + 8 + 32 + 1 + 1 + 8 + 1 + 56 + 32 + 32 + 16 + 1 + 1 + 16 + 16 + 1 + 8 + 1 + 8 + 8 + 41
 impl Pack for Reserve {
     const LEN: usize = RESERVE_LEN;
 
@@ -763,6 +1185,23 @@ impl Pack for Reserve {
             config_deposit_limit,
             config_borrow_limit,
             config_fee_receiver,
+            config_isolated,
+            config_borrow_only,
+            config_added_borrow_weight_bps,
+            pause_flags,
+            rate_limiter,
+            liquidity_token_swap_oracle_pubkey,
+            liquidity_stake_pool_oracle_pubkey,
+            liquidity_smoothed_market_price,
+            config_emode_group,
+            config_max_liquidation_bonus,
+            liquidity_fixed_borrowed_amount_wads,
+            liquidity_fixed_interest_per_slot_wads,
+            config_fixed_rate_premium,
+            config_max_fixed_term_slots,
+            deprecated,
+            deprecation_slot,
+            ltv_decay_slots,
             _padding,
         ) = mut_array_refs![
             output,
@@ -795,7 +1234,24 @@ impl Pack for Reserve {
             8,
             8,
             PUBKEY_BYTES,
-            248
+            1,
+            1,
+            8,
+            1,
+            RATE_LIMITER_LEN,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            16,
+            1,
+            1,
+            16,
+            16,
+            1,
+            8,
+            1,
+            8,
+            8,
+            41
         ];
 
         // reserve
@@ -841,6 +1297,35 @@ impl Pack for Reserve {
         *config_deposit_limit = self.config.deposit_limit.to_le_bytes();
         *config_borrow_limit = self.config.borrow_limit.to_le_bytes();
         config_fee_receiver.copy_from_slice(self.config.fee_receiver.as_ref());
+        pack_bool(self.config.isolated, config_isolated);
+        pack_bool(self.config.borrow_only, config_borrow_only);
+        *config_added_borrow_weight_bps = self.config.added_borrow_weight_bps.to_le_bytes();
+
+        *pause_flags = self.pause_flags.to_le_bytes();
+        self.rate_limiter.pack_into_slice(rate_limiter);
+        liquidity_token_swap_oracle_pubkey
+            .copy_from_slice(self.liquidity.token_swap_oracle_pubkey.as_ref());
+        liquidity_stake_pool_oracle_pubkey
+            .copy_from_slice(self.liquidity.stake_pool_oracle_pubkey.as_ref());
+        pack_decimal(
+            self.liquidity.smoothed_market_price,
+            liquidity_smoothed_market_price,
+        );
+        *config_emode_group = self.config.emode_group.to_le_bytes();
+        *config_max_liquidation_bonus = self.config.max_liquidation_bonus.to_le_bytes();
+        pack_decimal(
+            self.liquidity.fixed_borrowed_amount_wads,
+            liquidity_fixed_borrowed_amount_wads,
+        );
+        pack_decimal(
+            self.liquidity.fixed_interest_per_slot_wads,
+            liquidity_fixed_interest_per_slot_wads,
+        );
+        *config_fixed_rate_premium = self.config.fixed_rate_premium.to_le_bytes();
+        *config_max_fixed_term_slots = self.config.max_fixed_term_slots.to_le_bytes();
+        pack_bool(self.deprecated, deprecated);
+        *deprecation_slot = self.deprecation_slot.to_le_bytes();
+        *ltv_decay_slots = self.ltv_decay_slots.to_le_bytes();
     }
 
     /// Unpacks a byte buffer into a [ReserveInfo](struct.ReserveInfo.html).
@@ -877,6 +1362,23 @@ impl Pack for Reserve {
             config_deposit_limit,
             config_borrow_limit,
             config_fee_receiver,
+            config_isolated,
+            config_borrow_only,
+            config_added_borrow_weight_bps,
+            pause_flags,
+            rate_limiter,
+            liquidity_token_swap_oracle_pubkey,
+            liquidity_stake_pool_oracle_pubkey,
+            liquidity_smoothed_market_price,
+            config_emode_group,
+            config_max_liquidation_bonus,
+            liquidity_fixed_borrowed_amount_wads,
+            liquidity_fixed_interest_per_slot_wads,
+            config_fixed_rate_premium,
+            config_max_fixed_term_slots,
+            deprecated,
+            deprecation_slot,
+            ltv_decay_slots,
             _padding,
         ) = array_refs![
             input,
@@ -909,7 +1411,24 @@ impl Pack for Reserve {
             8,
             8,
             PUBKEY_BYTES,
-            248
+            1,
+            1,
+            8,
+            1,
+            RATE_LIMITER_LEN,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            16,
+            1,
+            1,
+            16,
+            16,
+            1,
+            8,
+            1,
+            8,
+            8,
+            41
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -937,6 +1456,17 @@ impl Pack for Reserve {
                 borrowed_amount_wads: unpack_decimal(liquidity_borrowed_amount_wads),
                 cumulative_borrow_rate_wads: unpack_decimal(liquidity_cumulative_borrow_rate_wads),
                 market_price: unpack_decimal(liquidity_market_price),
+                token_swap_oracle_pubkey: Pubkey::new_from_array(
+                    *liquidity_token_swap_oracle_pubkey,
+                ),
+                stake_pool_oracle_pubkey: Pubkey::new_from_array(
+                    *liquidity_stake_pool_oracle_pubkey,
+                ),
+                smoothed_market_price: unpack_decimal(liquidity_smoothed_market_price),
+                fixed_borrowed_amount_wads: unpack_decimal(liquidity_fixed_borrowed_amount_wads),
+                fixed_interest_per_slot_wads: unpack_decimal(
+                    liquidity_fixed_interest_per_slot_wads,
+                ),
             },
             collateral: ReserveCollateral {
                 mint_pubkey: Pubkey::new_from_array(*collateral_mint_pubkey),
@@ -959,7 +1489,19 @@ impl Pack for Reserve {
                 deposit_limit: u64::from_le_bytes(*config_deposit_limit),
                 borrow_limit: u64::from_le_bytes(*config_borrow_limit),
                 fee_receiver: Pubkey::new_from_array(*config_fee_receiver),
+                isolated: unpack_bool(config_isolated)?,
+                borrow_only: unpack_bool(config_borrow_only)?,
+                added_borrow_weight_bps: u64::from_le_bytes(*config_added_borrow_weight_bps),
+                emode_group: u8::from_le_bytes(*config_emode_group),
+                max_liquidation_bonus: u8::from_le_bytes(*config_max_liquidation_bonus),
+                fixed_rate_premium: u8::from_le_bytes(*config_fixed_rate_premium),
+                max_fixed_term_slots: u64::from_le_bytes(*config_max_fixed_term_slots),
             },
+            pause_flags: u8::from_le_bytes(*pause_flags),
+            rate_limiter: RateLimiter::unpack_from_slice(rate_limiter),
+            deprecated: unpack_bool(deprecated)?,
+            deprecation_slot: u64::from_le_bytes(*deprecation_slot),
+            ltv_decay_slots: u64::from_le_bytes(*ltv_decay_slots),
         })
     }
 }
@@ -1251,6 +1793,203 @@ mod test {
         }
     }
 
+    #[test]
+    fn smoothed_market_price() {
+        let mut liquidity = ReserveLiquidity {
+            market_price: Decimal::from(100u64),
+            ..ReserveLiquidity::default()
+        };
+
+        // initialized to the market price
+        liquidity.update_smoothed_market_price(0).unwrap();
+        assert_eq!(liquidity.smoothed_market_price, Decimal::from(100u64));
+
+        // refreshing within the same slot does not move the smoothed price
+        liquidity.market_price = Decimal::from(50u64);
+        liquidity.update_smoothed_market_price(0).unwrap();
+        assert_eq!(liquidity.smoothed_market_price, Decimal::from(100u64));
+
+        // a one slot wick only moves the smoothed price by 1 / PRICE_EMA_SLOTS of the difference,
+        // up to rounding
+        liquidity.update_smoothed_market_price(1).unwrap();
+        let expected_price = Decimal::from(100u64)
+            .try_sub(Decimal::from(50u64).try_div(PRICE_EMA_SLOTS).unwrap())
+            .unwrap();
+        assert!(liquidity.smoothed_market_price >= expected_price);
+        assert!(
+            liquidity.smoothed_market_price
+                < expected_price
+                    .try_add(Decimal::from_scaled_val(100))
+                    .unwrap()
+        );
+        assert_eq!(liquidity.market_price_lower_bound(), Decimal::from(50u64));
+        assert_eq!(
+            liquidity.market_price_upper_bound(),
+            liquidity.smoothed_market_price
+        );
+
+        // a sustained price converges
+        liquidity
+            .update_smoothed_market_price(10 * PRICE_EMA_SLOTS)
+            .unwrap();
+        assert!(liquidity.smoothed_market_price > Decimal::from(50u64));
+        assert!(
+            liquidity.smoothed_market_price
+                < Decimal::from(50u64)
+                    .try_add(Decimal::from_percent(1))
+                    .unwrap()
+        );
+    }
+
+    #[test]
+    fn deprecated_loan_to_value_rate() {
+        let mut reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                loan_to_value_ratio: 50,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        assert_eq!(
+            reserve.loan_to_value_rate(1_000).unwrap(),
+            Rate::from_percent(50)
+        );
+
+        // the loan to value ratio decays linearly from the deprecation slot
+        reserve.deprecate(100, 10);
+        assert_eq!(
+            reserve.loan_to_value_rate(100).unwrap(),
+            Rate::from_percent(50)
+        );
+        assert_eq!(
+            reserve.loan_to_value_rate(104).unwrap(),
+            Rate::from_percent(30)
+        );
+        assert_eq!(reserve.loan_to_value_rate(110).unwrap(), Rate::zero());
+        assert_eq!(reserve.loan_to_value_rate(1_000).unwrap(), Rate::zero());
+
+        // without a decay the loan to value ratio drops to zero immediately
+        reserve.deprecate(100, 0);
+        assert_eq!(reserve.loan_to_value_rate(100).unwrap(), Rate::zero());
+
+        let mut packed = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut packed).unwrap();
+        assert_eq!(Reserve::unpack(&packed).unwrap(), reserve);
+    }
+
+    #[test]
+    fn fixed_rate_borrows() {
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 1_000,
+                cumulative_borrow_rate_wads: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                optimal_utilization_rate: 80,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                fixed_rate_premium: 2,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+
+        // fixed rate borrows count towards utilization but not towards variable rate borrows
+        reserve
+            .liquidity
+            .borrow_fixed(Decimal::from(500u64), Decimal::from(2u64))
+            .unwrap();
+        assert_eq!(
+            reserve.liquidity.utilization_rate().unwrap(),
+            Rate::from_percent(50)
+        );
+        let current_borrow_rate = reserve.current_borrow_rate().unwrap();
+        assert!(current_borrow_rate > Rate::zero());
+        assert_eq!(
+            reserve.fixed_borrow_rate(Decimal::zero()).unwrap(),
+            current_borrow_rate.try_add(Rate::from_percent(2)).unwrap()
+        );
+
+        // fixed rate borrows are priced at the utilization rate after the borrow
+        assert_eq!(
+            reserve.fixed_borrow_rate(Decimal::from(400u64)).unwrap(),
+            Rate::from_percent(19)
+        );
+
+        // fixed rate borrows accrue simple interest
+        reserve.accrue_interest(10).unwrap();
+        assert_eq!(
+            reserve.liquidity.fixed_borrowed_amount_wads,
+            Decimal::from(520u64)
+        );
+        assert_eq!(reserve.liquidity.borrowed_amount_wads, Decimal::zero());
+
+        // repaying half of a borrow settles half of the interest it accrues each slot
+        let liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(520u64),
+            maturity_slot: 100,
+            fixed_interest_per_slot_wads: Decimal::from(2u64),
+            ..ObligationLiquidity::default()
+        };
+        reserve
+            .liquidity
+            .repay_obligation_liquidity(&liquidity, 260, Decimal::from(260u64))
+            .unwrap();
+        assert_eq!(reserve.liquidity.available_amount, 760);
+        assert_eq!(
+            reserve.liquidity.fixed_borrowed_amount_wads,
+            Decimal::from(260u64)
+        );
+        assert_eq!(
+            reserve.liquidity.fixed_interest_per_slot_wads,
+            Decimal::one()
+        );
+
+        // settling a matured borrow moves it into variable rate borrows, writes off the interest
+        // the reserve accrued after maturity and accrues the current borrow rate instead
+        reserve.accrue_interest(5).unwrap();
+        assert_eq!(
+            reserve.liquidity.fixed_borrowed_amount_wads,
+            Decimal::from(265u64)
+        );
+        let mut liquidity = ObligationLiquidity {
+            borrowed_amount_wads: Decimal::from(260u64),
+            maturity_slot: 10,
+            fixed_interest_per_slot_wads: Decimal::one(),
+            last_accrual_slot: 15,
+            ..ObligationLiquidity::default()
+        };
+        let compounded_interest_rate = Rate::one()
+            .try_add(
+                reserve
+                    .current_borrow_rate()
+                    .unwrap()
+                    .try_div(SLOTS_PER_YEAR)
+                    .unwrap(),
+            )
+            .unwrap()
+            .try_pow(5)
+            .unwrap();
+        let borrowed_amount_wads = Decimal::from(260u64)
+            .try_mul(compounded_interest_rate)
+            .unwrap();
+        assert!(borrowed_amount_wads > Decimal::from(260u64));
+        reserve.settle_fixed_borrow(&mut liquidity, 15).unwrap();
+        assert!(!liquidity.is_fixed_rate());
+        assert_eq!(liquidity.borrowed_amount_wads, borrowed_amount_wads);
+        assert_eq!(
+            reserve.liquidity.fixed_borrowed_amount_wads,
+            Decimal::zero()
+        );
+        assert_eq!(
+            reserve.liquidity.fixed_interest_per_slot_wads,
+            Decimal::zero()
+        );
+        assert_eq!(reserve.liquidity.borrowed_amount_wads, borrowed_amount_wads);
+    }
+
     #[test]
     fn borrow_fee_calculation_min_host() {
         let fees = ReserveFees {
@@ -1339,4 +2078,146 @@ mod test {
         assert_eq!(total_fee, 10); // 1% of 1000
         assert_eq!(host_fee, 0); // 0 host fee
     }
+
+    #[test]
+    fn borrow_weight() {
+        let config = ReserveConfig {
+            added_borrow_weight_bps: 5_000,
+            ..ReserveConfig::default()
+        };
+        assert_eq!(
+            config.borrow_weight().unwrap(),
+            Decimal::from(3u64).try_div(2u64).unwrap()
+        );
+        assert_eq!(
+            ReserveConfig::default().borrow_weight().unwrap(),
+            Decimal::one()
+        );
+    }
+
+    #[test]
+    fn risk_reducing_config_change() {
+        let config = ReserveConfig {
+            loan_to_value_ratio: 50,
+            liquidation_threshold: 55,
+            deposit_limit: 100,
+            borrow_limit: 100,
+            ..ReserveConfig::default()
+        };
+        assert!(config.is_risk_reducing_change(&config));
+        assert!(config.is_risk_reducing_change(&ReserveConfig {
+            loan_to_value_ratio: 40,
+            deposit_limit: 50,
+            borrow_limit: 0,
+            ..config
+        }));
+
+        assert!(!config.is_risk_reducing_change(&ReserveConfig {
+            loan_to_value_ratio: 51,
+            ..config
+        }));
+        assert!(!config.is_risk_reducing_change(&ReserveConfig {
+            borrow_limit: 101,
+            ..config
+        }));
+        // a lower liquidation threshold would make existing obligations liquidatable
+        assert!(!config.is_risk_reducing_change(&ReserveConfig {
+            loan_to_value_ratio: 40,
+            liquidation_threshold: 45,
+            ..config
+        }));
+    }
+
+    #[test]
+    fn calculate_borrow_weighted() {
+        let reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                mint_decimals: 0,
+                available_amount: 1_000,
+                market_price: Decimal::one(),
+                ..ReserveLiquidity::default()
+            },
+            config: ReserveConfig {
+                added_borrow_weight_bps: 10_000, // 2x
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+
+        // a borrow of 60 consumes 120 of borrowing power
+        let err = reserve
+            .calculate_borrow(60, Decimal::from(100u64), Decimal::from(1_000u64))
+            .unwrap_err();
+        assert_eq!(err, LendingError::BorrowTooLarge.into());
+
+        let result = reserve
+            .calculate_borrow(50, Decimal::from(100u64), Decimal::from(1_000u64))
+            .unwrap();
+        assert_eq!(result.borrow_amount, Decimal::from(50u64));
+
+        let result = reserve
+            .calculate_borrow(u64::MAX, Decimal::from(100u64), Decimal::from(1_000u64))
+            .unwrap();
+        assert_eq!(result.borrow_amount, Decimal::from(50u64));
+    }
+
+    #[test]
+    fn liquidation_bonus_grows_with_unhealthiness() {
+        let reserve = Reserve {
+            config: ReserveConfig {
+                liquidation_bonus: 5,
+                max_liquidation_bonus: 20,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        let obligation = |borrowed_value: u64| Obligation {
+            borrowed_value: Decimal::from(borrowed_value),
+            unhealthy_borrow_value: Decimal::from(100u64),
+            ..Obligation::default()
+        };
+
+        assert_eq!(
+            reserve.liquidation_bonus(&obligation(100)).unwrap(),
+            Rate::from_percent(5)
+        );
+        assert_eq!(
+            reserve.liquidation_bonus(&obligation(108)).unwrap(),
+            Rate::from_percent(13)
+        );
+        assert_eq!(
+            reserve.liquidation_bonus(&obligation(150)).unwrap(),
+            Rate::from_percent(20)
+        );
+
+        // reserves without a max liquidation bonus keep a fixed bonus
+        let reserve = Reserve {
+            config: ReserveConfig {
+                liquidation_bonus: 5,
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+        assert_eq!(
+            reserve.liquidation_bonus(&obligation(150)).unwrap(),
+            Rate::from_percent(5)
+        );
+
+        // slightly unhealthy obligations are liquidated by the close factor, very unhealthy or
+        // dust obligations in full
+        assert_eq!(
+            obligation(105).liquidation_close_factor().unwrap(),
+            Rate::from_percent(LIQUIDATION_CLOSE_FACTOR)
+        );
+        assert_eq!(
+            obligation(110).liquidation_close_factor().unwrap(),
+            Rate::one()
+        );
+        let dust = Obligation {
+            borrowed_value: Decimal::from_percent(50),
+            unhealthy_borrow_value: Decimal::from_percent(40),
+            ..Obligation::default()
+        };
+        assert_eq!(dust.liquidation_close_factor().unwrap(), Rate::one());
+    }
 }
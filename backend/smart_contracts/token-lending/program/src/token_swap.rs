@@ -0,0 +1,134 @@
+//! Minimal interface to the spl-token-swap program, used for swaps through CPI
+
+use crate::error::LendingError;
+use arrayref::{array_ref, array_refs};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::{Pubkey, PUBKEY_BYTES},
+};
+use std::mem::size_of;
+
+solana_program::declare_id!("SwapsVeCiPHMUAtzQWZw7RjsKjgCjhwU55QGu4U1Szw");
+
+/// Instruction tag of `Swap` in the spl-token-swap program
+pub const SWAP_TAG: u8 = 1;
+
+/// Version byte of a `SwapV1` swap account
+pub const SWAP_V1_VERSION: u8 = 1;
+
+/// Packed size of a versioned `SwapV1` swap account
+pub const SWAP_V1_LEN: usize = 324; // 1 + 1 + 1 + 32 + 32 + 32 + 32 + 32 + 32 + 32 + 64 + 1 + 32
+
+/// Curve type of constant product swap pools
+pub const CONSTANT_PRODUCT_CURVE: u8 = 0;
+
+/// Fields of a spl-token-swap `SwapV1` account used by the lending program
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SwapV1 {
+    /// Token A account owned by the swap authority
+    pub token_a: Pubkey,
+    /// Token B account owned by the swap authority
+    pub token_b: Pubkey,
+    /// Pool token mint
+    pub pool_mint: Pubkey,
+    /// Token A mint
+    pub token_a_mint: Pubkey,
+    /// Token B mint
+    pub token_b_mint: Pubkey,
+    /// Swap curve type
+    pub curve_type: u8,
+}
+
+impl SwapV1 {
+    /// Unpacks an initialized, versioned `SwapV1` swap account
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() < SWAP_V1_LEN {
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+        let input = array_ref![input, 0, SWAP_V1_LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            is_initialized,
+            _bump_seed,
+            _token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            _pool_fee_account,
+            _fees,
+            curve_type,
+            _curve_calculator,
+        ) = array_refs![
+            input,
+            1,
+            1,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            64,
+            1,
+            PUBKEY_BYTES
+        ];
+        if version[0] != SWAP_V1_VERSION || is_initialized[0] != 1 {
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        Ok(Self {
+            token_a: Pubkey::new_from_array(*token_a),
+            token_b: Pubkey::new_from_array(*token_b),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            curve_type: curve_type[0],
+        })
+    }
+}
+
+/// Creates a spl-token-swap `Swap` instruction, without the optional host fee account
+#[allow(clippy::too_many_arguments)]
+pub fn swap(
+    program_id: Pubkey,
+    token_program_id: Pubkey,
+    swap_pubkey: Pubkey,
+    swap_authority_pubkey: Pubkey,
+    user_transfer_authority_pubkey: Pubkey,
+    source_pubkey: Pubkey,
+    swap_source_pubkey: Pubkey,
+    swap_destination_pubkey: Pubkey,
+    destination_pubkey: Pubkey,
+    pool_mint_pubkey: Pubkey,
+    pool_fee_pubkey: Pubkey,
+    amount_in: u64,
+    minimum_amount_out: u64,
+) -> Instruction {
+    let mut data = Vec::with_capacity(1 + 2 * size_of::<u64>());
+    data.push(SWAP_TAG);
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&minimum_amount_out.to_le_bytes());
+
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(swap_pubkey, false),
+            AccountMeta::new_readonly(swap_authority_pubkey, false),
+            AccountMeta::new_readonly(user_transfer_authority_pubkey, true),
+            AccountMeta::new(source_pubkey, false),
+            AccountMeta::new(swap_source_pubkey, false),
+            AccountMeta::new(swap_destination_pubkey, false),
+            AccountMeta::new(destination_pubkey, false),
+            AccountMeta::new(pool_mint_pubkey, false),
+            AccountMeta::new(pool_fee_pubkey, false),
+            AccountMeta::new_readonly(token_program_id, false),
+        ],
+        data,
+    }
+}
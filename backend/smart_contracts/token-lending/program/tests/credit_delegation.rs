@@ -0,0 +1,712 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::instruction::approve;
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        add_market_member, approve_credit_delegation, borrow_obligation_liquidity_delegated,
+        refresh_obligation, refresh_reserve, repay_obligation_liquidity, revoke_credit_delegation,
+        revoke_market_member, set_lending_market_allowlist_admin, set_obligation_owner,
+    },
+    math::Decimal,
+    processor::process_instruction,
+    state::{
+        credit_delegation_address, market_member_address, CreditDelegation,
+        INITIAL_COLLATERAL_RATIO,
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10_000 * FRACTIONAL_TO_USDC;
+const DELEGATED_BORROW_AMOUNT: u64 = 300 * FRACTIONAL_TO_USDC;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 200 * FRACTIONAL_TO_USDC;
+
+fn setup() -> (ProgramTest, TestSolUsdcMarket) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // the obligation owner pays the rent for credit delegations
+    let market = add_sol_usdc_market(
+        &mut test,
+        AddSolUsdcMarketArgs {
+            fund_user_accounts_owner: true,
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+
+    (test, market)
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            usdc_oracle,
+            test_obligation,
+            ..
+        },
+    ) = setup();
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let delegate = Keypair::new();
+    let delegate_liquidity_pubkey = create_token_account(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_mint_pubkey,
+        &payer,
+        Some(delegate.pubkey()),
+        None,
+    )
+    .await;
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &spl_token_lending::id(),
+        &test_obligation.pubkey,
+        &delegate.pubkey(),
+        &usdc_test_reserve.pubkey,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve_credit_delegation(
+                spl_token_lending::id(),
+                DELEGATED_BORROW_AMOUNT,
+                test_obligation.pubkey,
+                usdc_test_reserve.pubkey,
+                user_accounts_owner.pubkey(),
+                delegate.pubkey(),
+                lending_market.pubkey,
+                None,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_delegated(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                delegate_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                delegate.pubkey(),
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner, &delegate], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // the delegate receives the liquidity and the debt is recorded on the obligation
+    assert_eq!(
+        get_token_balance(&mut banks_client, delegate_liquidity_pubkey).await,
+        USDC_BORROW_AMOUNT_FRACTIONAL
+    );
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    let liquidity = &obligation.borrows[0];
+    assert_eq!(liquidity.borrow_reserve, usdc_test_reserve.pubkey);
+    let borrow_amount = liquidity.borrowed_amount_wads.try_ceil_u64().unwrap();
+    assert!(borrow_amount > USDC_BORROW_AMOUNT_FRACTIONAL);
+
+    let credit_delegation_account = banks_client
+        .get_account(credit_delegation_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let credit_delegation = CreditDelegation::unpack(&credit_delegation_account.data).unwrap();
+    assert_eq!(credit_delegation.obligation, test_obligation.pubkey);
+    assert_eq!(credit_delegation.delegate, delegate.pubkey());
+    assert_eq!(credit_delegation.borrow_reserve, usdc_test_reserve.pubkey);
+    assert_eq!(credit_delegation.delegator, user_accounts_owner.pubkey());
+    assert_eq!(
+        credit_delegation.remaining_borrow_amount,
+        DELEGATED_BORROW_AMOUNT - borrow_amount
+    );
+
+    // the delegate cannot borrow above the remaining allowance
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_delegated(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                delegate_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                delegate.pubkey(),
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &delegate], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(LendingError::CreditDelegationExceeded as u32)
+        )
+    );
+
+    // the delegate can repay the debt of the obligation
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &delegate_liquidity_pubkey,
+                &delegate.pubkey(),
+                &delegate.pubkey(),
+                &[],
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+            )
+            .unwrap(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                delegate_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                delegate.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &delegate], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(borrow_amount - USDC_BORROW_AMOUNT_FRACTIONAL)
+    );
+
+    // revoking the credit delegation returns its rent to the obligation owner
+    let owner_lamports = banks_client
+        .get_balance(user_accounts_owner.pubkey())
+        .await
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[revoke_credit_delegation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+            user_accounts_owner.pubkey(),
+            delegate.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    assert_eq!(
+        banks_client
+            .get_account(credit_delegation_pubkey)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        banks_client
+            .get_balance(user_accounts_owner.pubkey())
+            .await
+            .unwrap(),
+        owner_lamports + credit_delegation_account.lamports
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_signers() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+
+            test_obligation,
+            ..
+        },
+    ) = setup();
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let delegate = Keypair::new();
+    let delegate_liquidity_pubkey = create_token_account(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_mint_pubkey,
+        &payer,
+        Some(delegate.pubkey()),
+        None,
+    )
+    .await;
+
+    // only the obligation owner can approve a delegate
+    let mut transaction = Transaction::new_with_payer(
+        &[approve_credit_delegation(
+            spl_token_lending::id(),
+            DELEGATED_BORROW_AMOUNT,
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+            delegate.pubkey(),
+            delegate.pubkey(),
+            lending_market.pubkey,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &delegate], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[approve_credit_delegation(
+            spl_token_lending::id(),
+            DELEGATED_BORROW_AMOUNT,
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+            user_accounts_owner.pubkey(),
+            delegate.pubkey(),
+            lending_market.pubkey,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // another wallet cannot borrow with the credit delegation of the delegate
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &spl_token_lending::id(),
+        &test_obligation.pubkey,
+        &delegate.pubkey(),
+        &usdc_test_reserve.pubkey,
+    );
+    let other_wallet = Keypair::new();
+    let mut borrow_instruction = borrow_obligation_liquidity_delegated(
+        spl_token_lending::id(),
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        usdc_test_reserve.liquidity_supply_pubkey,
+        delegate_liquidity_pubkey,
+        usdc_test_reserve.pubkey,
+        usdc_test_reserve.config.fee_receiver,
+        test_obligation.pubkey,
+        lending_market.pubkey,
+        other_wallet.pubkey(),
+        None,
+        None,
+    );
+    borrow_instruction.accounts[8].pubkey = credit_delegation_pubkey;
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_instruction,
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &other_wallet], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_obligation_owner_changed() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            test_obligation,
+            ..
+        },
+    ) = setup();
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let delegate = Keypair::new();
+    let delegate_liquidity_pubkey = create_token_account(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_mint_pubkey,
+        &payer,
+        Some(delegate.pubkey()),
+        None,
+    )
+    .await;
+
+    // the delegation approved by the previous owner lapses with the transfer
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve_credit_delegation(
+                spl_token_lending::id(),
+                DELEGATED_BORROW_AMOUNT,
+                test_obligation.pubkey,
+                usdc_test_reserve.pubkey,
+                user_accounts_owner.pubkey(),
+                delegate.pubkey(),
+                lending_market.pubkey,
+                None,
+            ),
+            set_obligation_owner(
+                spl_token_lending::id(),
+                true,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                user_accounts_owner.pubkey(),
+                new_owner.pubkey(),
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &new_owner],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_delegated(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                delegate_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                delegate.pubkey(),
+                None,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &delegate], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+
+    // the new owner can revoke the lapsed delegation, refunding the rent to the previous owner
+    // that approved it
+    let (credit_delegation_pubkey, _bump_seed) = credit_delegation_address(
+        &spl_token_lending::id(),
+        &test_obligation.pubkey,
+        &delegate.pubkey(),
+        &usdc_test_reserve.pubkey,
+    );
+    let credit_delegation_lamports = banks_client
+        .get_balance(credit_delegation_pubkey)
+        .await
+        .unwrap();
+    let delegator_lamports = banks_client
+        .get_balance(user_accounts_owner.pubkey())
+        .await
+        .unwrap();
+
+    let revoke = |delegator_pubkey| {
+        revoke_credit_delegation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+            new_owner.pubkey(),
+            delegate.pubkey(),
+            delegator_pubkey,
+        )
+    };
+
+    let mut transaction =
+        Transaction::new_with_payer(&[revoke(new_owner.pubkey())], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[revoke(user_accounts_owner.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    assert_eq!(
+        banks_client
+            .get_balance(user_accounts_owner.pubkey())
+            .await
+            .unwrap(),
+        delegator_lamports + credit_delegation_lamports
+    );
+}
+
+#[tokio::test]
+async fn test_allowlisted_market() {
+    let (
+        mut test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            usdc_oracle,
+            test_obligation,
+            ..
+        },
+    ) = setup();
+
+    // the allowlist admin pays the rent for memberships
+    let allowlist_admin = Keypair::new();
+    add_funded_account(&mut test, allowlist_admin.pubkey());
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let delegate = Keypair::new();
+    let delegate_liquidity_pubkey = create_token_account(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_mint_pubkey,
+        &payer,
+        Some(delegate.pubkey()),
+        None,
+    )
+    .await;
+    let (market_member_pubkey, _bump_seed) = market_member_address(
+        &spl_token_lending::id(),
+        &lending_market.pubkey,
+        &delegate.pubkey(),
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_lending_market_allowlist_admin(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            allowlist_admin.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let approve_delegate = |market_member_pubkey| {
+        approve_credit_delegation(
+            spl_token_lending::id(),
+            DELEGATED_BORROW_AMOUNT,
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+            user_accounts_owner.pubkey(),
+            delegate.pubkey(),
+            lending_market.pubkey,
+            market_member_pubkey,
+        )
+    };
+    let borrow = |market_member_pubkey| {
+        borrow_obligation_liquidity_delegated(
+            spl_token_lending::id(),
+            USDC_BORROW_AMOUNT_FRACTIONAL / 2,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            delegate_liquidity_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.config.fee_receiver,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            delegate.pubkey(),
+            market_member_pubkey,
+            None,
+        )
+    };
+
+    // only market members can be approved as delegates
+    let mut transaction =
+        Transaction::new_with_payer(&[approve_delegate(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            add_market_member(
+                spl_token_lending::id(),
+                lending_market.pubkey,
+                allowlist_admin.pubkey(),
+                delegate.pubkey(),
+            ),
+            approve_delegate(Some(market_member_pubkey)),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow(Some(market_member_pubkey)),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &allowlist_admin, &user_accounts_owner, &delegate],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // the delegate's membership is checked again on each borrow
+    let mut transaction = Transaction::new_with_payer(
+        &[revoke_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            delegate.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &allowlist_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            borrow(Some(market_member_pubkey)),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &delegate], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+}
@@ -0,0 +1,315 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    clock::Clock,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        borrow_obligation_liquidity, deposit_reserve_liquidity, deprecate_reserve,
+        refresh_obligation, refresh_reserve,
+    },
+    math::{Decimal, TryDiv},
+    processor::process_instruction,
+    state::INITIAL_COLLATERAL_RATIO,
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const LTV_DECAY_SLOTS: u64 = 10;
+
+fn setup() -> (ProgramTest, TestSolUsdcMarket) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let market = add_sol_usdc_market(
+        &mut test,
+        AddSolUsdcMarketArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                user_liquidity_amount: USDC_AMOUNT_FRACTIONAL,
+                liquidity_amount: 10 * USDC_AMOUNT_FRACTIONAL,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+
+    (test, market)
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_oracle,
+            sol_test_reserve,
+            usdc_oracle,
+            usdc_test_reserve,
+            test_obligation,
+        },
+    ) = setup();
+
+    let mut test_context = test.start_with_context().await;
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+    let deprecation_slot = test_context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .slot;
+
+    let refresh_sol_obligation = [
+        refresh_reserve(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            sol_oracle.pyth_price_pubkey,
+            sol_oracle.switchboard_feed_pubkey,
+        ),
+        refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![sol_test_reserve.pubkey],
+        ),
+    ];
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_sol_obligation[0].clone(),
+            refresh_sol_obligation[1].clone(),
+            deprecate_reserve(
+                spl_token_lending::id(),
+                LTV_DECAY_SLOTS,
+                sol_test_reserve.pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            deprecate_reserve(
+                spl_token_lending::id(),
+                LTV_DECAY_SLOTS,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let sol_reserve = sol_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert!(sol_reserve.deprecated);
+    assert_eq!(sol_reserve.deprecation_slot, deprecation_slot);
+    assert_eq!(sol_reserve.ltv_decay_slots, LTV_DECAY_SLOTS);
+    let allowed_borrow_value = test_obligation
+        .get_state(&mut test_context.banks_client)
+        .await
+        .allowed_borrow_value;
+    assert!(allowed_borrow_value > Decimal::zero());
+
+    // deprecated reserves do not allow deposits
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            USDC_AMOUNT_FRACTIONAL,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ReserveDeprecated as u32)
+        )
+    );
+
+    // deprecated reserves do not allow borrows
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_sol_obligation[0].clone(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_sol_obligation[1].clone(),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                FRACTIONAL_TO_USDC,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            3,
+            InstructionError::Custom(LendingError::ReserveDeprecated as u32)
+        )
+    );
+
+    // the loan to value ratio of deposits decays linearly to zero
+    for (slots_elapsed, expected_allowed_borrow_value) in [
+        (
+            LTV_DECAY_SLOTS / 2,
+            allowed_borrow_value.try_div(2).unwrap(),
+        ),
+        (LTV_DECAY_SLOTS, Decimal::zero()),
+    ] {
+        test_context
+            .warp_to_slot(deprecation_slot + slots_elapsed)
+            .unwrap();
+        let mut transaction =
+            Transaction::new_with_payer(&refresh_sol_obligation, Some(&payer.pubkey()));
+        let recent_blockhash = test_context
+            .banks_client
+            .get_recent_blockhash()
+            .await
+            .unwrap();
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .is_ok());
+
+        let obligation = test_obligation
+            .get_state(&mut test_context.banks_client)
+            .await;
+        assert_eq!(
+            obligation.allowed_borrow_value,
+            expected_allowed_borrow_value
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_deprecation() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            lending_market,
+            usdc_test_reserve,
+            ..
+        },
+    ) = setup();
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // only the lending market owner can deprecate a reserve
+    let not_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[deprecate_reserve(
+            spl_token_lending::id(),
+            LTV_DECAY_SLOTS,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            not_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &not_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+
+    // deprecation cannot be repeated to change the schedule
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            deprecate_reserve(
+                spl_token_lending::id(),
+                LTV_DECAY_SLOTS,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            deprecate_reserve(
+                spl_token_lending::id(),
+                2 * LTV_DECAY_SLOTS,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ReserveDeprecated as u32)
+        )
+    );
+}
@@ -0,0 +1,641 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    clock::Clock,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::instruction::approve;
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        borrow_obligation_liquidity, borrow_obligation_liquidity_fixed_term, refresh_obligation,
+        refresh_reserve, repay_obligation_liquidity, settle_fixed_term_borrow,
+    },
+    math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
+    processor::process_instruction,
+    state::{Reserve, ReserveConfig, INITIAL_COLLATERAL_RATIO, SLOTS_PER_YEAR},
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 500 * FRACTIONAL_TO_USDC;
+const FIXED_TERM_SLOTS: u64 = 10;
+
+fn setup(usdc_reserve_config: ReserveConfig) -> (ProgramTest, TestSolUsdcMarket) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let market = add_sol_usdc_market(
+        &mut test,
+        AddSolUsdcMarketArgs {
+            sol_reserve: AddReserveArgs {
+                collateral_amount: 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                user_liquidity_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                liquidity_amount: 10 * USDC_BORROW_AMOUNT_FRACTIONAL,
+                config: usdc_reserve_config,
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+
+    (test, market)
+}
+
+fn fixed_term_reserve_config() -> ReserveConfig {
+    ReserveConfig {
+        fixed_rate_premium: 2,
+        max_fixed_term_slots: FIXED_TERM_SLOTS,
+        ..test_reserve_config()
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            usdc_oracle,
+            test_obligation,
+            ..
+        },
+    ) = setup(fixed_term_reserve_config());
+
+    let mut test_context = test.start_with_context().await;
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+    let borrow_slot = test_context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .slot;
+
+    let initial_usdc_reserve = usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_fixed_term(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                FIXED_TERM_SLOTS,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], test_context.last_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the fixed rate borrow is tracked apart from variable rate borrows
+    let usdc_reserve = usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    let obligation = test_obligation
+        .get_state(&mut test_context.banks_client)
+        .await;
+    let liquidity = &obligation.borrows[0];
+    assert!(liquidity.is_fixed_rate());
+    assert_eq!(liquidity.maturity_slot, borrow_slot + FIXED_TERM_SLOTS);
+    assert!(liquidity.borrowed_amount_wads > Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL));
+
+    // the fixed rate is priced at the utilization rate after the borrow
+    let fixed_borrow_rate = initial_usdc_reserve
+        .fixed_borrow_rate(liquidity.borrowed_amount_wads)
+        .unwrap();
+    assert!(
+        fixed_borrow_rate
+            > initial_usdc_reserve
+                .current_borrow_rate()
+                .unwrap()
+                .try_add(Rate::from_percent(2))
+                .unwrap()
+    );
+    assert_eq!(
+        liquidity.fixed_interest_per_slot_wads,
+        liquidity
+            .borrowed_amount_wads
+            .try_mul(fixed_borrow_rate)
+            .unwrap()
+            .try_div(SLOTS_PER_YEAR)
+            .unwrap()
+    );
+    assert_eq!(usdc_reserve.liquidity.borrowed_amount_wads, Decimal::zero());
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_borrowed_amount_wads,
+        liquidity.borrowed_amount_wads
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_interest_per_slot_wads,
+        liquidity.fixed_interest_per_slot_wads
+    );
+    let borrowed_amount_wads = liquidity.borrowed_amount_wads;
+    let fixed_interest_per_slot_wads = liquidity.fixed_interest_per_slot_wads;
+
+    // variable rate borrows cannot be added to a fixed rate borrow
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity(
+                spl_token_lending::id(),
+                FRACTIONAL_TO_USDC,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(LendingError::FixedRateBorrowConflict as u32)
+        )
+    );
+
+    // the borrow cannot be settled before maturity
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            settle_fixed_term_borrow(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                usdc_test_reserve.pubkey,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::FixedTermNotMatured as u32)
+        )
+    );
+
+    // anyone can settle the borrow into a variable rate borrow once it matures, and interest
+    // accrues at the variable rate instead of the fixed rate from maturity
+    test_context
+        .warp_to_slot(borrow_slot + FIXED_TERM_SLOTS + 5)
+        .unwrap();
+    let usdc_reserve =
+        refresh_usdc_reserve(&mut test_context, &usdc_test_reserve, &usdc_oracle).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[settle_fixed_term_borrow(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            usdc_test_reserve.pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    assert_settled(
+        &mut test_context,
+        &usdc_test_reserve,
+        &test_obligation,
+        matured_borrowed_amount(
+            &usdc_reserve,
+            borrowed_amount_wads
+                .try_add(
+                    fixed_interest_per_slot_wads
+                        .try_mul(FIXED_TERM_SLOTS)
+                        .unwrap(),
+                )
+                .unwrap(),
+            5,
+        ),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_refresh_settles_matured_borrow() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_oracle,
+            sol_test_reserve,
+            usdc_test_reserve,
+            usdc_oracle,
+            test_obligation,
+            ..
+        },
+    ) = setup(fixed_term_reserve_config());
+
+    let mut test_context = test.start_with_context().await;
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+    let borrow_slot = test_context
+        .banks_client
+        .get_sysvar::<Clock>()
+        .await
+        .unwrap()
+        .slot;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_fixed_term(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                FIXED_TERM_SLOTS,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                None,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], test_context.last_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let liquidity = test_obligation
+        .get_state(&mut test_context.banks_client)
+        .await
+        .borrows[0]
+        .clone();
+
+    // refreshing the obligation after maturity settles the borrow without SettleFixedTermBorrow
+    test_context
+        .warp_to_slot(borrow_slot + FIXED_TERM_SLOTS + 5)
+        .unwrap();
+    let usdc_reserve =
+        refresh_usdc_reserve(&mut test_context, &usdc_test_reserve, &usdc_oracle).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.pyth_price_pubkey,
+                sol_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    assert_settled(
+        &mut test_context,
+        &usdc_test_reserve,
+        &test_obligation,
+        matured_borrowed_amount(
+            &usdc_reserve,
+            liquidity
+                .borrowed_amount_wads
+                .try_add(
+                    liquidity
+                        .fixed_interest_per_slot_wads
+                        .try_mul(FIXED_TERM_SLOTS)
+                        .unwrap(),
+                )
+                .unwrap(),
+            5,
+        ),
+    )
+    .await;
+}
+
+async fn refresh_usdc_reserve(
+    test_context: &mut ProgramTestContext,
+    usdc_test_reserve: &TestReserve,
+    usdc_oracle: &TestOracle,
+) -> Reserve {
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+    test_context.last_blockhash = test_context
+        .banks_client
+        .get_recent_blockhash()
+        .await
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserve(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_oracle.pyth_price_pubkey,
+            usdc_oracle.switchboard_feed_pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await
+}
+
+/// Amount a fixed term borrow that matured `slots_since_maturity` ago owes once settled, when
+/// `reserve` is the borrow reserve just before settling
+fn matured_borrowed_amount(
+    reserve: &Reserve,
+    fixed_borrowed_amount_wads: Decimal,
+    slots_since_maturity: u64,
+) -> Decimal {
+    let compounded_interest_rate = Rate::one()
+        .try_add(
+            reserve
+                .current_borrow_rate()
+                .unwrap()
+                .try_div(SLOTS_PER_YEAR)
+                .unwrap(),
+        )
+        .unwrap()
+        .try_pow(slots_since_maturity)
+        .unwrap();
+    fixed_borrowed_amount_wads
+        .try_mul(compounded_interest_rate)
+        .unwrap()
+}
+
+async fn assert_settled(
+    test_context: &mut ProgramTestContext,
+    usdc_test_reserve: &TestReserve,
+    test_obligation: &TestObligation,
+    borrowed_amount_wads: Decimal,
+) {
+    let usdc_reserve = usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    let obligation = test_obligation
+        .get_state(&mut test_context.banks_client)
+        .await;
+    let liquidity = &obligation.borrows[0];
+    assert!(!liquidity.is_fixed_rate());
+    assert!(borrowed_amount_wads > Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL));
+    assert_eq!(liquidity.borrowed_amount_wads, borrowed_amount_wads);
+    assert_eq!(
+        liquidity.cumulative_borrow_rate_wads,
+        usdc_reserve.liquidity.cumulative_borrow_rate_wads
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.borrowed_amount_wads,
+        liquidity.borrowed_amount_wads
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_borrowed_amount_wads,
+        Decimal::zero()
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_interest_per_slot_wads,
+        Decimal::zero()
+    );
+}
+
+#[tokio::test]
+async fn test_repay() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            user_accounts_owner,
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            usdc_oracle,
+            test_obligation,
+            ..
+        },
+    ) = setup(fixed_term_reserve_config());
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let user_transfer_authority = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey],
+            ),
+            borrow_obligation_liquidity_fixed_term(
+                spl_token_lending::id(),
+                USDC_BORROW_AMOUNT_FRACTIONAL,
+                FIXED_TERM_SLOTS,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.config.fee_receiver,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                None,
+            ),
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                u64::MAX,
+            )
+            .unwrap(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            repay_obligation_liquidity(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.user_liquidity_pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                usdc_test_reserve.pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // repaying in full removes the borrow and its interest from the fixed rate borrows
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert!(obligation.borrows.is_empty());
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_borrowed_amount_wads,
+        Decimal::zero()
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.fixed_interest_per_slot_wads,
+        Decimal::zero()
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.available_amount,
+        10 * USDC_BORROW_AMOUNT_FRACTIONAL
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_fixed_term() {
+    for (config, term_slots) in [
+        (fixed_term_reserve_config(), 0),
+        (fixed_term_reserve_config(), FIXED_TERM_SLOTS + 1),
+        // fixed term borrows are disabled without a max fixed term
+        (test_reserve_config(), 1),
+    ] {
+        let (
+            test,
+            TestSolUsdcMarket {
+                user_accounts_owner,
+                lending_market,
+                sol_test_reserve,
+                usdc_test_reserve,
+                test_obligation,
+                ..
+            },
+        ) = setup(config);
+
+        let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                refresh_obligation(
+                    spl_token_lending::id(),
+                    test_obligation.pubkey,
+                    vec![sol_test_reserve.pubkey],
+                ),
+                borrow_obligation_liquidity_fixed_term(
+                    spl_token_lending::id(),
+                    USDC_BORROW_AMOUNT_FRACTIONAL,
+                    term_slots,
+                    usdc_test_reserve.liquidity_supply_pubkey,
+                    usdc_test_reserve.user_liquidity_pubkey,
+                    usdc_test_reserve.pubkey,
+                    usdc_test_reserve.config.fee_receiver,
+                    test_obligation.pubkey,
+                    lending_market.pubkey,
+                    test_obligation.owner,
+                    None,
+                ),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+        assert_eq!(
+            banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(LendingError::InvalidFixedTerm as u32)
+            )
+        );
+    }
+}
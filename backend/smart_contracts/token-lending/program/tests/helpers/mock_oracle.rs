@@ -0,0 +1,244 @@
+//! A tiny oracle program that lets tests write Pyth and Switchboard price accounts, so prices can
+//! be moved between transactions without devnet fixtures.
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use spl_token_lending::pyth;
+use std::{convert::TryInto, mem::size_of};
+use switchboard_program::{AggregatorState, RoundResult, SwitchboardAccountType};
+
+/// Length of the Switchboard aggregator accounts written by the mock oracle, large enough for any
+/// serialized `MockSwitchboardPrice`
+pub const SWITCHBOARD_FEED_LEN: usize = 128;
+
+/// Pyth aggregate price written to a Pyth `Price` account
+#[derive(Clone, Copy)]
+pub struct MockPythPrice {
+    pub price: i64,
+    pub expo: i32,
+    pub conf: u64,
+    pub status: pyth::PriceStatus,
+    pub valid_slot: u64,
+}
+
+impl Default for MockPythPrice {
+    fn default() -> Self {
+        Self {
+            price: 0,
+            expo: 0,
+            conf: 0,
+            status: pyth::PriceStatus::Trading,
+            valid_slot: 0,
+        }
+    }
+}
+
+/// Switchboard round result written to an aggregator account
+#[derive(Clone, Copy, Default)]
+pub struct MockSwitchboardPrice {
+    pub price: f64,
+    pub round_open_slot: u64,
+}
+
+pub enum MockOracleInstruction {
+    /// Overwrites the aggregate price of a Pyth price account.
+    ///
+    /// Accounts expected:
+    ///
+    ///   0. `[writable]` Pyth price account, owned by the mock oracle program.
+    SetPythPrice(MockPythPrice),
+
+    /// Overwrites the round result of a Switchboard aggregator account.
+    ///
+    /// Accounts expected:
+    ///
+    ///   0. `[writable]` Switchboard aggregator account, owned by the mock oracle program.
+    SetSwitchboardPrice(MockSwitchboardPrice),
+}
+
+impl MockOracleInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(match tag {
+            0 => {
+                if rest.len() != 29 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Self::SetPythPrice(MockPythPrice {
+                    price: i64::from_le_bytes(rest[0..8].try_into().unwrap()),
+                    expo: i32::from_le_bytes(rest[8..12].try_into().unwrap()),
+                    conf: u64::from_le_bytes(rest[12..20].try_into().unwrap()),
+                    status: match rest[20] {
+                        0 => pyth::PriceStatus::Unknown,
+                        1 => pyth::PriceStatus::Trading,
+                        2 => pyth::PriceStatus::Halted,
+                        3 => pyth::PriceStatus::Auction,
+                        _ => return Err(ProgramError::InvalidInstructionData),
+                    },
+                    valid_slot: u64::from_le_bytes(rest[21..29].try_into().unwrap()),
+                })
+            }
+            1 => {
+                if rest.len() != 16 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Self::SetSwitchboardPrice(MockSwitchboardPrice {
+                    price: f64::from_le_bytes(rest[0..8].try_into().unwrap()),
+                    round_open_slot: u64::from_le_bytes(rest[8..16].try_into().unwrap()),
+                })
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        match self {
+            Self::SetPythPrice(price) => {
+                buf.push(0);
+                buf.extend_from_slice(&price.price.to_le_bytes());
+                buf.extend_from_slice(&price.expo.to_le_bytes());
+                buf.extend_from_slice(&price.conf.to_le_bytes());
+                buf.push(price.status as u8);
+                buf.extend_from_slice(&price.valid_slot.to_le_bytes());
+            }
+            Self::SetSwitchboardPrice(price) => {
+                buf.push(1);
+                buf.extend_from_slice(&price.price.to_le_bytes());
+                buf.extend_from_slice(&price.round_open_slot.to_le_bytes());
+            }
+        }
+        buf
+    }
+}
+
+/// Creates a `SetPythPrice` instruction
+pub fn set_pyth_price(
+    program_id: Pubkey,
+    pyth_price_pubkey: Pubkey,
+    price: MockPythPrice,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(pyth_price_pubkey, false)],
+        data: MockOracleInstruction::SetPythPrice(price).pack(),
+    }
+}
+
+/// Creates a `SetSwitchboardPrice` instruction
+pub fn set_switchboard_price(
+    program_id: Pubkey,
+    switchboard_feed_pubkey: Pubkey,
+    price: MockSwitchboardPrice,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(switchboard_feed_pubkey, false)],
+        data: MockOracleInstruction::SetSwitchboardPrice(price).pack(),
+    }
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let oracle_info = next_account_info(account_info_iter)?;
+    if oracle_info.owner != program_id {
+        msg!("Oracle account is not owned by the mock oracle program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut data = oracle_info.try_borrow_mut_data()?;
+    match MockOracleInstruction::unpack(instruction_data)? {
+        MockOracleInstruction::SetPythPrice(price) => {
+            msg!("Instruction: Set Pyth Price");
+            write_pyth_price(&mut data, &price)
+        }
+        MockOracleInstruction::SetSwitchboardPrice(price) => {
+            msg!("Instruction: Set Switchboard Price");
+            write_switchboard_price(&mut data, &price)
+        }
+    }
+}
+
+/// Writes a Pyth price, keeping the header and product key of an existing account
+fn write_pyth_price(data: &mut [u8], price: &MockPythPrice) -> ProgramResult {
+    let pyth_price =
+        pyth::load_mut::<pyth::Price>(data).map_err(|_| ProgramError::InvalidAccountData)?;
+    pyth_price.expo = price.expo;
+    pyth_price.valid_slot = price.valid_slot;
+    pyth_price.agg.price = price.price;
+    pyth_price.agg.conf = price.conf;
+    pyth_price.agg.status = price.status;
+    pyth_price.agg.pub_slot = price.valid_slot;
+    Ok(())
+}
+
+/// Writes a Switchboard aggregator holding a single valid round
+fn write_switchboard_price(data: &mut [u8], price: &MockSwitchboardPrice) -> ProgramResult {
+    let aggregator = AggregatorState {
+        version: Some(1),
+        last_round_result: Some(RoundResult {
+            num_success: Some(1),
+            result: Some(price.price),
+            round_open_slot: Some(price.round_open_slot),
+            ..RoundResult::default()
+        }),
+        ..AggregatorState::default()
+    };
+
+    data.fill(0);
+    data[0] = SwitchboardAccountType::TYPE_AGGREGATOR as u8;
+    quick_protobuf::serialize_into_slice(&aggregator, &mut data[1..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
+
+/// Account data of a Pyth product quoted in `quote_currency`, pointing at `pyth_price_pubkey`
+pub fn pyth_product_data(pyth_price_pubkey: &Pubkey, quote_currency: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; size_of::<pyth::Product>()];
+    let pyth_product = pyth::load_mut::<pyth::Product>(&mut data).unwrap();
+    pyth_product.magic = pyth::MAGIC;
+    pyth_product.ver = pyth::VERSION;
+    pyth_product.atype = pyth::AccountType::Product as u32;
+    pyth_product.size = pyth::PROD_ACCT_SIZE as u32;
+    pyth_product.px_acc.val = pyth_price_pubkey.to_bytes();
+
+    let key = b"quote_currency";
+    let mut attr = vec![key.len() as u8];
+    attr.extend_from_slice(key);
+    attr.push(quote_currency.len() as u8);
+    attr.extend_from_slice(quote_currency);
+    pyth_product.attr[..attr.len()].copy_from_slice(&attr);
+    data
+}
+
+/// Account data of a Pyth price for `pyth_product_pubkey`
+pub fn pyth_price_data(pyth_product_pubkey: &Pubkey, price: &MockPythPrice) -> Vec<u8> {
+    let mut data = vec![0; size_of::<pyth::Price>()];
+    let pyth_price = pyth::load_mut::<pyth::Price>(&mut data).unwrap();
+    pyth_price.magic = pyth::MAGIC;
+    pyth_price.ver = pyth::VERSION;
+    pyth_price.atype = pyth::AccountType::Price as u32;
+    pyth_price.size = size_of::<pyth::Price>() as u32;
+    pyth_price.ptype = pyth::PriceType::Price;
+    pyth_price.prod.val = pyth_product_pubkey.to_bytes();
+    write_pyth_price(&mut data, price).unwrap();
+    data
+}
+
+/// Account data of a Switchboard aggregator
+pub fn switchboard_feed_data(price: &MockSwitchboardPrice) -> Vec<u8> {
+    let mut data = vec![0; SWITCHBOARD_FEED_LEN];
+    write_switchboard_price(&mut data, price).unwrap();
+    data
+}
@@ -2,14 +2,18 @@
 
 pub mod flash_loan_receiver;
 pub mod genesis;
+pub mod mock_oracle;
+pub mod token_swap;
 
 use assert_matches::*;
-use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use mock_oracle::{MockPythPrice, MockSwitchboardPrice};
+use solana_program::{clock::Epoch, program_option::COption, program_pack::Pack, pubkey::Pubkey};
 use solana_program_test::*;
 use solana_sdk::{
     account::Account,
     signature::{read_keypair_file, Keypair, Signer},
     system_instruction::create_account,
+    system_program,
     transaction::{Transaction, TransactionError},
 };
 use spl_token::{
@@ -22,16 +26,20 @@ use spl_token_lending::{
         deposit_reserve_liquidity_and_obligation_collateral, init_lending_market, init_obligation,
         init_reserve, liquidate_obligation, refresh_reserve,
     },
-    math::{Decimal, Rate, TryAdd, TryMul},
+    math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
     pyth,
+    stake_pool::{STAKE_POOL_ACCOUNT_TYPE, STAKE_POOL_PREFIX_LEN},
     state::{
-        InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-        NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, ObligationCollateral,
-        ObligationLiquidity, Reserve, ReserveCollateral, ReserveConfig, ReserveFees,
-        ReserveLiquidity, INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION,
+        EModeGroup, InitLendingMarketParams, InitObligationParams, InitReserveParams,
+        LendingMarket, NewReserveCollateralParams, NewReserveLiquidityParams, Obligation,
+        ObligationCollateral, ObligationLiquidity, Reserve, ReserveCollateral, ReserveConfig,
+        ReserveFees, ReserveLiquidity, INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION,
     },
 };
-use std::{convert::TryInto, str::FromStr};
+use std::{
+    convert::{TryFrom, TryInto},
+    str::FromStr,
+};
 
 pub const QUOTE_CURRENCY: [u8; 32] =
     *b"USD\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
@@ -56,6 +64,13 @@ pub fn test_reserve_config() -> ReserveConfig {
         deposit_limit: 100_000_000_000,
         borrow_limit: u64::MAX,
         fee_receiver: Keypair::new().pubkey(),
+        isolated: false,
+        borrow_only: false,
+        added_borrow_weight_bps: 0,
+        emode_group: 0,
+        max_liquidation_bonus: 0,
+        fixed_rate_premium: 0,
+        max_fixed_term_slots: 0,
     }
 }
 
@@ -69,7 +84,7 @@ pub const SRM_SWITCHBOARD_FEED: &str = "BAoygKcKN7wk8yKzLD6sxzUQUqLvhBV1rjMA4UJq
 
 pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
 
-trait AddPacked {
+pub trait AddPacked {
     fn add_packable_account<T: Pack>(
         &mut self,
         pubkey: Pubkey,
@@ -94,6 +109,15 @@ impl AddPacked for ProgramTest {
 }
 
 pub fn add_lending_market(test: &mut ProgramTest) -> TestLendingMarket {
+    add_lending_market_with_emode_groups(test, &[])
+}
+
+/// Adds a lending market with efficiency mode groups already enabled, since enabling a group
+/// otherwise waits for RESERVE_CONFIG_DELAY_SLOTS
+pub fn add_lending_market_with_emode_groups(
+    test: &mut ProgramTest,
+    emode_groups: &[(u8, EModeGroup)],
+) -> TestLendingMarket {
     let lending_market_pubkey = Pubkey::new_unique();
     let (lending_market_authority, bump_seed) =
         Pubkey::find_program_address(&[lending_market_pubkey.as_ref()], &spl_token_lending::id());
@@ -104,17 +128,21 @@ pub fn add_lending_market(test: &mut ProgramTest) -> TestLendingMarket {
         .unwrap()
         .pubkey();
 
+    let mut lending_market = LendingMarket::new(InitLendingMarketParams {
+        bump_seed,
+        owner: lending_market_owner.pubkey(),
+        quote_currency: QUOTE_CURRENCY,
+        token_program_id: spl_token::id(),
+        oracle_program_id,
+        switchboard_oracle_program_id: oracle_program_id,
+    });
+    for (group, emode_group) in emode_groups {
+        lending_market.emode_groups[*group as usize - 1] = *emode_group;
+    }
     test.add_packable_account(
         lending_market_pubkey,
         u32::MAX as u64,
-        &LendingMarket::new(InitLendingMarketParams {
-            bump_seed,
-            owner: lending_market_owner.pubkey(),
-            quote_currency: QUOTE_CURRENCY,
-            token_program_id: spl_token::id(),
-            oracle_program_id,
-            switchboard_oracle_program_id: oracle_program_id,
-        }),
+        &lending_market,
         &spl_token_lending::id(),
     );
 
@@ -220,6 +248,171 @@ pub fn add_obligation(
     }
 }
 
+/// Adds a system account holding 1 SOL, for signers that pay rent
+pub fn add_funded_account(test: &mut ProgramTest, pubkey: Pubkey) {
+    test.add_account(
+        pubkey,
+        Account {
+            lamports: LAMPORTS_TO_SOL,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+}
+
+// 110 SOL staked for 100 pool tokens, each pool token is worth 1.1 SOL
+pub const STAKE_POOL_TOTAL_LAMPORTS: u64 = 110 * LAMPORTS_TO_SOL;
+pub const STAKE_POOL_TOKEN_SUPPLY: u64 = 100 * LAMPORTS_TO_SOL;
+
+pub fn add_stake_pool(
+    test: &mut ProgramTest,
+    owner: Pubkey,
+    pool_mint_pubkey: Pubkey,
+    last_update_epoch: Epoch,
+) -> Pubkey {
+    let mut data = Vec::with_capacity(STAKE_POOL_PREFIX_LEN);
+    data.push(STAKE_POOL_ACCOUNT_TYPE);
+    for _ in 0..3 {
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+    }
+    data.push(255);
+    for _ in 0..2 {
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+    }
+    data.extend_from_slice(pool_mint_pubkey.as_ref());
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(spl_token::id().as_ref());
+    data.extend_from_slice(&STAKE_POOL_TOTAL_LAMPORTS.to_le_bytes());
+    data.extend_from_slice(&STAKE_POOL_TOKEN_SUPPLY.to_le_bytes());
+    data.extend_from_slice(&last_update_epoch.to_le_bytes());
+
+    let stake_pool_pubkey = Pubkey::new_unique();
+    test.add_account(
+        stake_pool_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    stake_pool_pubkey
+}
+
+#[derive(Default)]
+pub struct AddSolUsdcMarketArgs<'a> {
+    pub emode_groups: &'a [(u8, EModeGroup)],
+    /// Fund the lending market owner to pay the rent of pending configs
+    pub fund_lending_market_owner: bool,
+    /// Fund the user accounts owner to pay the rent of accounts it creates
+    pub fund_user_accounts_owner: bool,
+    /// SOL reserve, backed by the native mint unless another liquidity mint is provided
+    pub sol_reserve: AddReserveArgs,
+    pub usdc_reserve: AddReserveArgs,
+    pub sol_deposit_amount: u64,
+    pub usdc_borrow_amount: u64,
+}
+
+pub struct TestSolUsdcMarket {
+    pub user_accounts_owner: Keypair,
+    pub lending_market: TestLendingMarket,
+    pub sol_oracle: TestOracle,
+    pub sol_test_reserve: TestReserve,
+    pub usdc_oracle: TestOracle,
+    pub usdc_test_reserve: TestReserve,
+    pub test_obligation: TestObligation,
+}
+
+/// Adds a lending market with a SOL and a USDC reserve, and an obligation of the user accounts
+/// owner depositing SOL and borrowing USDC
+pub fn add_sol_usdc_market(
+    test: &mut ProgramTest,
+    args: AddSolUsdcMarketArgs,
+) -> TestSolUsdcMarket {
+    let AddSolUsdcMarketArgs {
+        emode_groups,
+        fund_lending_market_owner,
+        fund_user_accounts_owner,
+        sol_reserve,
+        usdc_reserve,
+        sol_deposit_amount,
+        usdc_borrow_amount,
+    } = args;
+
+    let user_accounts_owner = Keypair::new();
+    if fund_user_accounts_owner {
+        add_funded_account(test, user_accounts_owner.pubkey());
+    }
+    let lending_market = add_lending_market_with_emode_groups(test, emode_groups);
+    if fund_lending_market_owner {
+        add_funded_account(test, lending_market.owner.pubkey());
+    }
+
+    let sol_oracle = add_sol_oracle(test);
+    let sol_liquidity_mint_pubkey = if sol_reserve.liquidity_mint_pubkey == Pubkey::default() {
+        spl_token::native_mint::id()
+    } else {
+        sol_reserve.liquidity_mint_pubkey
+    };
+    let sol_test_reserve = add_reserve(
+        test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_mint_pubkey: sol_liquidity_mint_pubkey,
+            liquidity_mint_decimals: 9,
+            ..sol_reserve
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(test);
+    let usdc_oracle = add_usdc_oracle(test);
+    let usdc_test_reserve = add_reserve(
+        test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            ..usdc_reserve
+        },
+    );
+
+    let deposits = [(&sol_test_reserve, sol_deposit_amount)];
+    let borrows = [(&usdc_test_reserve, usdc_borrow_amount)];
+    let test_obligation = add_obligation(
+        test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: if sol_deposit_amount > 0 {
+                &deposits
+            } else {
+                &[]
+            },
+            borrows: if usdc_borrow_amount > 0 {
+                &borrows
+            } else {
+                &[]
+            },
+            ..AddObligationArgs::default()
+        },
+    );
+
+    TestSolUsdcMarket {
+        user_accounts_owner,
+        lending_market,
+        sol_oracle,
+        sol_test_reserve,
+        usdc_oracle,
+        usdc_test_reserve,
+        test_obligation,
+    }
+}
+
 #[derive(Default)]
 pub struct AddReserveArgs {
     pub name: String,
@@ -233,6 +426,8 @@ pub struct AddReserveArgs {
     pub collateral_amount: u64,
     pub mark_fresh: bool,
     pub slots_elapsed: u64,
+    pub token_swap_oracle_pubkey: Pubkey,
+    pub stake_pool_oracle_pubkey: Pubkey,
 }
 
 pub fn add_reserve(
@@ -254,6 +449,8 @@ pub fn add_reserve(
         collateral_amount,
         mark_fresh,
         slots_elapsed,
+        token_swap_oracle_pubkey,
+        stake_pool_oracle_pubkey,
     } = args;
 
     let is_native = if liquidity_mint_pubkey == spl_token::native_mint::id() {
@@ -366,6 +563,8 @@ pub fn add_reserve(
         .unwrap();
     reserve.liquidity.cumulative_borrow_rate_wads =
         Decimal::one().try_mul(borrow_rate_multiplier).unwrap();
+    reserve.liquidity.token_swap_oracle_pubkey = token_swap_oracle_pubkey;
+    reserve.liquidity.stake_pool_oracle_pubkey = stake_pool_oracle_pubkey;
 
     if mark_fresh {
         reserve.last_update.update_slot(current_slot);
@@ -581,6 +780,7 @@ impl TestLendingMarket {
                     reserve.collateral_mint_pubkey,
                     self.pubkey,
                     user_transfer_authority.pubkey(),
+                    None,
                 ),
             ],
             Some(&payer.pubkey()),
@@ -632,6 +832,7 @@ impl TestLendingMarket {
                     reserve.liquidity_pyth_oracle_pubkey,
                     reserve.liquidity_switchboard_oracle_pubkey,
                     user_transfer_authority.pubkey(),
+                    None,
                 ),
             ],
             Some(&payer.pubkey()),
@@ -1013,6 +1214,7 @@ impl TestObligation {
                     obligation.pubkey,
                     lending_market.pubkey,
                     user_accounts_owner.pubkey(),
+                    None,
                 ),
             ],
             Some(&payer.pubkey()),
@@ -1238,6 +1440,118 @@ pub fn add_oracle(
     }
 }
 
+/// Registers the mock oracle program under the oracle program id of test lending markets, so
+/// oracle accounts can be updated with `TestOracle::set_pyth_price` and
+/// `TestOracle::set_switchboard_price`
+pub fn add_mock_oracle_program(test: &mut ProgramTest) {
+    let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json").unwrap();
+    test.add_program(
+        "mock_oracle",
+        oracle_program_id.pubkey(),
+        processor!(mock_oracle::process_instruction),
+    );
+}
+
+/// Adds Pyth and Switchboard accounts holding arbitrary prices. The oracle price is the Pyth
+/// price, scaled by its exponent.
+pub fn add_mock_oracle(
+    test: &mut ProgramTest,
+    pyth_price: MockPythPrice,
+    switchboard_price: MockSwitchboardPrice,
+) -> TestOracle {
+    let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json").unwrap();
+    let pyth_product_pubkey = Pubkey::new_unique();
+    let pyth_price_pubkey = Pubkey::new_unique();
+    let switchboard_feed_pubkey = Pubkey::new_unique();
+
+    for (pubkey, data) in [
+        (
+            pyth_product_pubkey,
+            mock_oracle::pyth_product_data(&pyth_price_pubkey, b"USD"),
+        ),
+        (
+            pyth_price_pubkey,
+            mock_oracle::pyth_price_data(&pyth_product_pubkey, &pyth_price),
+        ),
+        (
+            switchboard_feed_pubkey,
+            mock_oracle::switchboard_feed_data(&switchboard_price),
+        ),
+    ] {
+        test.add_account(
+            pubkey,
+            Account {
+                lamports: u32::MAX as u64,
+                data,
+                owner: oracle_program_id.pubkey(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+    }
+
+    let price = Decimal::from(u64::try_from(pyth_price.price).unwrap_or(0));
+    let scale = 10u64.pow(pyth_price.expo.unsigned_abs());
+    let price = if pyth_price.expo >= 0 {
+        price.try_mul(scale).unwrap()
+    } else {
+        price.try_div(scale).unwrap()
+    };
+
+    TestOracle {
+        pyth_product_pubkey,
+        pyth_price_pubkey,
+        switchboard_feed_pubkey,
+        price,
+    }
+}
+
+impl TestOracle {
+    pub async fn set_pyth_price(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        price: MockPythPrice,
+    ) {
+        let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json")
+            .unwrap()
+            .pubkey();
+        let mut transaction = Transaction::new_with_payer(
+            &[mock_oracle::set_pyth_price(
+                oracle_program_id,
+                self.pyth_price_pubkey,
+                price,
+            )],
+            Some(&payer.pubkey()),
+        );
+        let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+        transaction.sign(&[payer], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+    }
+
+    pub async fn set_switchboard_price(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        price: MockSwitchboardPrice,
+    ) {
+        let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json")
+            .unwrap()
+            .pubkey();
+        let mut transaction = Transaction::new_with_payer(
+            &[mock_oracle::set_switchboard_price(
+                oracle_program_id,
+                self.switchboard_feed_pubkey,
+                price,
+            )],
+            Some(&payer.pubkey()),
+        );
+        let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+        transaction.sign(&[payer], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+    }
+}
+
 pub async fn create_and_mint_to_token_account(
     banks_client: &mut BanksClient,
     mint_pubkey: Pubkey,
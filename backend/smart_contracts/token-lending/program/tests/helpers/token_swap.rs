@@ -0,0 +1,123 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_program_test::{processor, ProgramTest};
+use spl_token_lending::token_swap::SWAP_TAG;
+use std::convert::TryInto;
+
+use super::add_account_for_program;
+
+/// Mock of the spl-token-swap `Swap` instruction which always trades at the minimum amount out.
+///
+/// Pool liquidity is owned by the program derived address of `[swap_pubkey]`.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (tag, rest) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if *tag != SWAP_TAG || rest.len() != 16 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount_in = u64::from_le_bytes(rest[..8].try_into().unwrap());
+    let minimum_amount_out = u64::from_le_bytes(rest[8..].try_into().unwrap());
+
+    let account_info_iter = &mut accounts.iter();
+    let swap_info = next_account_info(account_info_iter)?;
+    let swap_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let source_info = next_account_info(account_info_iter)?;
+    let swap_source_info = next_account_info(account_info_iter)?;
+    let swap_destination_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let _pool_mint_info = next_account_info(account_info_iter)?;
+    let _pool_fee_info = next_account_info(account_info_iter)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let (swap_authority, bump_seed) =
+        Pubkey::find_program_address(&[swap_info.key.as_ref()], program_id);
+    if &swap_authority != swap_authority_info.key {
+        msg!("Swap authority does not match the swap authority provided");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &spl_token::instruction::transfer(
+            token_program_id.key,
+            source_info.key,
+            swap_source_info.key,
+            user_transfer_authority_info.key,
+            &[],
+            amount_in,
+        )?,
+        &[
+            source_info.clone(),
+            swap_source_info.clone(),
+            user_transfer_authority_info.clone(),
+            token_program_id.clone(),
+        ],
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program_id.key,
+            swap_destination_info.key,
+            destination_info.key,
+            swap_authority_info.key,
+            &[],
+            minimum_amount_out,
+        )?,
+        &[
+            swap_destination_info.clone(),
+            destination_info.clone(),
+            swap_authority_info.clone(),
+            token_program_id.clone(),
+        ],
+        &[&[swap_info.key.as_ref(), &[bump_seed]]],
+    )
+}
+
+pub struct TestSwap {
+    pub program_id: Pubkey,
+    pub pubkey: Pubkey,
+    pub authority: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+}
+
+pub fn add_swap(
+    test: &mut ProgramTest,
+    source_mint_pubkey: &Pubkey,
+    destination_mint_pubkey: &Pubkey,
+    destination_amount: u64,
+) -> TestSwap {
+    let program_id = Pubkey::new_unique();
+    test.add_program(
+        "mock_token_swap",
+        program_id,
+        processor!(process_instruction),
+    );
+
+    let pubkey = Pubkey::new_unique();
+    let (authority, _bump_seed) = Pubkey::find_program_address(&[pubkey.as_ref()], &program_id);
+
+    TestSwap {
+        program_id,
+        pubkey,
+        authority,
+        source: add_account_for_program(test, &authority, 0, source_mint_pubkey),
+        destination: add_account_for_program(
+            test,
+            &authority,
+            destination_amount,
+            destination_mint_pubkey,
+        ),
+    }
+}
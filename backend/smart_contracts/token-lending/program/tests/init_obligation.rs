@@ -69,6 +69,7 @@ async fn test_already_initialized() {
             usdc_obligation.pubkey,
             lending_market.pubkey,
             user_accounts_owner.pubkey(),
+            None,
         )],
         Some(&payer.pubkey()),
     );
@@ -418,6 +418,13 @@ async fn test_update_reserve_config() {
         deposit_limit: 1_000_000,
         borrow_limit: 300_000,
         fee_receiver: Keypair::new().pubkey(),
+        isolated: false,
+        borrow_only: false,
+        added_borrow_weight_bps: 0,
+        emode_group: 0,
+        max_liquidation_bonus: 20,
+        fixed_rate_premium: 2,
+        max_fixed_term_slots: 216_000,
     };
 
     let (mut banks_client, payer, recent_blockhash) = test.start().await;
@@ -434,57 +441,6 @@ async fn test_update_reserve_config() {
         )],
         Some(&payer.pubkey()),
     );
-    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
-    assert!(banks_client.process_transaction(transaction).await.is_ok());
-
-    let updated_reserve = test_reserve.get_state(&mut banks_client).await;
-    assert_eq!(updated_reserve.config, new_config);
-}
-
-#[tokio::test]
-async fn test_update_invalid_oracle_config() {
-    let mut test = ProgramTest::new(
-        "spl_token_lending",
-        spl_token_lending::id(),
-        processor!(process_instruction),
-    );
-
-    let user_accounts_owner = Keypair::new();
-    let lending_market = add_lending_market(&mut test);
-    let config = test_reserve_config();
-    let mint = add_usdc_mint(&mut test);
-    let oracle = add_usdc_oracle(&mut test);
-    let test_reserve = add_reserve(
-        &mut test,
-        &lending_market,
-        &oracle,
-        &user_accounts_owner,
-        AddReserveArgs {
-            liquidity_amount: 42,
-            liquidity_mint_decimals: mint.decimals,
-            liquidity_mint_pubkey: mint.pubkey,
-            config: config,
-            ..AddReserveArgs::default()
-        },
-    );
-
-    let (mut banks_client, payer, recent_blockhash) = test.start().await;
-
-    // Try setting both of the oracles to null: Should fail
-    let mut transaction = Transaction::new_with_payer(
-        &[update_reserve_config(
-            spl_token_lending::id(),
-            config,
-            test_reserve.pubkey,
-            lending_market.pubkey,
-            lending_market.owner.pubkey(),
-            spl_token_lending::NULL_PUBKEY,
-            spl_token_lending::NULL_PUBKEY,
-            spl_token_lending::NULL_PUBKEY,
-        )],
-        Some(&payer.pubkey()),
-    );
-
     transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
     assert_eq!(
         banks_client
@@ -494,63 +450,33 @@ async fn test_update_invalid_oracle_config() {
             .unwrap(),
         TransactionError::InstructionError(
             0,
-            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+            InstructionError::Custom(LendingError::ReserveConfigChangeNotAllowed as u32)
         )
     );
 
-    // Set one of the oracles to null
+    // Lowering the loan to value ratio and limits applies immediately
+    let new_config = ReserveConfig {
+        loan_to_value_ratio: new_config.loan_to_value_ratio,
+        deposit_limit: new_config.deposit_limit,
+        borrow_limit: new_config.borrow_limit,
+        ..test_reserve.config
+    };
     let mut transaction = Transaction::new_with_payer(
         &[update_reserve_config(
             spl_token_lending::id(),
-            config,
+            new_config,
             test_reserve.pubkey,
             lending_market.pubkey,
             lending_market.owner.pubkey(),
             oracle.pyth_product_pubkey,
             oracle.pyth_price_pubkey,
-            spl_token_lending::NULL_PUBKEY,
+            oracle.switchboard_feed_pubkey,
         )],
         Some(&payer.pubkey()),
     );
     transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
     assert!(banks_client.process_transaction(transaction).await.is_ok());
-    let updated_reserve = test_reserve.get_state(&mut banks_client).await;
-    assert_eq!(updated_reserve.config, config);
-    assert_eq!(
-        updated_reserve.liquidity.pyth_oracle_pubkey,
-        oracle.pyth_price_pubkey
-    );
-    assert_eq!(
-        updated_reserve.liquidity.switchboard_oracle_pubkey,
-        spl_token_lending::NULL_PUBKEY
-    );
 
-    // Setting both oracles to null still fails, even if one is
-    // already null
-    let mut transaction = Transaction::new_with_payer(
-        &[update_reserve_config(
-            spl_token_lending::id(),
-            config,
-            test_reserve.pubkey,
-            lending_market.pubkey,
-            lending_market.owner.pubkey(),
-            spl_token_lending::NULL_PUBKEY,
-            spl_token_lending::NULL_PUBKEY,
-            spl_token_lending::NULL_PUBKEY,
-        )],
-        Some(&payer.pubkey()),
-    );
-
-    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
-    assert_eq!(
-        banks_client
-            .process_transaction(transaction)
-            .await
-            .unwrap_err()
-            .unwrap(),
-        TransactionError::InstructionError(
-            0,
-            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
-        )
-    );
+    let updated_reserve = test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(updated_reserve.config, new_config);
 }
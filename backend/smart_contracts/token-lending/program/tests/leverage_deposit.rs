@@ -0,0 +1,283 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{token_swap::*, *};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{leverage_deposit, refresh_obligation},
+    math::Decimal,
+    processor::process_instruction,
+    stake_pool,
+    state::{FeeCalculation, INITIAL_COLLATERAL_RATIO},
+};
+
+const SOL_RESERVE_LIQUIDITY_LAMPORTS: u64 = 10 * LAMPORTS_TO_SOL;
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = SOL_RESERVE_LIQUIDITY_LAMPORTS * INITIAL_COLLATERAL_RATIO;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10_000 * FRACTIONAL_TO_USDC;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 50 * FRACTIONAL_TO_USDC;
+const SOL_SWAP_OUT_LAMPORTS: u64 = 5 * LAMPORTS_TO_SOL / 2;
+
+struct LeverageTest {
+    market: TestSolUsdcMarket,
+    swap: TestSwap,
+    sol_reserve_oracle_pubkeys: Vec<Pubkey>,
+}
+
+fn setup(test: &mut ProgramTest, stake_pool_priced: bool) -> LeverageTest {
+    let sol_mint_pubkey = Pubkey::new_unique();
+    // the SOL reserve can be priced as the pool token of a stake pool, which RefreshReserve
+    // expects as an additional oracle account
+    let sol_reserve_oracle_pubkeys = if stake_pool_priced {
+        vec![add_stake_pool(test, stake_pool::id(), sol_mint_pubkey, 0)]
+    } else {
+        vec![]
+    };
+    let market = add_sol_usdc_market(
+        test,
+        AddSolUsdcMarketArgs {
+            sol_reserve: AddReserveArgs {
+                liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+                collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                liquidity_mint_pubkey: sol_mint_pubkey,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                stake_pool_oracle_pubkey: sol_reserve_oracle_pubkeys
+                    .first()
+                    .copied()
+                    .unwrap_or_default(),
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+
+    let swap = add_swap(
+        test,
+        &market.usdc_test_reserve.liquidity_mint_pubkey,
+        &sol_mint_pubkey,
+        SOL_RESERVE_LIQUIDITY_LAMPORTS,
+    );
+
+    LeverageTest {
+        market,
+        swap,
+        sol_reserve_oracle_pubkeys,
+    }
+}
+
+fn leverage_deposit_through_swap(test: &LeverageTest, target_ltv: u8) -> Instruction {
+    leverage_deposit(
+        spl_token_lending::id(),
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        SOL_SWAP_OUT_LAMPORTS,
+        target_ltv,
+        test.market.test_obligation.pubkey,
+        test.market.usdc_test_reserve.pubkey,
+        test.market.usdc_test_reserve.liquidity_supply_pubkey,
+        test.market.usdc_test_reserve.config.fee_receiver,
+        test.market.usdc_test_reserve.user_liquidity_pubkey,
+        test.market.sol_test_reserve.pubkey,
+        test.market.sol_test_reserve.liquidity_supply_pubkey,
+        test.market.sol_test_reserve.collateral_mint_pubkey,
+        test.market.sol_test_reserve.collateral_supply_pubkey,
+        test.market.sol_test_reserve.liquidity_pyth_oracle_pubkey,
+        test.market
+            .sol_test_reserve
+            .liquidity_switchboard_oracle_pubkey,
+        test.market.sol_test_reserve.user_liquidity_pubkey,
+        test.market.sol_test_reserve.user_collateral_pubkey,
+        test.market.lending_market.pubkey,
+        test.market.test_obligation.owner,
+        test.swap.program_id,
+        test.swap.pubkey,
+        test.swap.authority,
+        test.swap.source,
+        test.swap.destination,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        None,
+        test.sol_reserve_oracle_pubkeys.clone(),
+        vec![
+            test.market.sol_test_reserve.pubkey,
+            test.market.usdc_test_reserve.pubkey,
+        ],
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let leverage_test = setup(&mut test, false);
+    let user_accounts_owner = &leverage_test.market.user_accounts_owner;
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                leverage_test.market.test_obligation.pubkey,
+                vec![leverage_test.market.sol_test_reserve.pubkey],
+            ),
+            leverage_deposit_through_swap(&leverage_test, 30),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let usdc_reserve = leverage_test
+        .market
+        .usdc_test_reserve
+        .get_state(&mut banks_client)
+        .await;
+    let (borrow_fee, _host_fee) = usdc_reserve
+        .config
+        .fees
+        .calculate_borrow_fees(
+            Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL),
+            FeeCalculation::Exclusive,
+        )
+        .unwrap();
+    let borrow_amount = USDC_BORROW_AMOUNT_FRACTIONAL + borrow_fee;
+
+    let obligation = leverage_test
+        .market
+        .test_obligation
+        .get_state(&mut banks_client)
+        .await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS + SOL_SWAP_OUT_LAMPORTS * INITIAL_COLLATERAL_RATIO
+    );
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(borrow_amount)
+    );
+
+    assert_eq!(
+        usdc_reserve.liquidity.available_amount,
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL - borrow_amount
+    );
+    assert_eq!(
+        get_token_balance(&mut banks_client, leverage_test.swap.source).await,
+        USDC_BORROW_AMOUNT_FRACTIONAL
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut banks_client,
+            leverage_test.market.usdc_test_reserve.config.fee_receiver
+        )
+        .await,
+        borrow_fee
+    );
+}
+
+#[tokio::test]
+async fn test_fail_target_exceeded() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let leverage_test = setup(&mut test, false);
+    let user_accounts_owner = &leverage_test.market.user_accounts_owner;
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // borrowing $50 against $250 of deposits is a 20% loan to value
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                leverage_test.market.test_obligation.pubkey,
+                vec![leverage_test.market.sol_test_reserve.pubkey],
+            ),
+            leverage_deposit_through_swap(&leverage_test, 10),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::LeverageTargetExceeded as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_success_stake_pool_oracle() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let leverage_test = setup(&mut test, true);
+    let user_accounts_owner = &leverage_test.market.user_accounts_owner;
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // the deposit reserve is refreshed with the stake pool after the deposit
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                leverage_test.market.test_obligation.pubkey,
+                vec![leverage_test.market.sol_test_reserve.pubkey],
+            ),
+            leverage_deposit_through_swap(&leverage_test, 30),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let sol_reserve = leverage_test
+        .market
+        .sol_test_reserve
+        .get_state(&mut banks_client)
+        .await;
+    // each pool token is worth 1.1 SOL at $20
+    assert_eq!(sol_reserve.liquidity.market_price, Decimal::from(22u64));
+
+    let obligation = leverage_test
+        .market
+        .test_obligation
+        .get_state(&mut banks_client)
+        .await;
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(
+        obligation.borrows[0].borrow_reserve,
+        leverage_test.market.usdc_test_reserve.pubkey
+    );
+}
@@ -2,7 +2,10 @@
 
 mod helpers;
 
-use helpers::*;
+use helpers::{
+    mock_oracle::{MockPythPrice, MockSwitchboardPrice},
+    *,
+};
 use solana_program_test::*;
 use solana_sdk::{
     pubkey::Pubkey,
@@ -11,9 +14,11 @@ use solana_sdk::{
 };
 use spl_token::instruction::approve;
 use spl_token_lending::{
-    instruction::{liquidate_obligation, refresh_obligation},
+    instruction::{liquidate_obligation, refresh_obligation, refresh_reserve},
+    math::Decimal,
     processor::process_instruction,
-    state::INITIAL_COLLATERAL_RATIO,
+    pyth::PriceStatus,
+    state::{Obligation, INITIAL_COLLATERAL_RATIO},
 };
 
 #[tokio::test]
@@ -182,3 +187,262 @@ async fn test_success() {
         (USDC_BORROW_AMOUNT_FRACTIONAL - USDC_LIQUIDATION_AMOUNT_FRACTIONAL).into()
     )
 }
+
+// $25 per SOL
+const SOL_PYTH_PRICE: MockPythPrice = MockPythPrice {
+    price: 2_500,
+    expo: -2,
+    conf: 0,
+    status: PriceStatus::Trading,
+    valid_slot: 0,
+};
+
+// $1 per USDC
+const USDC_PYTH_PRICE: MockPythPrice = MockPythPrice {
+    price: 100,
+    expo: -2,
+    conf: 0,
+    status: PriceStatus::Trading,
+    valid_slot: 0,
+};
+
+const SWITCHBOARD_PRICE: MockSwitchboardPrice = MockSwitchboardPrice {
+    price: 0.0,
+    round_open_slot: 0,
+};
+
+struct LiquidationResult {
+    repay_amount: u64,
+    withdraw_amount: u64,
+    obligation: Obligation,
+}
+
+/// Open a SOL collateral, USDC borrow obligation at $25 per SOL, move the SOL price with the
+/// mock oracle and liquidate as much of the borrow as allowed
+async fn liquidate_after_price_drop(
+    sol_deposit_lamports: u64,
+    usdc_borrow_fractional: u64,
+    sol_price_cents: i64,
+) -> LiquidationResult {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+    add_mock_oracle_program(&mut test);
+
+    let user_accounts_owner = Keypair::new();
+    let user_transfer_authority = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let mut reserve_config = test_reserve_config();
+    reserve_config.loan_to_value_ratio = 40;
+    reserve_config.liquidation_threshold = 50;
+    reserve_config.liquidation_bonus = 5;
+    reserve_config.max_liquidation_bonus = 20;
+
+    let sol_oracle = add_mock_oracle(&mut test, SOL_PYTH_PRICE, SWITCHBOARD_PRICE);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: 2 * sol_deposit_lamports,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_mock_oracle(&mut test, USDC_PYTH_PRICE, SWITCHBOARD_PRICE);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: usdc_borrow_fractional,
+            user_liquidity_amount: usdc_borrow_fractional,
+            liquidity_amount: 2 * usdc_borrow_fractional,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, sol_deposit_lamports)],
+            borrows: &[(&usdc_test_reserve, usdc_borrow_fractional)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, _recent_blockhash) = test.start().await;
+
+    sol_oracle
+        .set_pyth_price(
+            &mut banks_client,
+            &payer,
+            MockPythPrice {
+                price: sol_price_cents,
+                ..SOL_PYTH_PRICE
+            },
+        )
+        .await;
+
+    let initial_user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+    let initial_user_collateral_balance =
+        get_token_balance(&mut banks_client, sol_test_reserve.user_collateral_pubkey).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            approve(
+                &spl_token::id(),
+                &usdc_test_reserve.user_liquidity_pubkey,
+                &user_transfer_authority.pubkey(),
+                &user_accounts_owner.pubkey(),
+                &[],
+                usdc_borrow_fractional,
+            )
+            .unwrap(),
+            refresh_reserve(
+                spl_token_lending::id(),
+                sol_test_reserve.pubkey,
+                sol_oracle.pyth_price_pubkey,
+                sol_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_reserve(
+                spl_token_lending::id(),
+                usdc_test_reserve.pubkey,
+                usdc_oracle.pyth_price_pubkey,
+                usdc_oracle.switchboard_feed_pubkey,
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            liquidate_obligation(
+                spl_token_lending::id(),
+                u64::MAX,
+                usdc_test_reserve.user_liquidity_pubkey,
+                sol_test_reserve.user_collateral_pubkey,
+                usdc_test_reserve.pubkey,
+                usdc_test_reserve.liquidity_supply_pubkey,
+                sol_test_reserve.pubkey,
+                sol_test_reserve.collateral_supply_pubkey,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                user_transfer_authority.pubkey(),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &user_transfer_authority],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let user_liquidity_balance =
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await;
+    let user_collateral_balance =
+        get_token_balance(&mut banks_client, sol_test_reserve.user_collateral_pubkey).await;
+
+    LiquidationResult {
+        repay_amount: initial_user_liquidity_balance - user_liquidity_balance,
+        withdraw_amount: user_collateral_balance - initial_user_collateral_balance,
+        obligation: test_obligation.get_state(&mut banks_client).await,
+    }
+}
+
+#[tokio::test]
+async fn test_partial_close_factor() {
+    // 100 SOL * $20 * 50% threshold -> $1000 unhealthy, $1020 borrowed is 2% over
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_020 * FRACTIONAL_TO_USDC;
+    // 1020 USDC * 50% close factor -> 510 USDC repaid
+    const USDC_LIQUIDATION_AMOUNT_FRACTIONAL: u64 = 510 * FRACTIONAL_TO_USDC;
+    // $510 * (5% + 2% bonus) -> $545.70 / $20 per SOL -> 27.285 SOL
+    const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 27_285_000_000 * INITIAL_COLLATERAL_RATIO;
+
+    let result = liquidate_after_price_drop(
+        SOL_DEPOSIT_AMOUNT_LAMPORTS,
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        2_000,
+    )
+    .await;
+
+    assert_eq!(result.repay_amount, USDC_LIQUIDATION_AMOUNT_FRACTIONAL);
+    assert_eq!(result.withdraw_amount, SOL_LIQUIDATION_AMOUNT_LAMPORTS);
+    assert_eq!(
+        result.obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        result.obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL - USDC_LIQUIDATION_AMOUNT_FRACTIONAL)
+    );
+}
+
+#[tokio::test]
+async fn test_full_liquidation_above_threshold() {
+    // 100 SOL * $16 * 50% threshold -> $800 unhealthy, $1020 borrowed is 27.5% over
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_020 * FRACTIONAL_TO_USDC;
+    // $1020 * 20% max bonus -> $1224 / $16 per SOL -> 76.5 SOL
+    const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 76_500_000_000 * INITIAL_COLLATERAL_RATIO;
+
+    let result = liquidate_after_price_drop(
+        SOL_DEPOSIT_AMOUNT_LAMPORTS,
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        1_600,
+    )
+    .await;
+
+    assert_eq!(result.repay_amount, USDC_BORROW_AMOUNT_FRACTIONAL);
+    assert_eq!(result.withdraw_amount, SOL_LIQUIDATION_AMOUNT_LAMPORTS);
+    assert_eq!(
+        result.obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+    assert!(result.obligation.borrows.is_empty());
+}
+
+#[tokio::test]
+async fn test_dust_close_out() {
+    // 0.05 SOL * $20 * 50% threshold -> $0.50 unhealthy, $0.51 borrowed is only 2% over but
+    // below the dust value, so it is closed out in full
+    const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 50_000_000 * INITIAL_COLLATERAL_RATIO;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 510_000;
+    // $0.51 * (5% + 2% bonus) -> $0.5457 / $20 per SOL -> 0.027285 SOL
+    const SOL_LIQUIDATION_AMOUNT_LAMPORTS: u64 = 27_285_000 * INITIAL_COLLATERAL_RATIO;
+
+    let result = liquidate_after_price_drop(
+        SOL_DEPOSIT_AMOUNT_LAMPORTS,
+        USDC_BORROW_AMOUNT_FRACTIONAL,
+        2_000,
+    )
+    .await;
+
+    assert_eq!(result.repay_amount, USDC_BORROW_AMOUNT_FRACTIONAL);
+    assert_eq!(result.withdraw_amount, SOL_LIQUIDATION_AMOUNT_LAMPORTS);
+    assert_eq!(
+        result.obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_LIQUIDATION_AMOUNT_LAMPORTS
+    );
+    assert!(result.obligation.borrows.is_empty());
+}
@@ -0,0 +1,642 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction::create_account,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        add_market_member, deposit_reserve_liquidity,
+        deposit_reserve_liquidity_and_obligation_collateral, init_obligation, revoke_market_member,
+        set_lending_market_allowlist_admin, set_obligation_owner,
+    },
+    processor::process_instruction,
+    state::{market_member_address, MarketMember, Obligation, PROGRAM_VERSION},
+};
+
+const DEPOSIT_AMOUNT: u64 = 100 * FRACTIONAL_TO_USDC;
+
+fn setup() -> (
+    ProgramTest,
+    TestLendingMarket,
+    TestReserve,
+    Keypair,
+    Keypair,
+) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // the allowlist admin pays the rent for memberships
+    let allowlist_admin = Keypair::new();
+    add_funded_account(&mut test, allowlist_admin.pubkey());
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            user_liquidity_amount: DEPOSIT_AMOUNT,
+            liquidity_amount: 10_000 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    (
+        test,
+        lending_market,
+        usdc_test_reserve,
+        user_accounts_owner,
+        allowlist_admin,
+    )
+}
+
+async fn set_allowlist_admin(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    lending_market: &TestLendingMarket,
+    allowlist_admin: Pubkey,
+) {
+    let mut transaction = Transaction::new_with_payer(
+        &[set_lending_market_allowlist_admin(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            allowlist_admin,
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    transaction.sign(&[payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+}
+
+async fn add_member(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    lending_market: &TestLendingMarket,
+    allowlist_admin: &Keypair,
+    member: Pubkey,
+) -> Pubkey {
+    let mut transaction = Transaction::new_with_payer(
+        &[add_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            member,
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    transaction.sign(&[payer, allowlist_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let (market_member_pubkey, _bump_seed) =
+        market_member_address(&spl_token_lending::id(), &lending_market.pubkey, &member);
+    market_member_pubkey
+}
+
+async fn init_member_obligation(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    lending_market: &TestLendingMarket,
+    obligation_owner: &Keypair,
+    market_member_pubkey: Option<Pubkey>,
+) -> Result<(), TransactionError> {
+    let obligation_keypair = Keypair::new();
+    let rent = banks_client.get_rent().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            create_account(
+                &payer.pubkey(),
+                &obligation_keypair.pubkey(),
+                rent.minimum_balance(Obligation::LEN),
+                Obligation::LEN as u64,
+                &spl_token_lending::id(),
+            ),
+            init_obligation(
+                spl_token_lending::id(),
+                obligation_keypair.pubkey(),
+                lending_market.pubkey,
+                obligation_owner.pubkey(),
+                market_member_pubkey,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    transaction.sign(
+        &[payer, &obligation_keypair, obligation_owner],
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (test, lending_market, usdc_test_reserve, user_accounts_owner, allowlist_admin) = setup();
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+    let market = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(market.allowlist_admin, allowlist_admin.pubkey());
+
+    let (market_member_pubkey, _bump_seed) = market_member_address(
+        &spl_token_lending::id(),
+        &lending_market.pubkey,
+        &user_accounts_owner.pubkey(),
+    );
+
+    // wallets can't use the market before they are added
+    assert_eq!(
+        init_member_obligation(
+            &mut banks_client,
+            &payer,
+            &lending_market,
+            &user_accounts_owner,
+            None
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        ))
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[add_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &allowlist_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let market_member_account = banks_client
+        .get_account(market_member_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let market_member = MarketMember::unpack(&market_member_account.data).unwrap();
+    assert_eq!(market_member.lending_market, lending_market.pubkey);
+    assert_eq!(market_member.member, user_accounts_owner.pubkey());
+
+    assert_eq!(
+        init_member_obligation(
+            &mut banks_client,
+            &payer,
+            &lending_market,
+            &user_accounts_owner,
+            Some(market_member_pubkey)
+        )
+        .await,
+        Ok(())
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            DEPOSIT_AMOUNT,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            Some(market_member_pubkey),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_revoke_market_member() {
+    let (test, lending_market, usdc_test_reserve, user_accounts_owner, allowlist_admin) = setup();
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[add_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &allowlist_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let admin_lamports = banks_client
+        .get_balance(allowlist_admin.pubkey())
+        .await
+        .unwrap();
+    let (market_member_pubkey, _bump_seed) = market_member_address(
+        &spl_token_lending::id(),
+        &lending_market.pubkey,
+        &user_accounts_owner.pubkey(),
+    );
+    let market_member_lamports = banks_client
+        .get_balance(market_member_pubkey)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[revoke_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &allowlist_admin], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    assert_eq!(
+        banks_client
+            .get_account(market_member_pubkey)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        banks_client
+            .get_balance(allowlist_admin.pubkey())
+            .await
+            .unwrap(),
+        admin_lamports + market_member_lamports
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            DEPOSIT_AMOUNT,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            Some(market_member_pubkey),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+
+    // removing the allowlist admin opens the market to every wallet
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        Pubkey::default(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            DEPOSIT_AMOUNT,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_invalid_allowlist_admin() {
+    let (test, lending_market, _usdc_test_reserve, user_accounts_owner, allowlist_admin) = setup();
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // memberships can't be issued before the market has an allowlist admin
+    let mut transaction = Transaction::new_with_payer(
+        &[add_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            allowlist_admin.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &allowlist_admin], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAllowlistAdmin as u32)
+        )
+    );
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[add_market_member(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            user_accounts_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAllowlistAdmin as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_obligation_collateral() {
+    let (mut test, lending_market, usdc_test_reserve, user_accounts_owner, allowlist_admin) =
+        setup();
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+
+    let deposit = |market_member_pubkey| {
+        deposit_reserve_liquidity_and_obligation_collateral(
+            spl_token_lending::id(),
+            DEPOSIT_AMOUNT,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            usdc_test_reserve.collateral_supply_pubkey,
+            test_obligation.pubkey,
+            test_obligation.owner,
+            usdc_test_reserve.liquidity_pyth_oracle_pubkey,
+            usdc_test_reserve.liquidity_switchboard_oracle_pubkey,
+            user_accounts_owner.pubkey(),
+            market_member_pubkey,
+        )
+    };
+
+    let mut transaction = Transaction::new_with_payer(&[deposit(None)], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+
+    let market_member_pubkey = add_member(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        &allowlist_admin,
+        user_accounts_owner.pubkey(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit(Some(market_member_pubkey))],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.deposits.len(), 1);
+}
+
+#[tokio::test]
+async fn test_set_obligation_owner() {
+    let (mut test, lending_market, _usdc_test_reserve, user_accounts_owner, allowlist_admin) =
+        setup();
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+
+    // obligations can't be transferred to wallets outside the allowlist
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            false,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            new_owner.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+
+    let market_member_pubkey = add_member(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        &allowlist_admin,
+        new_owner.pubkey(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            false,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            new_owner.pubkey(),
+            Some(market_member_pubkey),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.owner, new_owner.pubkey());
+}
+
+#[tokio::test]
+async fn test_invalid_market_member_address() {
+    let (mut test, lending_market, usdc_test_reserve, user_accounts_owner, allowlist_admin) =
+        setup();
+
+    // a membership stored outside the derived address isn't accepted
+    let market_member_pubkey = Pubkey::new_unique();
+    let mut market_member_data = vec![0; MarketMember::LEN];
+    MarketMember::pack(
+        MarketMember {
+            version: PROGRAM_VERSION,
+            lending_market: lending_market.pubkey,
+            member: user_accounts_owner.pubkey(),
+        },
+        &mut market_member_data,
+    )
+    .unwrap();
+    test.add_account(
+        market_member_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data: market_member_data,
+            owner: spl_token_lending::id(),
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    set_allowlist_admin(
+        &mut banks_client,
+        &payer,
+        &lending_market,
+        allowlist_admin.pubkey(),
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[deposit_reserve_liquidity(
+            spl_token_lending::id(),
+            DEPOSIT_AMOUNT,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.user_collateral_pubkey,
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            usdc_test_reserve.collateral_mint_pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            Some(market_member_pubkey),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::NotMarketMember as u32)
+        )
+    );
+}
@@ -121,6 +121,7 @@ async fn test_success() {
                 obligation_pubkey,
                 lending_market.pubkey,
                 user_accounts_owner_pubkey,
+                None,
             ),
             // 2
             refresh_reserve(
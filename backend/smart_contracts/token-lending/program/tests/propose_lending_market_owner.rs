@@ -0,0 +1,321 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{accept_lending_market_owner, propose_lending_market_owner},
+    processor::process_instruction,
+    state::{InitLendingMarketParams, LendingMarket},
+};
+
+// length of lending markets created before pending owners were stored
+const LEGACY_LENDING_MARKET_LEN: usize = 290;
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // the current owner keeps the market until the new owner accepts
+    let lending_market_info = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(lending_market_info.owner, lending_market.owner.pubkey());
+    assert_eq!(lending_market_info.pending_owner, new_owner.pubkey());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[accept_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let lending_market_info = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(lending_market_info.owner, new_owner.pubkey());
+    assert_eq!(lending_market_info.pending_owner, Pubkey::default());
+}
+
+#[tokio::test]
+async fn test_legacy_lending_market() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market_owner =
+        read_keypair_file("tests/fixtures/lending_market_owner.json").unwrap();
+    test.add_account(
+        lending_market_owner.pubkey(),
+        Account {
+            lamports: LAMPORTS_TO_SOL,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let lending_market_pubkey = Pubkey::new_unique();
+    let mut legacy_lending_market = Account::new(
+        Rent::default().minimum_balance(LEGACY_LENDING_MARKET_LEN),
+        LEGACY_LENDING_MARKET_LEN,
+        &spl_token_lending::id(),
+    );
+    LendingMarket::pack(
+        LendingMarket::new(InitLendingMarketParams {
+            bump_seed: 0,
+            owner: lending_market_owner.pubkey(),
+            quote_currency: QUOTE_CURRENCY,
+            token_program_id: spl_token::id(),
+            oracle_program_id: Pubkey::new_unique(),
+            switchboard_oracle_program_id: Pubkey::new_unique(),
+        }),
+        &mut legacy_lending_market.data,
+    )
+    .unwrap();
+    test.add_account(lending_market_pubkey, legacy_lending_market);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let new_owner = Pubkey::new_unique();
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market_pubkey,
+            lending_market_owner.pubkey(),
+            new_owner,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // the lending market is reallocated to hold the pending owner
+    let lending_market_account = banks_client
+        .get_account(lending_market_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(lending_market_account.data.len(), LendingMarket::LEN);
+    assert!(Rent::default().is_exempt(
+        lending_market_account.lamports,
+        lending_market_account.data.len()
+    ));
+    let lending_market_info = LendingMarket::unpack(&lending_market_account.data).unwrap();
+    assert_eq!(lending_market_info.owner, lending_market_owner.pubkey());
+    assert_eq!(lending_market_info.pending_owner, new_owner);
+}
+
+#[tokio::test]
+async fn test_invalid_pending_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // nothing to accept before an owner is proposed
+    let new_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[accept_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[accept_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+
+    // proposing the default pubkey cancels the transfer
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            Pubkey::default(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[accept_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            new_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &new_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_owner(
+            spl_token_lending::id(),
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+            invalid_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_set_lending_market_owner_unused() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let lending_market = add_lending_market(&mut test);
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // the one step SetLendingMarketOwner instruction is no longer accepted
+    let new_owner = Pubkey::new_unique();
+    let mut data = vec![1];
+    data.extend_from_slice(new_owner.as_ref());
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction {
+            program_id: spl_token_lending::id(),
+            accounts: vec![
+                AccountMeta::new(lending_market.pubkey, false),
+                AccountMeta::new_readonly(lending_market.owner.pubkey(), true),
+            ],
+            data,
+        }],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InstructionUnpackError as u32)
+        )
+    );
+
+    let lending_market_info = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(lending_market_info.owner, lending_market.owner.pubkey());
+}
@@ -0,0 +1,369 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{apply_reserve_config, propose_reserve_config},
+    processor::process_instruction,
+    state::{
+        pending_reserve_config_address, PendingReserveConfig, ReserveConfig,
+        RESERVE_CONFIG_DELAY_SLOTS,
+    },
+};
+
+const PROPOSAL_SLOT: u64 = 10;
+
+fn setup() -> (
+    ProgramTest,
+    TestLendingMarket,
+    TestReserve,
+    TestOracle,
+    TestOracle,
+) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    // the lending market owner pays the rent for the pending config
+    add_funded_account(&mut test, lending_market.owner.pubkey());
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let sol_oracle = add_sol_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 42,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            config: test_reserve_config(),
+            ..AddReserveArgs::default()
+        },
+    );
+
+    (
+        test,
+        lending_market,
+        usdc_test_reserve,
+        usdc_oracle,
+        sol_oracle,
+    )
+}
+
+async fn get_pending_reserve_config(
+    banks_client: &mut BanksClient,
+    reserve: &TestReserve,
+) -> Option<PendingReserveConfig> {
+    let (pending_reserve_config_pubkey, _bump_seed) =
+        pending_reserve_config_address(&spl_token_lending::id(), &reserve.pubkey);
+    banks_client
+        .get_account(pending_reserve_config_pubkey)
+        .await
+        .unwrap()
+        .map(|account| PendingReserveConfig::unpack(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (mut test, lending_market, usdc_test_reserve, _usdc_oracle, sol_oracle) = setup();
+
+    // lamports sent to the pending config address don't prevent proposals
+    let (pending_reserve_config_pubkey, _bump_seed) =
+        pending_reserve_config_address(&spl_token_lending::id(), &usdc_test_reserve.pubkey);
+    test.add_account(
+        pending_reserve_config_pubkey,
+        Account {
+            lamports: 1,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(PROPOSAL_SLOT).unwrap();
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+
+    let new_config = ReserveConfig {
+        loan_to_value_ratio: 60,
+        liquidation_threshold: 70,
+        deposit_limit: u64::MAX,
+        ..usdc_test_reserve.config
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_reserve_config(
+            spl_token_lending::id(),
+            new_config,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            sol_oracle.pyth_product_pubkey,
+            sol_oracle.pyth_price_pubkey,
+            sol_oracle.switchboard_feed_pubkey,
+            None,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let pending_reserve_config =
+        get_pending_reserve_config(&mut test_context.banks_client, &usdc_test_reserve)
+            .await
+            .unwrap();
+    assert_eq!(pending_reserve_config.reserve, usdc_test_reserve.pubkey);
+    assert_eq!(pending_reserve_config.config, new_config);
+    assert_eq!(
+        pending_reserve_config.pyth_oracle_pubkey,
+        sol_oracle.pyth_price_pubkey
+    );
+    assert_eq!(
+        pending_reserve_config.activation_slot,
+        PROPOSAL_SLOT + RESERVE_CONFIG_DELAY_SLOTS
+    );
+
+    // the reserve is unchanged until the config is applied
+    let reserve = usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(reserve.config, usdc_test_reserve.config);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ReserveConfigNotActive as u32)
+        )
+    );
+
+    test_context
+        .warp_to_slot(PROPOSAL_SLOT + RESERVE_CONFIG_DELAY_SLOTS)
+        .unwrap();
+    let owner_lamports = test_context
+        .banks_client
+        .get_balance(lending_market.owner.pubkey())
+        .await
+        .unwrap();
+    let pending_reserve_config_lamports = test_context
+        .banks_client
+        .get_balance(pending_reserve_config_pubkey)
+        .await
+        .unwrap();
+
+    // anyone can apply the config once it is active
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = test_context
+        .banks_client
+        .get_recent_blockhash()
+        .await
+        .unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let reserve = usdc_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(reserve.config, new_config);
+    assert_eq!(
+        reserve.liquidity.pyth_oracle_pubkey,
+        sol_oracle.pyth_price_pubkey
+    );
+    assert_eq!(
+        reserve.liquidity.switchboard_oracle_pubkey,
+        sol_oracle.switchboard_feed_pubkey
+    );
+    assert!(reserve.last_update.stale);
+
+    assert_eq!(
+        get_pending_reserve_config(&mut test_context.banks_client, &usdc_test_reserve).await,
+        None
+    );
+    assert_eq!(
+        test_context
+            .banks_client
+            .get_balance(lending_market.owner.pubkey())
+            .await
+            .unwrap(),
+        owner_lamports + pending_reserve_config_lamports
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_oracle_config() {
+    let (test, lending_market, usdc_test_reserve, usdc_oracle, _sol_oracle) = setup();
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // Try setting both of the oracles to null: Should fail
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.config,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            spl_token_lending::NULL_PUBKEY,
+            spl_token_lending::NULL_PUBKEY,
+            spl_token_lending::NULL_PUBKEY,
+            None,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+
+    // Set one of the oracles to null
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.config,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            usdc_oracle.pyth_product_pubkey,
+            usdc_oracle.pyth_price_pubkey,
+            spl_token_lending::NULL_PUBKEY,
+            None,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let pending_reserve_config = get_pending_reserve_config(&mut banks_client, &usdc_test_reserve)
+        .await
+        .unwrap();
+    assert_eq!(
+        pending_reserve_config.pyth_oracle_pubkey,
+        usdc_oracle.pyth_price_pubkey
+    );
+    assert_eq!(
+        pending_reserve_config.switchboard_oracle_pubkey,
+        spl_token_lending::NULL_PUBKEY
+    );
+
+    // Oracle changes can't skip the delay
+    let mut transaction = Transaction::new_with_payer(
+        &[spl_token_lending::instruction::update_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.config,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+            usdc_oracle.pyth_product_pubkey,
+            usdc_oracle.pyth_price_pubkey,
+            spl_token_lending::NULL_PUBKEY,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ReserveConfigChangeNotAllowed as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_owner() {
+    let (test, lending_market, usdc_test_reserve, usdc_oracle, _sol_oracle) = setup();
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_reserve_config(
+            spl_token_lending::id(),
+            usdc_test_reserve.config,
+            usdc_test_reserve.pubkey,
+            lending_market.pubkey,
+            payer.pubkey(),
+            usdc_oracle.pyth_product_pubkey,
+            usdc_oracle.pyth_price_pubkey,
+            usdc_oracle.switchboard_feed_pubkey,
+            None,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
@@ -0,0 +1,217 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{
+    mock_oracle::{MockPythPrice, MockSwitchboardPrice},
+    *,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError, instruction::refresh_reserve, math::Decimal,
+    processor::process_instruction, pyth::PriceStatus,
+};
+
+const CURRENT_SLOT: u64 = 100;
+
+// $20 with a confidence of $0.10
+const PYTH_PRICE: MockPythPrice = MockPythPrice {
+    price: 2_000,
+    expo: -2,
+    conf: 10,
+    status: PriceStatus::Trading,
+    valid_slot: CURRENT_SLOT - 5,
+};
+
+const SWITCHBOARD_PRICE: MockSwitchboardPrice = MockSwitchboardPrice {
+    price: 30.0,
+    round_open_slot: CURRENT_SLOT - 5,
+};
+
+async fn refresh_with_prices(
+    pyth_price: MockPythPrice,
+    switchboard_price: MockSwitchboardPrice,
+) -> Result<Decimal, TransactionError> {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+    add_mock_oracle_program(&mut test);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let oracle = add_mock_oracle(&mut test, PYTH_PRICE, SWITCHBOARD_PRICE);
+    let test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 100 * LAMPORTS_TO_SOL,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: test_reserve_config(),
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(CURRENT_SLOT).unwrap();
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        ..
+    } = test_context;
+
+    oracle
+        .set_pyth_price(&mut banks_client, &payer, pyth_price)
+        .await;
+    oracle
+        .set_switchboard_price(&mut banks_client, &payer, switchboard_price)
+        .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserve(
+            spl_token_lending::id(),
+            test_reserve.pubkey,
+            oracle.pyth_price_pubkey,
+            oracle.switchboard_feed_pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())?;
+
+    let reserve = test_reserve.get_state(&mut banks_client).await;
+    Ok(reserve.liquidity.market_price)
+}
+
+#[tokio::test]
+async fn test_pyth_price() {
+    assert_eq!(
+        refresh_with_prices(PYTH_PRICE, SWITCHBOARD_PRICE).await,
+        Ok(Decimal::from(20u64))
+    );
+
+    // positive exponents scale the price up
+    assert_eq!(
+        refresh_with_prices(
+            MockPythPrice {
+                price: 2,
+                expo: 1,
+                conf: 0,
+                ..PYTH_PRICE
+            },
+            SWITCHBOARD_PRICE
+        )
+        .await,
+        Ok(Decimal::from(20u64))
+    );
+}
+
+#[tokio::test]
+async fn test_stale_pyth_price_falls_back_to_switchboard() {
+    assert_eq!(
+        refresh_with_prices(
+            MockPythPrice {
+                valid_slot: CURRENT_SLOT - 20,
+                ..PYTH_PRICE
+            },
+            SWITCHBOARD_PRICE
+        )
+        .await,
+        Ok(Decimal::from(30u64))
+    );
+
+    // a price published after the current slot is not trusted either
+    assert_eq!(
+        refresh_with_prices(
+            MockPythPrice {
+                valid_slot: CURRENT_SLOT + 1,
+                ..PYTH_PRICE
+            },
+            SWITCHBOARD_PRICE
+        )
+        .await,
+        Ok(Decimal::from(30u64))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_pyth_price_falls_back_to_switchboard() {
+    for pyth_price in [
+        // confidence wider than 10% of the price
+        MockPythPrice {
+            conf: 201,
+            ..PYTH_PRICE
+        },
+        MockPythPrice {
+            status: PriceStatus::Halted,
+            ..PYTH_PRICE
+        },
+        MockPythPrice {
+            price: -2_000,
+            ..PYTH_PRICE
+        },
+        MockPythPrice {
+            price: 0,
+            conf: 0,
+            ..PYTH_PRICE
+        },
+    ] {
+        assert_eq!(
+            refresh_with_prices(pyth_price, SWITCHBOARD_PRICE).await,
+            Ok(Decimal::from(30u64))
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_switchboard_price_below_one() {
+    assert_eq!(
+        refresh_with_prices(
+            MockPythPrice {
+                status: PriceStatus::Unknown,
+                ..PYTH_PRICE
+            },
+            MockSwitchboardPrice {
+                price: 0.25,
+                ..SWITCHBOARD_PRICE
+            }
+        )
+        .await,
+        Ok(Decimal::from_percent(25))
+    );
+}
+
+#[tokio::test]
+async fn test_stale_switchboard_price() {
+    assert_eq!(
+        refresh_with_prices(
+            MockPythPrice {
+                status: PriceStatus::Halted,
+                ..PYTH_PRICE
+            },
+            MockSwitchboardPrice {
+                round_open_slot: CURRENT_SLOT - 100,
+                ..SWITCHBOARD_PRICE
+            }
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        ))
+    );
+}
@@ -0,0 +1,418 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::{token_swap::*, *};
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{refresh_obligation, repay_with_collateral},
+    math::{Decimal, TrySub},
+    processor::process_instruction,
+    state::{EModeGroup, ReserveConfig, INITIAL_COLLATERAL_RATIO},
+};
+
+const SOL_RESERVE_LIQUIDITY_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL;
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = SOL_RESERVE_LIQUIDITY_LAMPORTS * INITIAL_COLLATERAL_RATIO;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10_000 * FRACTIONAL_TO_USDC;
+
+#[allow(clippy::too_many_arguments)]
+fn repay_with_collateral_through_swap(
+    collateral_amount: u64,
+    minimum_liquidity_out: u64,
+    obligation: &TestObligation,
+    sol_reserve: &TestReserve,
+    usdc_reserve: &TestReserve,
+    lending_market: &TestLendingMarket,
+    swap: &TestSwap,
+) -> solana_sdk::instruction::Instruction {
+    repay_with_collateral(
+        spl_token_lending::id(),
+        collateral_amount,
+        minimum_liquidity_out,
+        obligation.pubkey,
+        sol_reserve.pubkey,
+        sol_reserve.collateral_supply_pubkey,
+        sol_reserve.collateral_mint_pubkey,
+        sol_reserve.liquidity_supply_pubkey,
+        sol_reserve.user_collateral_pubkey,
+        sol_reserve.user_liquidity_pubkey,
+        usdc_reserve.pubkey,
+        usdc_reserve.liquidity_supply_pubkey,
+        usdc_reserve.user_liquidity_pubkey,
+        lending_market.pubkey,
+        obligation.owner,
+        swap.program_id,
+        swap.pubkey,
+        swap.authority,
+        swap.source,
+        swap.destination,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 500 * FRACTIONAL_TO_USDC;
+    const SOL_WITHDRAW_AMOUNT_LAMPORTS: u64 = 10 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_SWAP_OUT_FRACTIONAL: u64 = 150 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_mint_pubkey = Pubkey::new_unique();
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: sol_mint_pubkey,
+            liquidity_mint_decimals: 9,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let swap = add_swap(
+        &mut test,
+        &sol_mint_pubkey,
+        &usdc_mint.pubkey,
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            repay_with_collateral_through_swap(
+                SOL_WITHDRAW_AMOUNT_LAMPORTS,
+                USDC_SWAP_OUT_FRACTIONAL,
+                &test_obligation,
+                &sol_test_reserve,
+                &usdc_test_reserve,
+                &lending_market,
+                &swap,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.deposits[0].deposited_amount,
+        SOL_DEPOSIT_AMOUNT_LAMPORTS - SOL_WITHDRAW_AMOUNT_LAMPORTS
+    );
+    assert_eq!(
+        obligation.borrows[0].borrowed_amount_wads,
+        Decimal::from(USDC_BORROW_AMOUNT_FRACTIONAL - USDC_SWAP_OUT_FRACTIONAL)
+    );
+
+    let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        usdc_reserve.liquidity.borrowed_amount_wads,
+        initial_usdc_reserve
+            .liquidity
+            .borrowed_amount_wads
+            .try_sub(Decimal::from(USDC_SWAP_OUT_FRACTIONAL))
+            .unwrap()
+    );
+    assert_eq!(
+        usdc_reserve.liquidity.available_amount,
+        initial_usdc_reserve.liquidity.available_amount + USDC_SWAP_OUT_FRACTIONAL
+    );
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, swap.source).await,
+        SOL_WITHDRAW_AMOUNT_LAMPORTS / INITIAL_COLLATERAL_RATIO
+    );
+    assert_eq!(
+        get_token_balance(&mut banks_client, usdc_test_reserve.user_liquidity_pubkey).await,
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_fail_health_worsened() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // above the allowed borrow value of $1,000 but below the liquidation threshold
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_050 * FRACTIONAL_TO_USDC;
+    const SOL_WITHDRAW_AMOUNT_LAMPORTS: u64 = 50 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    const USDC_SWAP_OUT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_mint_pubkey = Pubkey::new_unique();
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: sol_mint_pubkey,
+            liquidity_mint_decimals: 9,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let swap = add_swap(
+        &mut test,
+        &sol_mint_pubkey,
+        &usdc_mint.pubkey,
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+            repay_with_collateral_through_swap(
+                SOL_WITHDRAW_AMOUNT_LAMPORTS,
+                USDC_SWAP_OUT_FRACTIONAL,
+                &test_obligation,
+                &sol_test_reserve,
+                &usdc_test_reserve,
+                &lending_market,
+                &swap,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationHealthWorsened as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_fail_emode_health_worsened() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // above the efficiency mode allowed borrow value of $1,800 but below its liquidation threshold
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_850 * FRACTIONAL_TO_USDC;
+    const SOL_WITHDRAW_AMOUNT_LAMPORTS: u64 = 5 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+    // enough to improve health at the reserve loan to value ratio, but not at the group's
+    const USDC_SWAP_OUT_FRACTIONAL: u64 = 75 * FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market_with_emode_groups(
+        &mut test,
+        &[(
+            1,
+            EModeGroup {
+                loan_to_value_ratio: 90,
+                liquidation_threshold: 95,
+            },
+        )],
+    );
+
+    let sol_mint_pubkey = Pubkey::new_unique();
+    let sol_oracle = add_sol_oracle(&mut test);
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: SOL_RESERVE_LIQUIDITY_LAMPORTS,
+            collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            liquidity_mint_pubkey: sol_mint_pubkey,
+            liquidity_mint_decimals: 9,
+            config: ReserveConfig {
+                emode_group: 1,
+                ..test_reserve_config()
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: ReserveConfig {
+                emode_group: 1,
+                ..test_reserve_config()
+            },
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let swap = add_swap(
+        &mut test,
+        &sol_mint_pubkey,
+        &usdc_mint.pubkey,
+        USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![
+                    sol_test_reserve.pubkey,
+                    usdc_test_reserve.pubkey,
+                    lending_market.pubkey,
+                ],
+            ),
+            repay_with_collateral_through_swap(
+                SOL_WITHDRAW_AMOUNT_LAMPORTS,
+                USDC_SWAP_OUT_FRACTIONAL,
+                &test_obligation,
+                &sol_test_reserve,
+                &usdc_test_reserve,
+                &lending_market,
+                &swap,
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ObligationHealthWorsened as u32)
+        )
+    );
+}
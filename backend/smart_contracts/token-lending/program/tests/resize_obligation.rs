@@ -0,0 +1,274 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{deposit_obligation_collateral, refresh_obligation, resize_obligation},
+    processor::process_instruction,
+    solana_program::program_pack::Pack,
+    state::{
+        InitObligationParams, Obligation, ObligationCollateral, MAX_EXTENDED_OBLIGATION_RESERVES,
+        MAX_OBLIGATION_RESERVES,
+    },
+};
+
+const COLLATERAL_AMOUNT: u64 = FRACTIONAL_TO_USDC;
+
+struct ResizeTest {
+    user_accounts_owner: Keypair,
+    lending_market: TestLendingMarket,
+    test_reserves: Vec<TestReserve>,
+    test_obligation: TestObligation,
+}
+
+// an obligation with deposits from MAX_OBLIGATION_RESERVES reserves and one more reserve to deposit
+fn setup(test: &mut ProgramTest) -> ResizeTest {
+    let user_accounts_owner = Keypair::new();
+    test.add_account(
+        user_accounts_owner.pubkey(),
+        Account::new(LAMPORTS_TO_SOL, 0, &system_program::id()),
+    );
+
+    let lending_market = add_lending_market(test);
+    let usdc_oracle = add_usdc_oracle(test);
+    let test_reserves: Vec<TestReserve> = (0..=MAX_OBLIGATION_RESERVES)
+        .map(|_| {
+            add_reserve(
+                test,
+                &lending_market,
+                &usdc_oracle,
+                &user_accounts_owner,
+                AddReserveArgs {
+                    liquidity_amount: COLLATERAL_AMOUNT,
+                    collateral_amount: COLLATERAL_AMOUNT,
+                    liquidity_mint_pubkey: Pubkey::new_unique(),
+                    liquidity_mint_decimals: 6,
+                    config: test_reserve_config(),
+                    mark_fresh: true,
+                    ..AddReserveArgs::default()
+                },
+            )
+        })
+        .collect();
+
+    let deposits: Vec<(&TestReserve, u64)> = test_reserves[..MAX_OBLIGATION_RESERVES]
+        .iter()
+        .map(|reserve| (reserve, COLLATERAL_AMOUNT))
+        .collect();
+    let test_obligation = add_obligation(
+        test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &deposits,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    ResizeTest {
+        user_accounts_owner,
+        lending_market,
+        test_reserves,
+        test_obligation,
+    }
+}
+
+fn deposit_and_refresh(test: &ResizeTest) -> [Instruction; 2] {
+    let reserve = &test.test_reserves[MAX_OBLIGATION_RESERVES];
+    [
+        deposit_obligation_collateral(
+            spl_token_lending::id(),
+            COLLATERAL_AMOUNT,
+            reserve.user_collateral_pubkey,
+            reserve.collateral_supply_pubkey,
+            reserve.pubkey,
+            test.test_obligation.pubkey,
+            test.lending_market.pubkey,
+            test.user_accounts_owner.pubkey(),
+            test.user_accounts_owner.pubkey(),
+        ),
+        refresh_obligation(
+            spl_token_lending::id(),
+            test.test_obligation.pubkey,
+            test.test_reserves
+                .iter()
+                .map(|reserve| reserve.pubkey)
+                .collect(),
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase of refreshing more than MAX_OBLIGATION_RESERVES
+    test.set_bpf_compute_max_units(120_000);
+
+    let resize_test = setup(&mut test);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut instructions = vec![resize_obligation(
+        spl_token_lending::id(),
+        MAX_OBLIGATION_RESERVES as u8 + 2,
+        resize_test.test_obligation.pubkey,
+        resize_test.lending_market.pubkey,
+        resize_test.user_accounts_owner.pubkey(),
+    )];
+    instructions.extend(deposit_and_refresh(&resize_test));
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &[&payer, &resize_test.user_accounts_owner],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation_account = banks_client
+        .get_account(resize_test.test_obligation.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(obligation_account.data.len(), Obligation::packed_len(2));
+
+    let obligation = resize_test
+        .test_obligation
+        .get_state(&mut banks_client)
+        .await;
+    assert_eq!(obligation.extra_reserves, 2);
+    assert_eq!(obligation.max_reserves(), MAX_OBLIGATION_RESERVES + 2);
+    assert_eq!(obligation.deposits.len(), MAX_OBLIGATION_RESERVES + 1);
+    assert!(!obligation.last_update.stale);
+}
+
+#[tokio::test]
+async fn test_fail_reserve_limit() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let resize_test = setup(&mut test);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction =
+        Transaction::new_with_payer(&deposit_and_refresh(&resize_test), Some(&payer.pubkey()));
+    transaction.sign(
+        &[&payer, &resize_test.user_accounts_owner],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ObligationReserveLimit as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_max_extended_obligation_reserves() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // every reserve of the largest obligation must refresh within the default instruction limit
+    test.set_bpf_compute_max_units(200_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let test_reserves: Vec<TestReserve> = (0..MAX_EXTENDED_OBLIGATION_RESERVES)
+        .map(|_| {
+            add_reserve(
+                &mut test,
+                &lending_market,
+                &usdc_oracle,
+                &user_accounts_owner,
+                AddReserveArgs {
+                    liquidity_amount: COLLATERAL_AMOUNT,
+                    collateral_amount: COLLATERAL_AMOUNT,
+                    liquidity_mint_pubkey: Pubkey::new_unique(),
+                    liquidity_mint_decimals: 6,
+                    config: test_reserve_config(),
+                    mark_fresh: true,
+                    ..AddReserveArgs::default()
+                },
+            )
+        })
+        .collect();
+
+    let mut obligation = Obligation::new(InitObligationParams {
+        current_slot: 1,
+        lending_market: lending_market.pubkey,
+        owner: user_accounts_owner.pubkey(),
+        deposits: test_reserves
+            .iter()
+            .map(|reserve| {
+                let mut collateral = ObligationCollateral::new(reserve.pubkey);
+                collateral.deposited_amount = COLLATERAL_AMOUNT;
+                collateral
+            })
+            .collect(),
+        borrows: vec![],
+    });
+    obligation.extra_reserves = (MAX_EXTENDED_OBLIGATION_RESERVES - MAX_OBLIGATION_RESERVES) as u8;
+    let mut data = vec![0; Obligation::packed_len(obligation.extra_reserves)];
+    obligation.pack_into_slice(&mut data);
+    let obligation_pubkey = Pubkey::new_unique();
+    test.add_account(
+        obligation_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token_lending::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_obligation(
+            spl_token_lending::id(),
+            obligation_pubkey,
+            test_reserves.iter().map(|reserve| reserve.pubkey).collect(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation_account = banks_client
+        .get_account(obligation_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let obligation = Obligation::unpack(&obligation_account.data).unwrap();
+    assert_eq!(obligation.deposits.len(), MAX_EXTENDED_OBLIGATION_RESERVES);
+    assert!(!obligation.last_update.stale);
+}
@@ -0,0 +1,520 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        apply_lending_market_emode_group, propose_lending_market_emode_group, refresh_obligation,
+        set_lending_market_emode_group,
+    },
+    math::{Rate, TryMul},
+    processor::process_instruction,
+    state::{
+        pending_emode_group_address, EModeGroup, PendingEModeGroup, ReserveConfig,
+        INITIAL_COLLATERAL_RATIO, RESERVE_CONFIG_DELAY_SLOTS,
+    },
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const PROPOSAL_SLOT: u64 = 10;
+
+// group 1 is enabled when the lending market is created, group 2 is disabled
+const EMODE_GROUP: EModeGroup = EModeGroup {
+    loan_to_value_ratio: 90,
+    liquidation_threshold: 95,
+};
+
+fn setup(emode_groups: (u8, u8)) -> (ProgramTest, TestSolUsdcMarket) {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // the lending market owner pays the rent for pending groups
+    let market = add_sol_usdc_market(
+        &mut test,
+        AddSolUsdcMarketArgs {
+            emode_groups: &[(1, EMODE_GROUP)],
+            fund_lending_market_owner: true,
+            sol_reserve: AddReserveArgs {
+                collateral_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+                config: ReserveConfig {
+                    emode_group: emode_groups.0,
+                    ..test_reserve_config()
+                },
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+                liquidity_amount: 10 * USDC_BORROW_AMOUNT_FRACTIONAL,
+                config: ReserveConfig {
+                    emode_group: emode_groups.1,
+                    ..test_reserve_config()
+                },
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            sol_deposit_amount: SOL_DEPOSIT_AMOUNT_LAMPORTS,
+            usdc_borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+
+    (test, market)
+}
+
+async fn get_pending_emode_group(
+    banks_client: &mut BanksClient,
+    lending_market: &TestLendingMarket,
+    group: u8,
+) -> Option<PendingEModeGroup> {
+    let (pending_emode_group_pubkey, _bump_seed) =
+        pending_emode_group_address(&spl_token_lending::id(), &lending_market.pubkey, group);
+    banks_client
+        .get_account(pending_emode_group_pubkey)
+        .await
+        .unwrap()
+        .map(|account| PendingEModeGroup::unpack(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn test_success() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            test_obligation,
+            ..
+        },
+    ) = setup((1, 1));
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // lowering the loan to value ratio takes effect immediately
+    let emode_group = EModeGroup {
+        loan_to_value_ratio: 85,
+        ..EMODE_GROUP
+    };
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_lending_market_emode_group(
+                spl_token_lending::id(),
+                1,
+                emode_group,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![
+                    sol_test_reserve.pubkey,
+                    usdc_test_reserve.pubkey,
+                    lending_market.pubkey,
+                ],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let market = lending_market.get_state(&mut banks_client).await;
+    assert_eq!(market.emode_group(1), Some(&emode_group));
+    assert_eq!(market.emode_group(2), None);
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(85))
+            .unwrap()
+    );
+    assert_eq!(
+        obligation.unhealthy_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(95))
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_propose_emode_group() {
+    let (test, TestSolUsdcMarket { lending_market, .. }) = setup((2, 2));
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(PROPOSAL_SLOT).unwrap();
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_emode_group(
+            spl_token_lending::id(),
+            2,
+            EMODE_GROUP,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let pending_emode_group =
+        get_pending_emode_group(&mut test_context.banks_client, &lending_market, 2)
+            .await
+            .unwrap();
+    assert_eq!(pending_emode_group.lending_market, lending_market.pubkey);
+    assert_eq!(pending_emode_group.group, 2);
+    assert_eq!(pending_emode_group.config, EMODE_GROUP);
+    assert_eq!(
+        pending_emode_group.activation_slot,
+        PROPOSAL_SLOT + RESERVE_CONFIG_DELAY_SLOTS
+    );
+
+    // the group is unchanged until the proposal is applied
+    let market = lending_market
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(market.emode_group(2), None);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_lending_market_emode_group(
+            spl_token_lending::id(),
+            2,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], test_context.last_blockhash);
+    assert_eq!(
+        test_context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::EModeGroupNotActive as u32)
+        )
+    );
+
+    test_context
+        .warp_to_slot(PROPOSAL_SLOT + RESERVE_CONFIG_DELAY_SLOTS)
+        .unwrap();
+    let (pending_emode_group_pubkey, _bump_seed) =
+        pending_emode_group_address(&spl_token_lending::id(), &lending_market.pubkey, 2);
+    let owner_lamports = test_context
+        .banks_client
+        .get_balance(lending_market.owner.pubkey())
+        .await
+        .unwrap();
+    let pending_emode_group_lamports = test_context
+        .banks_client
+        .get_balance(pending_emode_group_pubkey)
+        .await
+        .unwrap();
+
+    // anyone can apply the group once it is active
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_lending_market_emode_group(
+            spl_token_lending::id(),
+            2,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = test_context
+        .banks_client
+        .get_recent_blockhash()
+        .await
+        .unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let market = lending_market
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(market.emode_group(2), Some(&EMODE_GROUP));
+    assert_eq!(
+        get_pending_emode_group(&mut test_context.banks_client, &lending_market, 2).await,
+        None
+    );
+    assert_eq!(
+        test_context
+            .banks_client
+            .get_balance(lending_market.owner.pubkey())
+            .await
+            .unwrap(),
+        owner_lamports + pending_emode_group_lamports
+    );
+}
+
+#[tokio::test]
+async fn test_fail_change_adds_risk() {
+    let (test, TestSolUsdcMarket { lending_market, .. }) = setup((1, 1));
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    for (group, emode_group) in [
+        // raising the loan to value ratio
+        (
+            1,
+            EModeGroup {
+                loan_to_value_ratio: 91,
+                ..EMODE_GROUP
+            },
+        ),
+        // lowering the liquidation threshold
+        (
+            1,
+            EModeGroup {
+                liquidation_threshold: 94,
+                ..EMODE_GROUP
+            },
+        ),
+        // disabling an enabled group
+        (1, EModeGroup::default()),
+        // enabling a disabled group
+        (2, EMODE_GROUP),
+    ] {
+        let mut transaction = Transaction::new_with_payer(
+            &[set_lending_market_emode_group(
+                spl_token_lending::id(),
+                group,
+                emode_group,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+        assert_eq!(
+            banks_client
+                .process_transaction(transaction)
+                .await
+                .unwrap_err()
+                .unwrap(),
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(LendingError::EModeGroupChangeNotAllowed as u32)
+            )
+        );
+    }
+
+    // only the lending market owner can propose a group
+    let not_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_lending_market_emode_group(
+            spl_token_lending::id(),
+            2,
+            EMODE_GROUP,
+            lending_market.pubkey,
+            not_owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &not_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidMarketOwner as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_mixed_groups_use_reserve_config() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            test_obligation,
+            ..
+        },
+    ) = setup((1, 2));
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![
+                sol_test_reserve.pubkey,
+                usdc_test_reserve.pubkey,
+                lending_market.pubkey,
+            ],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(
+                sol_test_reserve.config.loan_to_value_ratio
+            ))
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_group_below_reserve_config() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            test_obligation,
+            ..
+        },
+    ) = setup((1, 1));
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // deposits count at the higher of the group and reserve loan to value ratios
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_lending_market_emode_group(
+                spl_token_lending::id(),
+                1,
+                EModeGroup {
+                    loan_to_value_ratio: sol_test_reserve.config.loan_to_value_ratio - 10,
+                    ..EMODE_GROUP
+                },
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                vec![
+                    sol_test_reserve.pubkey,
+                    usdc_test_reserve.pubkey,
+                    lending_market.pubkey,
+                ],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(
+                sol_test_reserve.config.loan_to_value_ratio
+            ))
+            .unwrap()
+    );
+    assert_eq!(
+        obligation.unhealthy_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(EMODE_GROUP.liquidation_threshold))
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_missing_lending_market() {
+    let (
+        test,
+        TestSolUsdcMarket {
+            lending_market,
+            sol_test_reserve,
+            usdc_test_reserve,
+            test_obligation,
+            ..
+        },
+    ) = setup((1, 1));
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+
+    // group 0 is reserved for reserves without a group
+    let mut transaction = Transaction::new_with_payer(
+        &[set_lending_market_emode_group(
+            spl_token_lending::id(),
+            0,
+            EModeGroup {
+                loan_to_value_ratio: 90,
+                liquidation_threshold: 95,
+            },
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &lending_market.owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidConfig as u32)
+        )
+    );
+}
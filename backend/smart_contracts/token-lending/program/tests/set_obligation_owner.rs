@@ -0,0 +1,126 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::set_obligation_owner,
+    processor::process_instruction,
+    state::{obligation_address_with_seed, InitObligationParams, Obligation},
+};
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let new_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            true,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            new_owner.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &user_accounts_owner, &new_owner],
+        recent_blockhash,
+    );
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.owner, new_owner.pubkey());
+}
+
+#[tokio::test]
+async fn test_fail_new_owner_has_obligation() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let new_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs::default(),
+    );
+
+    let new_owner_obligation_pubkey = obligation_address_with_seed(
+        &new_owner.pubkey(),
+        &lending_market.pubkey,
+        &spl_token_lending::id(),
+    )
+    .unwrap();
+    test.add_packable_account(
+        new_owner_obligation_pubkey,
+        u32::MAX as u64,
+        &Obligation::new(InitObligationParams {
+            current_slot: 1,
+            lending_market: lending_market.pubkey,
+            owner: new_owner.pubkey(),
+            deposits: vec![],
+            borrows: vec![],
+        }),
+        &spl_token_lending::id(),
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_obligation_owner(
+            spl_token_lending::id(),
+            false,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            user_accounts_owner.pubkey(),
+            new_owner.pubkey(),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ObligationOwnerConflict as u32)
+        )
+    );
+}
@@ -0,0 +1,296 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        apply_reserve_config, propose_reserve_config, refresh_reserve_with_stake_pool_oracle,
+    },
+    math::Decimal,
+    processor::process_instruction,
+    stake_pool,
+    state::RESERVE_CONFIG_DELAY_SLOTS,
+};
+
+struct StakePoolOracleTest {
+    lending_market: TestLendingMarket,
+    sol_oracle: TestOracle,
+    pool_test_reserve: TestReserve,
+    stake_pool_pubkey: Pubkey,
+}
+
+fn setup(
+    test: &mut ProgramTest,
+    stake_pool_owner: Pubkey,
+    stake_pool_oracle_set: bool,
+) -> StakePoolOracleTest {
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(test);
+
+    // the lending market owner pays the rent for pending configs
+    add_funded_account(test, lending_market.owner.pubkey());
+
+    let pool_mint_pubkey = Pubkey::new_unique();
+    let stake_pool_pubkey = add_stake_pool(test, stake_pool_owner, pool_mint_pubkey, 0);
+
+    let sol_oracle = add_sol_oracle(test);
+    let pool_test_reserve = add_reserve(
+        test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: LAMPORTS_TO_SOL,
+            liquidity_mint_pubkey: pool_mint_pubkey,
+            liquidity_mint_decimals: 9,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            stake_pool_oracle_pubkey: if stake_pool_oracle_set {
+                stake_pool_pubkey
+            } else {
+                Pubkey::default()
+            },
+            ..AddReserveArgs::default()
+        },
+    );
+
+    StakePoolOracleTest {
+        lending_market,
+        sol_oracle,
+        pool_test_reserve,
+        stake_pool_pubkey,
+    }
+}
+
+fn refresh_stake_pool_oracle(test: &StakePoolOracleTest) -> Instruction {
+    refresh_reserve_with_stake_pool_oracle(
+        spl_token_lending::id(),
+        test.pool_test_reserve.pubkey,
+        test.pool_test_reserve.liquidity_pyth_oracle_pubkey,
+        test.pool_test_reserve.liquidity_switchboard_oracle_pubkey,
+        test.stake_pool_pubkey,
+    )
+}
+
+fn propose_stake_pool_oracle(test: &StakePoolOracleTest) -> Instruction {
+    propose_reserve_config(
+        spl_token_lending::id(),
+        test.pool_test_reserve.config,
+        test.pool_test_reserve.pubkey,
+        test.lending_market.pubkey,
+        test.lending_market.owner.pubkey(),
+        test.sol_oracle.pyth_product_pubkey,
+        test.pool_test_reserve.liquidity_pyth_oracle_pubkey,
+        test.pool_test_reserve.liquidity_switchboard_oracle_pubkey,
+        None,
+        Some(test.stake_pool_pubkey),
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let stake_pool_oracle_test = setup(&mut test, stake_pool::id(), true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_stake_pool_oracle(&stake_pool_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let pool_reserve = stake_pool_oracle_test
+        .pool_test_reserve
+        .get_state(&mut banks_client)
+        .await;
+    assert!(!pool_reserve.last_update.stale);
+
+    // 1.1 SOL at $20
+    assert_eq!(pool_reserve.liquidity.market_price, Decimal::from(22u64));
+}
+
+#[tokio::test]
+async fn test_propose_stake_pool_oracle() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let stake_pool_oracle_test = setup(&mut test, stake_pool::id(), false);
+
+    let mut test_context = test.start_with_context().await;
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_stake_pool_oracle(&stake_pool_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &stake_pool_oracle_test.lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the stake pool oracle only prices the reserve once the proposal is applied
+    let pool_reserve = stake_pool_oracle_test
+        .pool_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(
+        pool_reserve.liquidity.stake_pool_oracle_pubkey,
+        Pubkey::default()
+    );
+
+    test_context
+        .warp_to_slot(RESERVE_CONFIG_DELAY_SLOTS + 1)
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_reserve_config(
+            spl_token_lending::id(),
+            stake_pool_oracle_test.pool_test_reserve.pubkey,
+            stake_pool_oracle_test.lending_market.pubkey,
+            stake_pool_oracle_test.lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = test_context
+        .banks_client
+        .get_recent_blockhash()
+        .await
+        .unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let pool_reserve = stake_pool_oracle_test
+        .pool_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(
+        pool_reserve.liquidity.stake_pool_oracle_pubkey,
+        stake_pool_oracle_test.stake_pool_pubkey
+    );
+    assert!(pool_reserve.last_update.stale);
+}
+
+#[tokio::test]
+async fn test_fail_invalid_stake_pool_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let stake_pool_oracle_test = setup(&mut test, Pubkey::new_unique(), true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_stake_pool_oracle(&stake_pool_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let stake_pool_oracle_test = setup(&mut test, Pubkey::new_unique(), false);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_stake_pool_oracle(&stake_pool_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &stake_pool_oracle_test.lending_market.owner],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_fail_stale_stake_pool() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let stake_pool_oracle_test = setup(&mut test, stake_pool::id(), true);
+
+    let mut test_context = test.start_with_context().await;
+    test_context.warp_to_slot(1_000).unwrap(); // past the first epoch
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        last_blockhash: recent_blockhash,
+        ..
+    } = test_context;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_stake_pool_oracle(&stake_pool_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::StakePoolStale as u32)
+        )
+    );
+}
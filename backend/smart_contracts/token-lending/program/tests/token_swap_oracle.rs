@@ -0,0 +1,392 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program::{program_option::COption, pubkey::Pubkey};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token::state::Mint;
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        apply_reserve_config, propose_reserve_config, refresh_reserve_with_token_swap_oracle,
+    },
+    math::Decimal,
+    processor::process_instruction,
+    state::RESERVE_CONFIG_DELAY_SLOTS,
+    token_swap::{self, CONSTANT_PRODUCT_CURVE, SWAP_V1_LEN, SWAP_V1_VERSION},
+};
+
+// the pool holds $6,400 of SOL and $100 of USDC after being skewed by a large trade, which is
+// worth 2 * sqrt(6,400 * 100) = $1,600 at the oracle prices
+const SOL_POOL_AMOUNT_LAMPORTS: u64 = 320 * LAMPORTS_TO_SOL;
+const USDC_POOL_AMOUNT_FRACTIONAL: u64 = 100 * FRACTIONAL_TO_USDC;
+const POOL_TOKEN_DECIMALS: u8 = 6;
+const POOL_TOKEN_SUPPLY: u64 = 400 * 1_000_000;
+
+struct TokenSwapOracleTest {
+    market: TestSolUsdcMarket,
+    pool_test_reserve: TestReserve,
+    token_swap_pubkey: Pubkey,
+    token_a_pubkey: Pubkey,
+    token_b_pubkey: Pubkey,
+}
+
+fn add_token_swap(
+    test: &mut ProgramTest,
+    owner: Pubkey,
+    token_a_pubkey: Pubkey,
+    token_b_pubkey: Pubkey,
+    pool_mint_pubkey: Pubkey,
+    token_a_mint_pubkey: Pubkey,
+    token_b_mint_pubkey: Pubkey,
+) -> Pubkey {
+    let mut data = Vec::with_capacity(SWAP_V1_LEN);
+    data.extend_from_slice(&[SWAP_V1_VERSION, 1, 0]);
+    data.extend_from_slice(spl_token::id().as_ref());
+    data.extend_from_slice(token_a_pubkey.as_ref());
+    data.extend_from_slice(token_b_pubkey.as_ref());
+    data.extend_from_slice(pool_mint_pubkey.as_ref());
+    data.extend_from_slice(token_a_mint_pubkey.as_ref());
+    data.extend_from_slice(token_b_mint_pubkey.as_ref());
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(&[0; 64]);
+    data.push(CONSTANT_PRODUCT_CURVE);
+    data.extend_from_slice(&[0; 32]);
+
+    let token_swap_pubkey = Pubkey::new_unique();
+    test.add_account(
+        token_swap_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    token_swap_pubkey
+}
+
+fn setup(
+    test: &mut ProgramTest,
+    mark_underlying_fresh: bool,
+    token_swap_owner: Pubkey,
+    token_swap_oracle_set: bool,
+) -> TokenSwapOracleTest {
+    let sol_mint_pubkey = Pubkey::new_unique();
+    // the lending market owner pays the rent for pending configs
+    let market = add_sol_usdc_market(
+        test,
+        AddSolUsdcMarketArgs {
+            fund_lending_market_owner: true,
+            sol_reserve: AddReserveArgs {
+                liquidity_amount: LAMPORTS_TO_SOL,
+                liquidity_mint_pubkey: sol_mint_pubkey,
+                config: test_reserve_config(),
+                mark_fresh: mark_underlying_fresh,
+                ..AddReserveArgs::default()
+            },
+            usdc_reserve: AddReserveArgs {
+                liquidity_amount: FRACTIONAL_TO_USDC,
+                config: test_reserve_config(),
+                mark_fresh: true,
+                ..AddReserveArgs::default()
+            },
+            ..AddSolUsdcMarketArgs::default()
+        },
+    );
+    let usdc_mint_pubkey = market.usdc_test_reserve.liquidity_mint_pubkey;
+
+    let pool_mint_pubkey = Pubkey::new_unique();
+    test.add_packable_account(
+        pool_mint_pubkey,
+        u32::MAX as u64,
+        &Mint {
+            is_initialized: true,
+            mint_authority: COption::Some(Pubkey::new_unique()),
+            supply: POOL_TOKEN_SUPPLY,
+            decimals: POOL_TOKEN_DECIMALS,
+            ..Mint::default()
+        },
+        &spl_token::id(),
+    );
+
+    let token_swap_authority = Pubkey::new_unique();
+    let token_a_pubkey = add_account_for_program(
+        test,
+        &token_swap_authority,
+        SOL_POOL_AMOUNT_LAMPORTS,
+        &sol_mint_pubkey,
+    );
+    let token_b_pubkey = add_account_for_program(
+        test,
+        &token_swap_authority,
+        USDC_POOL_AMOUNT_FRACTIONAL,
+        &usdc_mint_pubkey,
+    );
+    let token_swap_pubkey = add_token_swap(
+        test,
+        token_swap_owner,
+        token_a_pubkey,
+        token_b_pubkey,
+        pool_mint_pubkey,
+        sol_mint_pubkey,
+        usdc_mint_pubkey,
+    );
+
+    let pool_test_reserve = add_reserve(
+        test,
+        &market.lending_market,
+        &market.usdc_oracle,
+        &market.user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 1_000_000,
+            liquidity_mint_pubkey: pool_mint_pubkey,
+            liquidity_mint_decimals: POOL_TOKEN_DECIMALS,
+            config: test_reserve_config(),
+            mark_fresh: true,
+            token_swap_oracle_pubkey: if token_swap_oracle_set {
+                token_swap_pubkey
+            } else {
+                Pubkey::default()
+            },
+            ..AddReserveArgs::default()
+        },
+    );
+
+    TokenSwapOracleTest {
+        market,
+        pool_test_reserve,
+        token_swap_pubkey,
+        token_a_pubkey,
+        token_b_pubkey,
+    }
+}
+
+fn refresh_token_swap_oracle(test: &TokenSwapOracleTest) -> Instruction {
+    refresh_reserve_with_token_swap_oracle(
+        spl_token_lending::id(),
+        test.pool_test_reserve.pubkey,
+        test.pool_test_reserve.liquidity_pyth_oracle_pubkey,
+        test.pool_test_reserve.liquidity_switchboard_oracle_pubkey,
+        test.token_swap_pubkey,
+        test.token_a_pubkey,
+        test.token_b_pubkey,
+        test.pool_test_reserve.liquidity_mint_pubkey,
+        test.market.sol_test_reserve.pubkey,
+        test.market.usdc_test_reserve.pubkey,
+    )
+}
+
+fn propose_token_swap_oracle(test: &TokenSwapOracleTest) -> Instruction {
+    propose_reserve_config(
+        spl_token_lending::id(),
+        test.pool_test_reserve.config,
+        test.pool_test_reserve.pubkey,
+        test.market.lending_market.pubkey,
+        test.market.lending_market.owner.pubkey(),
+        test.market.usdc_oracle.pyth_product_pubkey,
+        test.pool_test_reserve.liquidity_pyth_oracle_pubkey,
+        test.pool_test_reserve.liquidity_switchboard_oracle_pubkey,
+        Some(test.token_swap_pubkey),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let token_swap_oracle_test = setup(&mut test, true, token_swap::id(), true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_token_swap_oracle(&token_swap_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let pool_reserve = token_swap_oracle_test
+        .pool_test_reserve
+        .get_state(&mut banks_client)
+        .await;
+    assert!(!pool_reserve.last_update.stale);
+
+    // $1,600 over 400 pool tokens, rather than the $6,500 spot value of the pool balances
+    assert_eq!(pool_reserve.liquidity.market_price, Decimal::from(4u64));
+}
+
+#[tokio::test]
+async fn test_propose_token_swap_oracle() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let token_swap_oracle_test = setup(&mut test, true, token_swap::id(), false);
+
+    let mut test_context = test.start_with_context().await;
+    let payer = Keypair::from_bytes(&test_context.payer.to_bytes()).unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_token_swap_oracle(&token_swap_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &token_swap_oracle_test.market.lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // the token swap oracle only prices the reserve once the proposal is applied
+    let pool_reserve = token_swap_oracle_test
+        .pool_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(
+        pool_reserve.liquidity.token_swap_oracle_pubkey,
+        Pubkey::default()
+    );
+
+    test_context
+        .warp_to_slot(RESERVE_CONFIG_DELAY_SLOTS + 1)
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[apply_reserve_config(
+            spl_token_lending::id(),
+            token_swap_oracle_test.pool_test_reserve.pubkey,
+            token_swap_oracle_test.market.lending_market.pubkey,
+            token_swap_oracle_test.market.lending_market.owner.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash = test_context
+        .banks_client
+        .get_recent_blockhash()
+        .await
+        .unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    let pool_reserve = token_swap_oracle_test
+        .pool_test_reserve
+        .get_state(&mut test_context.banks_client)
+        .await;
+    assert_eq!(
+        pool_reserve.liquidity.token_swap_oracle_pubkey,
+        token_swap_oracle_test.token_swap_pubkey
+    );
+    assert!(pool_reserve.last_update.stale);
+}
+
+#[tokio::test]
+async fn test_fail_invalid_token_swap_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let token_swap_oracle_test = setup(&mut test, true, Pubkey::new_unique(), true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_token_swap_oracle(&token_swap_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let token_swap_oracle_test = setup(&mut test, true, Pubkey::new_unique(), false);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[propose_token_swap_oracle(&token_swap_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(
+        &[&payer, &token_swap_oracle_test.market.lending_market.owner],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_fail_stale_underlying_reserve() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let token_swap_oracle_test = setup(&mut test, false, token_swap::id(), true);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_token_swap_oracle(&token_swap_oracle_test)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::ReserveStale as u32)
+        )
+    );
+}
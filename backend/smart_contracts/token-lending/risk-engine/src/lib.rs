@@ -0,0 +1,894 @@
+#![deny(missing_docs)]
+
+//! Off-chain simulation of obligation health, using the lending program's own state and math
+
+use solana_program::{
+    clock::Slot, entrypoint::ProgramResult, program_error::ProgramError, program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_token_lending::{
+    error::LendingError,
+    math::{Decimal, Rate, TryAdd, TryDiv, TryMul, TrySub},
+    state::{
+        CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult, EModeGroup,
+        LendingMarket, Obligation, Reserve, SLOTS_PER_YEAR,
+    },
+};
+use std::collections::BTreeMap;
+
+/// Action applied to an obligation and its reserves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Deposit liquidity into a reserve and its collateral into the obligation
+    Deposit {
+        /// Deposit reserve
+        reserve: Pubkey,
+        /// Amount of liquidity to deposit
+        liquidity_amount: u64,
+    },
+    /// Withdraw collateral from the obligation and redeem it for liquidity, u64::MAX for up to
+    /// 100% of the collateral the obligation can withdraw
+    Withdraw {
+        /// Withdraw reserve
+        reserve: Pubkey,
+        /// Amount of collateral to withdraw
+        collateral_amount: u64,
+    },
+    /// Borrow liquidity, u64::MAX for up to 100% of the remaining borrow value
+    Borrow {
+        /// Borrow reserve
+        reserve: Pubkey,
+        /// Amount of liquidity to receive
+        liquidity_amount: u64,
+    },
+    /// Repay borrowed liquidity, u64::MAX for 100% of the borrowed amount
+    Repay {
+        /// Repay reserve
+        reserve: Pubkey,
+        /// Amount of liquidity to repay
+        liquidity_amount: u64,
+    },
+    /// Liquidate the obligation, u64::MAX for up to 100% of the liquidatable amount
+    Liquidate {
+        /// Reserve of the repaid borrow
+        repay_reserve: Pubkey,
+        /// Reserve of the withdrawn collateral
+        withdraw_reserve: Pubkey,
+        /// Amount of liquidity to repay
+        liquidity_amount: u64,
+    },
+    /// Advance the clock, accruing interest on every reserve
+    Elapse {
+        /// Number of slots to advance
+        slots: u64,
+    },
+}
+
+/// Obligation health after refreshing it at the current slot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    /// Market value of deposits
+    pub deposited_value: Decimal,
+    /// Market value of borrows, weighted by the borrow weight of each borrow reserve
+    pub borrowed_value: Decimal,
+    /// The maximum borrow value at the weighted average loan to value ratio
+    pub allowed_borrow_value: Decimal,
+    /// The dangerous borrow value at the weighted average liquidation threshold
+    pub unhealthy_borrow_value: Decimal,
+    /// Ratio of the unhealthy borrow value to the borrowed value, None without borrows. The
+    /// obligation can be liquidated when this falls to or below one.
+    pub health_factor: Option<Decimal>,
+    /// True if the obligation can be liquidated
+    pub liquidatable: bool,
+}
+
+/// Annualized reserve rates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReserveApys {
+    /// Current borrow rate, before compounding
+    pub borrow_apr: Rate,
+    /// Borrow rate compounded every slot for a year
+    pub borrow_apy: Rate,
+    /// Yield earned by collateral holders, the borrow APY times the utilization rate
+    pub supply_apy: Rate,
+}
+
+/// Simulates actions on an obligation against snapshots of its reserves
+#[derive(Clone, Debug)]
+pub struct RiskEngine {
+    /// Simulated obligation, refreshed after every action
+    pub obligation: Obligation,
+    /// Simulated reserves by address
+    pub reserves: BTreeMap<Pubkey, Reserve>,
+    /// Current slot
+    pub slot: Slot,
+    /// Lending market of the obligation, used for its efficiency mode groups. Without groups
+    /// unless set.
+    pub lending_market: LendingMarket,
+}
+
+impl RiskEngine {
+    /// Create a risk engine, accruing reserve interest up to `slot` and refreshing the
+    /// obligation. Every reserve the obligation deposits into or borrows from must be provided.
+    pub fn new(
+        obligation: Obligation,
+        reserves: BTreeMap<Pubkey, Reserve>,
+        slot: Slot,
+    ) -> Result<Self, ProgramError> {
+        let mut engine = Self {
+            obligation,
+            reserves,
+            slot,
+            lending_market: LendingMarket::default(),
+        };
+        engine.refresh()?;
+        Ok(engine)
+    }
+
+    /// Create a risk engine from fetched obligation and reserve account data
+    pub fn unpack(
+        obligation_data: &[u8],
+        reserve_accounts: &[(Pubkey, &[u8])],
+        slot: Slot,
+    ) -> Result<Self, ProgramError> {
+        let obligation = Obligation::unpack(obligation_data)?;
+        let reserves = reserve_accounts
+            .iter()
+            .map(|(pubkey, data)| Ok((*pubkey, Reserve::unpack(data)?)))
+            .collect::<Result<_, ProgramError>>()?;
+        Self::new(obligation, reserves, slot)
+    }
+
+    /// Apply an action, failing with the error the lending program would return
+    pub fn simulate(&mut self, action: Action) -> ProgramResult {
+        match action {
+            Action::Deposit {
+                reserve,
+                liquidity_amount,
+            } => self.deposit(reserve, liquidity_amount),
+            Action::Withdraw {
+                reserve,
+                collateral_amount,
+            } => self.withdraw(reserve, collateral_amount).map(|_| ()),
+            Action::Borrow {
+                reserve,
+                liquidity_amount,
+            } => self.borrow(reserve, liquidity_amount).map(|_| ()),
+            Action::Repay {
+                reserve,
+                liquidity_amount,
+            } => self.repay(reserve, liquidity_amount),
+            Action::Liquidate {
+                repay_reserve,
+                withdraw_reserve,
+                liquidity_amount,
+            } => self.liquidate(repay_reserve, withdraw_reserve, liquidity_amount),
+            Action::Elapse { slots } => {
+                self.slot = self
+                    .slot
+                    .checked_add(slots)
+                    .ok_or(LendingError::MathOverflow)?;
+                Ok(())
+            }
+        }?;
+        self.refresh()
+    }
+
+    /// Use the efficiency mode groups of the obligation's lending market and refresh the
+    /// obligation with them
+    pub fn set_lending_market(&mut self, lending_market: &LendingMarket) -> ProgramResult {
+        self.lending_market = lending_market.clone();
+        self.refresh()
+    }
+
+    /// Set the market price of a reserve's liquidity and refresh the obligation at the new price
+    pub fn set_market_price(&mut self, reserve: &Pubkey, market_price: Decimal) -> ProgramResult {
+        self.reserve_mut(reserve)?.liquidity.market_price = market_price;
+        self.refresh()
+    }
+
+    /// Current obligation health
+    pub fn health(&self) -> Result<Health, ProgramError> {
+        let obligation = &self.obligation;
+        let health_factor = if obligation.borrowed_value == Decimal::zero() {
+            None
+        } else {
+            Some(
+                obligation
+                    .unhealthy_borrow_value
+                    .try_div(obligation.borrowed_value)?,
+            )
+        };
+
+        Ok(Health {
+            deposited_value: obligation.deposited_value,
+            borrowed_value: obligation.borrowed_value,
+            allowed_borrow_value: obligation.allowed_borrow_value,
+            unhealthy_borrow_value: obligation.unhealthy_borrow_value,
+            health_factor,
+            liquidatable: obligation.borrowed_value > Decimal::zero()
+                && obligation.borrowed_value >= obligation.unhealthy_borrow_value,
+        })
+    }
+
+    /// Max liquidity that can currently be received by borrowing from a reserve, after fees
+    pub fn max_borrow(&self, reserve: &Pubkey) -> Result<u64, ProgramError> {
+        if self.obligation.remaining_borrow_value()? == Decimal::zero() {
+            return Ok(0);
+        }
+        let CalculateBorrowResult { receive_amount, .. } =
+            self.clone().borrow(*reserve, u64::MAX)?;
+        Ok(receive_amount)
+    }
+
+    /// Price of a deposit reserve's liquidity at which the obligation becomes liquidatable, with
+    /// every other price held constant. None if no positive price makes it liquidatable.
+    pub fn liquidation_price(
+        &self,
+        deposit_reserve: &Pubkey,
+    ) -> Result<Option<Decimal>, ProgramError> {
+        let reserve = self.reserve(deposit_reserve)?;
+        let (collateral, _) = self
+            .obligation
+            .find_collateral_in_deposits(*deposit_reserve)?;
+        let decimals = mint_decimals(reserve)?;
+        let (_, liquidation_threshold_rate) =
+            reserve.collateral_rates(self.slot, self.emode_group()?.as_ref())?;
+
+        // value of the deposits and borrows that do not move with the price
+        let other_unhealthy_borrow_value = self.obligation.unhealthy_borrow_value.try_sub(
+            collateral
+                .market_value
+                .try_mul(liquidation_threshold_rate)?,
+        )?;
+        let mut other_borrowed_value = self.obligation.borrowed_value;
+
+        // unhealthy borrow value and borrowed value per unit of price
+        let unhealthy_per_price = reserve
+            .collateral_exchange_rate()?
+            .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?
+            .try_div(decimals)?
+            .try_mul(liquidation_threshold_rate)?;
+        let mut borrowed_per_price = Decimal::zero();
+        if let Ok((liquidity, _)) = self.obligation.find_liquidity_in_borrows(*deposit_reserve) {
+            let borrow_weight = reserve.config.borrow_weight()?;
+            other_borrowed_value =
+                other_borrowed_value.try_sub(liquidity.market_value.try_mul(borrow_weight)?)?;
+            borrowed_per_price = liquidity
+                .borrowed_amount_wads
+                .try_div(decimals)?
+                .try_mul(borrow_weight)?;
+        }
+
+        // solve other_borrowed + price * borrowed_per_price
+        //     = other_unhealthy + price * unhealthy_per_price
+        let price = if unhealthy_per_price > borrowed_per_price
+            && other_borrowed_value > other_unhealthy_borrow_value
+        {
+            other_borrowed_value
+                .try_sub(other_unhealthy_borrow_value)?
+                .try_div(unhealthy_per_price.try_sub(borrowed_per_price)?)?
+        } else if borrowed_per_price > unhealthy_per_price
+            && other_unhealthy_borrow_value > other_borrowed_value
+        {
+            other_unhealthy_borrow_value
+                .try_sub(other_borrowed_value)?
+                .try_div(borrowed_per_price.try_sub(unhealthy_per_price)?)?
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(price))
+    }
+
+    /// Current annualized rates of a reserve
+    pub fn reserve_apys(&self, reserve: &Pubkey) -> Result<ReserveApys, ProgramError> {
+        let reserve = self.reserve(reserve)?;
+        let borrow_apr = reserve.current_borrow_rate()?;
+        let borrow_apy = Rate::one()
+            .try_add(borrow_apr.try_div(SLOTS_PER_YEAR)?)?
+            .try_pow(SLOTS_PER_YEAR)?
+            .try_sub(Rate::one())?;
+        let supply_apy = borrow_apy.try_mul(reserve.liquidity.utilization_rate()?)?;
+
+        Ok(ReserveApys {
+            borrow_apr,
+            borrow_apy,
+            supply_apy,
+        })
+    }
+
+    fn reserve(&self, pubkey: &Pubkey) -> Result<&Reserve, ProgramError> {
+        self.reserves
+            .get(pubkey)
+            .ok_or_else(|| LendingError::InvalidAccountInput.into())
+    }
+
+    fn reserve_mut(&mut self, pubkey: &Pubkey) -> Result<&mut Reserve, ProgramError> {
+        self.reserves
+            .get_mut(pubkey)
+            .ok_or_else(|| LendingError::InvalidAccountInput.into())
+    }
+
+    fn deposit(&mut self, reserve_pubkey: Pubkey, liquidity_amount: u64) -> ProgramResult {
+        if liquidity_amount == 0 {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        let reserve = self.reserve_mut(&reserve_pubkey)?;
+        if Decimal::from(liquidity_amount)
+            .try_add(reserve.liquidity.total_supply()?)?
+            .try_floor_u64()?
+            > reserve.config.deposit_limit
+        {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        if reserve.config.loan_to_value_ratio == 0 || reserve.config.borrow_only {
+            return Err(LendingError::ReserveCollateralDisabled.into());
+        }
+        if reserve.deprecated {
+            return Err(LendingError::ReserveDeprecated.into());
+        }
+
+        let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+        self.obligation
+            .find_or_add_collateral_to_deposits(reserve_pubkey)?
+            .deposit(collateral_amount)
+    }
+
+    fn withdraw(
+        &mut self,
+        reserve_pubkey: Pubkey,
+        collateral_amount: u64,
+    ) -> Result<u64, ProgramError> {
+        if collateral_amount == 0 {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        let (collateral, collateral_index) = self
+            .obligation
+            .find_collateral_in_deposits(reserve_pubkey)?;
+        if collateral.deposited_amount == 0 {
+            return Err(LendingError::ObligationCollateralEmpty.into());
+        }
+
+        let withdraw_amount = if self.obligation.borrows.is_empty() {
+            collateral.deposited_amount.min(collateral_amount)
+        } else if self.obligation.deposited_value == Decimal::zero() {
+            return Err(LendingError::ObligationDepositsZero.into());
+        } else {
+            let max_withdraw_value = self.obligation.max_withdraw_value()?;
+            if max_withdraw_value == Decimal::zero() {
+                return Err(LendingError::WithdrawTooLarge.into());
+            }
+
+            let withdraw_amount = if collateral_amount == u64::MAX {
+                let withdraw_value = max_withdraw_value.min(collateral.market_value);
+                let withdraw_pct = withdraw_value.try_div(collateral.market_value)?;
+                withdraw_pct
+                    .try_mul(collateral.deposited_amount)?
+                    .try_floor_u64()?
+                    .min(collateral.deposited_amount)
+            } else {
+                let withdraw_amount = collateral_amount.min(collateral.deposited_amount);
+                let withdraw_pct =
+                    Decimal::from(withdraw_amount).try_div(collateral.deposited_amount)?;
+                let withdraw_value = collateral.market_value.try_mul(withdraw_pct)?;
+                if withdraw_value > max_withdraw_value {
+                    return Err(LendingError::WithdrawTooLarge.into());
+                }
+                withdraw_amount
+            };
+            if withdraw_amount == 0 {
+                return Err(LendingError::WithdrawTooSmall.into());
+            }
+            withdraw_amount
+        };
+
+        self.obligation
+            .withdraw(withdraw_amount, collateral_index)?;
+        self.reserve_mut(&reserve_pubkey)?
+            .redeem_collateral(withdraw_amount)
+    }
+
+    fn borrow(
+        &mut self,
+        reserve_pubkey: Pubkey,
+        liquidity_amount: u64,
+    ) -> Result<CalculateBorrowResult, ProgramError> {
+        if liquidity_amount == 0 {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        if self.obligation.deposits.is_empty() {
+            return Err(LendingError::ObligationDepositsEmpty.into());
+        }
+        if self.obligation.deposited_value == Decimal::zero() {
+            return Err(LendingError::ObligationDepositsZero.into());
+        }
+
+        let reserve = self.reserve(&reserve_pubkey)?;
+        if reserve.deprecated {
+            return Err(LendingError::ReserveDeprecated.into());
+        }
+        if reserve.config.isolated
+            && self
+                .obligation
+                .borrows
+                .iter()
+                .any(|liquidity| liquidity.borrow_reserve != reserve_pubkey)
+        {
+            return Err(LendingError::IsolatedReserveViolation.into());
+        }
+        if !reserve.config.isolated && self.obligation.borrowing_isolated_asset {
+            return Err(LendingError::IsolatedReserveViolation.into());
+        }
+
+        let remaining_borrow_value = self.obligation.remaining_borrow_value()?;
+        if remaining_borrow_value == Decimal::zero() {
+            return Err(LendingError::BorrowTooLarge.into());
+        }
+        let remaining_reserve_capacity = Decimal::from(reserve.config.borrow_limit)
+            .try_sub(reserve.liquidity.total_borrowed()?)
+            .unwrap_or_else(|_| Decimal::zero());
+
+        let result = reserve.calculate_borrow(
+            liquidity_amount,
+            remaining_borrow_value,
+            remaining_reserve_capacity,
+        )?;
+        if result.receive_amount == 0 {
+            return Err(LendingError::BorrowTooSmall.into());
+        }
+
+        let cumulative_borrow_rate_wads = reserve.liquidity.cumulative_borrow_rate_wads;
+        let isolated = reserve.config.isolated;
+        self.reserve_mut(&reserve_pubkey)?
+            .liquidity
+            .borrow(result.borrow_amount)?;
+        self.obligation
+            .find_or_add_liquidity_to_borrows(reserve_pubkey, cumulative_borrow_rate_wads)?
+            .borrow(result.borrow_amount)?;
+        if isolated {
+            self.obligation.borrowing_isolated_asset = true;
+        }
+
+        Ok(result)
+    }
+
+    fn repay(&mut self, reserve_pubkey: Pubkey, liquidity_amount: u64) -> ProgramResult {
+        if liquidity_amount == 0 {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        let (liquidity, liquidity_index) =
+            self.obligation.find_liquidity_in_borrows(reserve_pubkey)?;
+        if liquidity.borrowed_amount_wads == Decimal::zero() {
+            return Err(LendingError::ObligationLiquidityEmpty.into());
+        }
+
+        let liquidity = liquidity.clone();
+        let reserve = self.reserve_mut(&reserve_pubkey)?;
+        let CalculateRepayResult {
+            settle_amount,
+            repay_amount,
+        } = reserve.calculate_repay(liquidity_amount, liquidity.borrowed_amount_wads)?;
+        if repay_amount == 0 {
+            return Err(LendingError::RepayTooSmall.into());
+        }
+
+        reserve
+            .liquidity
+            .repay_obligation_liquidity(&liquidity, repay_amount, settle_amount)?;
+        self.obligation.repay(settle_amount, liquidity_index)
+    }
+
+    fn liquidate(
+        &mut self,
+        repay_reserve_pubkey: Pubkey,
+        withdraw_reserve_pubkey: Pubkey,
+        liquidity_amount: u64,
+    ) -> ProgramResult {
+        if liquidity_amount == 0 {
+            return Err(LendingError::InvalidAmount.into());
+        }
+        if self.obligation.deposited_value == Decimal::zero() {
+            return Err(LendingError::ObligationDepositsZero.into());
+        }
+        if self.obligation.borrowed_value == Decimal::zero() {
+            return Err(LendingError::ObligationBorrowsZero.into());
+        }
+        if self.obligation.borrowed_value < self.obligation.unhealthy_borrow_value {
+            return Err(LendingError::ObligationHealthy.into());
+        }
+
+        let (liquidity, liquidity_index) = self
+            .obligation
+            .find_liquidity_in_borrows(repay_reserve_pubkey)?;
+        if liquidity.market_value == Decimal::zero() {
+            return Err(LendingError::ObligationLiquidityEmpty.into());
+        }
+        let (collateral, collateral_index) = self
+            .obligation
+            .find_collateral_in_deposits(withdraw_reserve_pubkey)?;
+        if collateral.market_value == Decimal::zero() {
+            return Err(LendingError::ObligationCollateralEmpty.into());
+        }
+
+        let CalculateLiquidationResult {
+            settle_amount,
+            repay_amount,
+            withdraw_amount,
+        } = self
+            .reserve(&withdraw_reserve_pubkey)?
+            .calculate_liquidation(
+                liquidity_amount,
+                &self.obligation,
+                liquidity,
+                collateral,
+                self.reserve(&repay_reserve_pubkey)?
+                    .config
+                    .borrow_weight()?,
+            )?;
+        if repay_amount == 0 {
+            return Err(LendingError::LiquidationTooSmall.into());
+        }
+        if withdraw_amount == 0 {
+            return Err(LendingError::LiquidationTooSmall.into());
+        }
+
+        let liquidity = liquidity.clone();
+        self.reserve_mut(&repay_reserve_pubkey)?
+            .liquidity
+            .repay_obligation_liquidity(&liquidity, repay_amount, settle_amount)?;
+        self.obligation.repay(settle_amount, liquidity_index)?;
+        self.obligation.withdraw(withdraw_amount, collateral_index)
+    }
+
+    /// Accrue interest on every reserve and recompute the obligation's market values, as
+    /// `RefreshReserve` and `RefreshObligation` would at the current slot
+    /// Enabled efficiency mode group shared by every deposit and borrow reserve of the obligation
+    fn emode_group(&self) -> Result<Option<EModeGroup>, ProgramError> {
+        let mut group = None;
+        for reserve in self
+            .obligation
+            .deposits
+            .iter()
+            .map(|collateral| &collateral.deposit_reserve)
+            .chain(
+                self.obligation
+                    .borrows
+                    .iter()
+                    .map(|liquidity| &liquidity.borrow_reserve),
+            )
+        {
+            let reserve_group = self.reserve(reserve)?.config.emode_group;
+            if group.is_some_and(|group| group != reserve_group) {
+                return Ok(None);
+            }
+            group = Some(reserve_group);
+        }
+        Ok(group.and_then(|group| self.lending_market.emode_group(group).copied()))
+    }
+
+    fn refresh(&mut self) -> ProgramResult {
+        for reserve in self.reserves.values_mut() {
+            let slots_elapsed = reserve.last_update.slots_elapsed(self.slot)?;
+            reserve
+                .liquidity
+                .update_smoothed_market_price(slots_elapsed)?;
+            reserve.accrue_interest(self.slot)?;
+            reserve.last_update.update_slot(self.slot);
+        }
+
+        let emode_group = self.emode_group()?;
+        let mut deposited_value = Decimal::zero();
+        let mut borrowed_value = Decimal::zero();
+        let mut borrowed_value_upper_bound = Decimal::zero();
+        let mut allowed_borrow_value = Decimal::zero();
+        let mut unhealthy_borrow_value = Decimal::zero();
+        let mut borrowing_isolated_asset = false;
+
+        for collateral in self.obligation.deposits.iter_mut() {
+            let deposit_reserve = self
+                .reserves
+                .get(&collateral.deposit_reserve)
+                .ok_or(LendingError::InvalidAccountInput)?;
+
+            let liquidity_amount = deposit_reserve
+                .collateral_exchange_rate()?
+                .decimal_collateral_to_liquidity(collateral.deposited_amount.into())?;
+            let market_value = deposit_reserve.liquidity.market_value(liquidity_amount)?;
+            let market_value_lower_bound = deposit_reserve
+                .liquidity
+                .market_value_lower_bound(liquidity_amount)?;
+            collateral.market_value = market_value;
+
+            let (loan_to_value_rate, liquidation_threshold_rate) =
+                deposit_reserve.collateral_rates(self.slot, emode_group.as_ref())?;
+            deposited_value = deposited_value.try_add(market_value)?;
+            allowed_borrow_value = allowed_borrow_value
+                .try_add(market_value_lower_bound.try_mul(loan_to_value_rate)?)?;
+            unhealthy_borrow_value = unhealthy_borrow_value
+                .try_add(market_value.try_mul(liquidation_threshold_rate)?)?;
+        }
+
+        for liquidity in self.obligation.borrows.iter_mut() {
+            let borrow_reserve = self
+                .reserves
+                .get_mut(&liquidity.borrow_reserve)
+                .ok_or(LendingError::InvalidAccountInput)?;
+
+            if liquidity.is_fixed_rate() && self.slot >= liquidity.maturity_slot {
+                borrow_reserve.settle_fixed_borrow(liquidity, self.slot)?;
+            } else if liquidity.is_fixed_rate() {
+                liquidity.accrue_fixed_interest(self.slot)?;
+            } else {
+                liquidity.accrue_interest(borrow_reserve.liquidity.cumulative_borrow_rate_wads)?;
+            }
+
+            let borrow_weight = borrow_reserve.config.borrow_weight()?;
+            let market_value = borrow_reserve
+                .liquidity
+                .market_value(liquidity.borrowed_amount_wads)?;
+            liquidity.market_value = market_value;
+
+            borrowed_value = borrowed_value.try_add(market_value.try_mul(borrow_weight)?)?;
+            borrowed_value_upper_bound = borrowed_value_upper_bound.try_add(
+                borrow_reserve
+                    .liquidity
+                    .market_value_upper_bound(liquidity.borrowed_amount_wads)?
+                    .try_mul(borrow_weight)?,
+            )?;
+            if borrow_reserve.config.isolated {
+                borrowing_isolated_asset = true;
+            }
+        }
+
+        self.obligation.deposited_value = deposited_value;
+        self.obligation.borrowed_value = borrowed_value;
+        self.obligation.borrowed_value_upper_bound = borrowed_value_upper_bound;
+        self.obligation.allowed_borrow_value = allowed_borrow_value;
+        self.obligation.unhealthy_borrow_value = unhealthy_borrow_value;
+        self.obligation.borrowing_isolated_asset = borrowing_isolated_asset;
+        self.obligation.last_update.update_slot(self.slot);
+
+        Ok(())
+    }
+}
+
+fn mint_decimals(reserve: &Reserve) -> Result<u64, ProgramError> {
+    10u64
+        .checked_pow(reserve.liquidity.mint_decimals as u32)
+        .ok_or_else(|| LendingError::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use spl_token_lending::state::{
+        InitObligationParams, InitReserveParams, NewReserveCollateralParams,
+        NewReserveLiquidityParams, ReserveCollateral, ReserveConfig, ReserveLiquidity,
+    };
+
+    const LAMPORTS_TO_SOL: u64 = 1_000_000_000;
+    const FRACTIONAL_TO_USDC: u64 = 1_000_000;
+
+    fn reserve(mint_decimals: u8, market_price: u64, liquidity_amount: u64) -> Reserve {
+        let mut reserve = Reserve::new(InitReserveParams {
+            current_slot: 0,
+            lending_market: Pubkey::default(),
+            liquidity: ReserveLiquidity::new(NewReserveLiquidityParams {
+                mint_pubkey: Pubkey::new_unique(),
+                mint_decimals,
+                supply_pubkey: Pubkey::new_unique(),
+                pyth_oracle_pubkey: Pubkey::new_unique(),
+                switchboard_oracle_pubkey: Pubkey::new_unique(),
+                market_price: Decimal::from(market_price),
+            }),
+            collateral: ReserveCollateral::new(NewReserveCollateralParams {
+                mint_pubkey: Pubkey::new_unique(),
+                supply_pubkey: Pubkey::new_unique(),
+            }),
+            config: ReserveConfig {
+                optimal_utilization_rate: 80,
+                loan_to_value_ratio: 50,
+                liquidation_bonus: 5,
+                liquidation_threshold: 55,
+                min_borrow_rate: 0,
+                optimal_borrow_rate: 4,
+                max_borrow_rate: 30,
+                deposit_limit: u64::MAX,
+                borrow_limit: u64::MAX,
+                ..ReserveConfig::default()
+            },
+        });
+        reserve.deposit_liquidity(liquidity_amount).unwrap();
+        reserve
+    }
+
+    // 10 SOL at $20 deposited and 50 USDC borrowed
+    fn setup() -> (RiskEngine, Pubkey, Pubkey) {
+        let sol_reserve = Pubkey::new_unique();
+        let usdc_reserve = Pubkey::new_unique();
+        let mut reserves = BTreeMap::new();
+        reserves.insert(sol_reserve, reserve(9, 20, 100 * LAMPORTS_TO_SOL));
+        reserves.insert(usdc_reserve, reserve(6, 1, 10_000 * FRACTIONAL_TO_USDC));
+        let obligation = Obligation::new(InitObligationParams {
+            current_slot: 0,
+            lending_market: Pubkey::default(),
+            owner: Pubkey::new_unique(),
+            deposits: vec![],
+            borrows: vec![],
+        });
+
+        let mut engine = RiskEngine::new(obligation, reserves, 0).unwrap();
+        engine
+            .simulate(Action::Deposit {
+                reserve: sol_reserve,
+                liquidity_amount: 10 * LAMPORTS_TO_SOL,
+            })
+            .unwrap();
+        engine
+            .simulate(Action::Borrow {
+                reserve: usdc_reserve,
+                liquidity_amount: 50 * FRACTIONAL_TO_USDC,
+            })
+            .unwrap();
+        (engine, sol_reserve, usdc_reserve)
+    }
+
+    #[test]
+    fn simulate_health() {
+        let (engine, sol_reserve, usdc_reserve) = setup();
+
+        let health = engine.health().unwrap();
+        assert_eq!(health.deposited_value, Decimal::from(200u64));
+        assert_eq!(health.borrowed_value, Decimal::from(50u64));
+        assert_eq!(health.allowed_borrow_value, Decimal::from(100u64));
+        assert_eq!(health.unhealthy_borrow_value, Decimal::from(110u64));
+        assert_eq!(
+            health.health_factor,
+            Some(Decimal::from(110u64).try_div(50u64).unwrap())
+        );
+        assert!(!health.liquidatable);
+
+        assert_eq!(
+            engine.max_borrow(&usdc_reserve).unwrap(),
+            50 * FRACTIONAL_TO_USDC
+        );
+
+        // $50 of borrows against 10 SOL at a 55% liquidation threshold
+        assert_eq!(
+            engine.liquidation_price(&sol_reserve).unwrap(),
+            Some(
+                Decimal::from(50u64)
+                    .try_div(
+                        Decimal::from(10u64)
+                            .try_mul(Rate::from_percent(55))
+                            .unwrap()
+                    )
+                    .unwrap()
+            )
+        );
+
+        let mut borrow_too_large = engine.clone();
+        assert_eq!(
+            borrow_too_large.simulate(Action::Borrow {
+                reserve: usdc_reserve,
+                liquidity_amount: 51 * FRACTIONAL_TO_USDC,
+            }),
+            Err(LendingError::BorrowTooLarge.into())
+        );
+
+        let mut withdraw_max = engine;
+        withdraw_max
+            .simulate(Action::Withdraw {
+                reserve: sol_reserve,
+                collateral_amount: u64::MAX,
+            })
+            .unwrap();
+        assert_eq!(
+            withdraw_max.health().unwrap().deposited_value,
+            Decimal::from(100u64)
+        );
+    }
+
+    #[test]
+    fn simulate_interest_and_liquidation() {
+        let (mut engine, sol_reserve, usdc_reserve) = setup();
+
+        let apys = engine.reserve_apys(&usdc_reserve).unwrap();
+        assert!(apys.borrow_apy > apys.borrow_apr);
+        assert!(apys.supply_apy < apys.borrow_apy);
+
+        let initial_health = engine.health().unwrap();
+        engine
+            .simulate(Action::Elapse {
+                slots: SLOTS_PER_YEAR,
+            })
+            .unwrap();
+        let health = engine.health().unwrap();
+        assert!(health.borrowed_value > initial_health.borrowed_value);
+        assert!(health.health_factor < initial_health.health_factor);
+
+        assert_eq!(
+            engine.simulate(Action::Liquidate {
+                repay_reserve: usdc_reserve,
+                withdraw_reserve: sol_reserve,
+                liquidity_amount: u64::MAX,
+            }),
+            Err(LendingError::ObligationHealthy.into())
+        );
+
+        // slightly unhealthy, so only part of the borrow can be liquidated
+        engine
+            .set_market_price(&sol_reserve, Decimal::from(9u64))
+            .unwrap();
+        assert!(engine.health().unwrap().liquidatable);
+
+        let borrowed_amount_wads = engine.obligation.borrows[0].borrowed_amount_wads;
+        engine
+            .simulate(Action::Liquidate {
+                repay_reserve: usdc_reserve,
+                withdraw_reserve: sol_reserve,
+                liquidity_amount: u64::MAX,
+            })
+            .unwrap();
+        assert!(engine.obligation.borrows[0].borrowed_amount_wads < borrowed_amount_wads);
+        assert!(engine.obligation.deposits[0].deposited_amount < 10 * LAMPORTS_TO_SOL);
+    }
+
+    #[test]
+    fn simulate_emode_group() {
+        let (mut engine, sol_reserve, usdc_reserve) = setup();
+        let mut lending_market = LendingMarket::default();
+        lending_market.emode_groups[0] = EModeGroup {
+            loan_to_value_ratio: 80,
+            liquidation_threshold: 90,
+        };
+
+        // the group only applies once every reserve belongs to it
+        engine.reserve_mut(&sol_reserve).unwrap().config.emode_group = 1;
+        engine.set_lending_market(&lending_market).unwrap();
+        assert_eq!(
+            engine.health().unwrap().allowed_borrow_value,
+            Decimal::from(100u64)
+        );
+
+        engine
+            .reserve_mut(&usdc_reserve)
+            .unwrap()
+            .config
+            .emode_group = 1;
+        engine.set_lending_market(&lending_market).unwrap();
+        let health = engine.health().unwrap();
+        assert_eq!(health.allowed_borrow_value, Decimal::from(160u64));
+        assert_eq!(health.unhealthy_borrow_value, Decimal::from(180u64));
+        assert_eq!(
+            engine.liquidation_price(&sol_reserve).unwrap(),
+            Some(Decimal::from(50u64).try_div(Decimal::from(9u64)).unwrap())
+        );
+
+        // a group below the reserve rates does not lower them
+        lending_market.emode_groups[0] = EModeGroup {
+            loan_to_value_ratio: 40,
+            liquidation_threshold: 50,
+        };
+        engine.set_lending_market(&lending_market).unwrap();
+        let health = engine.health().unwrap();
+        assert_eq!(health.allowed_borrow_value, Decimal::from(100u64));
+        assert_eq!(health.unhealthy_borrow_value, Decimal::from(110u64));
+
+        // deposits with collateral disabled for borrowing don't count at the group rate
+        lending_market.emode_groups[0] = EModeGroup {
+            loan_to_value_ratio: 80,
+            liquidation_threshold: 90,
+        };
+        engine
+            .reserve_mut(&sol_reserve)
+            .unwrap()
+            .config
+            .loan_to_value_ratio = 0;
+        engine.set_lending_market(&lending_market).unwrap();
+        let health = engine.health().unwrap();
+        assert_eq!(health.allowed_borrow_value, Decimal::zero());
+        assert_eq!(health.unhealthy_borrow_value, Decimal::from(110u64));
+    }
+}